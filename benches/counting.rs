@@ -0,0 +1,95 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rs_wc::{
+    counter::{count_bytes, count_reader},
+    parser::CountMode,
+};
+
+// Counts every allocation so the benchmarks can report how many a count makes
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const MODES: [CountMode; 4] = [CountMode::Lines, CountMode::Words, CountMode::Bytes, CountMode::Chars];
+const SIZES: [usize; 3] = [64 * 1024, 4 * 1024 * 1024, 64 * 1024 * 1024];
+
+fn sample_text(size: usize) -> Vec<u8> {
+    "The quick brown fox jumps over the lazy dog, naïvely — twice.\n"
+        .bytes()
+        .cycle()
+        .take(size)
+        .collect()
+}
+
+// Prints the allocations made by one run of `count`
+fn report_allocations(name: &str, size: usize, count: impl FnOnce()) {
+    let (allocations, bytes) = (ALLOCATIONS.load(Ordering::Relaxed), ALLOCATED_BYTES.load(Ordering::Relaxed));
+    count();
+    println!(
+        "{}/{}: {} allocations, {} bytes allocated",
+        name,
+        size,
+        ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        ALLOCATED_BYTES.load(Ordering::Relaxed) - bytes,
+    );
+}
+
+fn bench_count_bytes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("count_bytes");
+    for size in SIZES {
+        let text = sample_text(size);
+        report_allocations("count_bytes", size, || {
+            black_box(count_bytes(&text, None, &MODES).unwrap());
+        });
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &text, |b, text| {
+            b.iter(|| count_bytes(black_box(text), None, &MODES).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_count_reader(c: &mut Criterion) {
+    let mut group = c.benchmark_group("count_reader");
+    for size in SIZES {
+        let text = sample_text(size);
+        report_allocations("count_reader", size, || {
+            black_box(count_reader(&text[..], None, &MODES).unwrap());
+        });
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &text, |b, text| {
+            b.iter(|| count_reader(black_box(&text[..]), None, &MODES).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_count_bytes, bench_count_reader);
+criterion_main!(benches);
@@ -1 +1,3 @@
-pub mod parser;
\ No newline at end of file
+pub mod escape;
+pub mod parser;
+pub mod size;
\ No newline at end of file
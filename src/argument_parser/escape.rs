@@ -0,0 +1,102 @@
+use std::str::FromStr;
+
+use crate::error::{WcError, WcResult};
+
+/// A non-empty byte string given on the command line with escapes decoded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EscapedBytes(pub Vec<u8>);
+
+impl FromStr for EscapedBytes {
+    type Err = WcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = unescape(s)?;
+        if bytes.is_empty() {
+            return Err(WcError::invalid_argument("expected at least one byte"));
+        }
+        Ok(EscapedBytes(bytes))
+    }
+}
+
+/// Decodes backslash escapes (`\n`, `\t`, `\r`, `\0`, `\\`, `\xHH`) into raw
+/// bytes; every other character is taken literally as UTF-8.
+pub fn unescape(input: &str) -> WcResult<Vec<u8>> {
+    let invalid = |reason: &str| WcError::invalid_argument(format!("invalid escape in '{}': {}", input, reason));
+    let mut bytes = Vec::with_capacity(input.len());
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some('r') => bytes.push(b'\r'),
+            Some('0') => bytes.push(0),
+            Some('\\') => bytes.push(b'\\'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                let byte = u8::from_str_radix(&hex, 16)
+                    .ok()
+                    .filter(|_| hex.len() == 2)
+                    .ok_or_else(|| invalid("\\x needs two hex digits"))?;
+                bytes.push(byte);
+            }
+            Some(other) => return Err(invalid(&format!("unknown escape '\\{}'", other))),
+            None => return Err(invalid("trailing backslash")),
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// Parses a single delimiter byte given literally (`;`), as an escape
+/// (`\x1e`, `\0`) or in hex (`0x1e`).
+pub fn parse_delimiter(input: &str) -> WcResult<u8> {
+    if let Some(hex) = input.strip_prefix("0x").or_else(|| input.strip_prefix("0X")) {
+        return u8::from_str_radix(hex, 16)
+            .map_err(|_| WcError::invalid_argument(format!("invalid delimiter '{}'", input)));
+    }
+
+    match unescape(input)?.as_slice() {
+        [byte] => Ok(*byte),
+        _ => Err(WcError::invalid_argument(format!(
+            "invalid delimiter '{}': must be a single byte", input
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod escape_tests {
+    use super::*;
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape(r"a\tb\n").unwrap(), b"a\tb\n");
+        assert_eq!(unescape(r"\x1e\0\\").unwrap(), [0x1e, 0, b'\\']);
+        assert_eq!(unescape("</record>").unwrap(), b"</record>");
+        assert!(unescape(r"\q").is_err());
+        assert!(unescape(r"\x1").is_err());
+        assert!(unescape("\\").is_err());
+    }
+
+    #[test]
+    fn test_escaped_bytes() {
+        assert_eq!("\\t,".parse::<EscapedBytes>().unwrap(), EscapedBytes(b"\t,".to_vec()));
+        assert!("".parse::<EscapedBytes>().is_err());
+    }
+
+    #[test]
+    fn test_parse_delimiter() {
+        assert_eq!(parse_delimiter(";").unwrap(), b';');
+        assert_eq!(parse_delimiter(r"\x1e").unwrap(), 0x1e);
+        assert_eq!(parse_delimiter("0x1E").unwrap(), 0x1e);
+        assert_eq!(parse_delimiter(r"\0").unwrap(), 0);
+        assert!(parse_delimiter(";;").is_err());
+        assert!(parse_delimiter("é").is_err());
+    }
+}
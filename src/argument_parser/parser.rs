@@ -1,5 +1,24 @@
-use clap::{Parser, ValueEnum};
-use std::path::PathBuf;
+use clap::{error::ErrorKind, ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use regex::bytes::Regex;
+use std::ffi::OsString;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::comments::Language;
+use crate::counter::CountOptions;
+use crate::destination::Destination;
+use crate::encoding::InputEncoding;
+use crate::error::{WcError, WcResult};
+use crate::escape::{parse_delimiter, EscapedBytes};
+use crate::file_list::ListSeparator;
+use crate::group::GroupBy;
+use crate::hash::HashAlgorithm;
+use crate::matches::LineFilter;
+use crate::printer::{FormatOptions, Totals};
+use crate::size::{parse_byte_range, parse_line_range, parse_percent, parse_size, InputRange};
+use crate::threshold::{Metric, Threshold};
+use crate::walker::{PathFilter, WalkOptions};
 
 #[derive(Debug, Clone, ValueEnum, PartialEq)]
 pub enum CountMode {
@@ -9,11 +28,213 @@ pub enum CountMode {
     Chars,
 }
 
+/// What to do with a byte order mark at the start of an input.
+#[derive(Debug, Clone, Copy, Default, ValueEnum, PartialEq)]
+pub enum BomPolicy {
+    /// Leave it out of every count but bytes, and decode UTF-16 input it marks
+    Strip,
+    /// Count it like any other character
+    #[default]
+    Count,
+    /// Fail on inputs that start with one
+    Error,
+}
+
+/// How malformed UTF-8 sequences count towards `-m`.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+pub enum InvalidUtf8 {
+    /// One character per malformed sequence, as U+FFFD would be
+    Replace,
+    /// Malformed sequences count as nothing
+    Skip,
+    /// Fail on the first malformed sequence
+    Error,
+    /// Any malformed sequence makes the character count the byte count
+    Bytes,
+}
+
+/// A wc implementation whose counting and output `--compat` reproduces.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+pub enum Compat {
+    /// GNU coreutils: columns padded to a shared width, invalid UTF-8 not counted by -m
+    Gnu,
+    /// The POSIX format: counts separated by single spaces, ASCII whitespace between words
+    Posix,
+    /// BSD and macOS: every count in an 8-character column, ASCII whitespace between words
+    Bsd,
+}
+
+/// Which lines `--min-line-length` measures.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+pub enum MinLineScope {
+    /// Every line, so any empty line makes the minimum 0
+    All,
+    /// Lines with at least one character
+    NonEmpty,
+}
+
+/// Which control characters `--control-chars` counts.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+pub enum ControlScope {
+    /// All but newlines and tabs, which plain text is expected to have
+    Text,
+    /// Every C0 and C1 control character
+    All,
+}
+
+/// What `-m` counts as one character.
+#[derive(Debug, Clone, Copy, Default, ValueEnum, PartialEq)]
+pub enum CharUnit {
+    /// Every byte, as wc does in the C locale
+    Bytes,
+    /// Unicode code points
+    #[default]
+    Codepoints,
+    /// User-perceived characters (extended grapheme clusters), so "e" plus a
+    /// combining accent, or a flag emoji, is one
+    Graphemes,
+}
+
+/// Line ending convention a file is expected to follow.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+pub enum LineEndingStyle {
+    Lf,
+    Crlf,
+}
+
+/// How filenames are shown in output.
+#[derive(Debug, Clone, Copy, Default, ValueEnum, PartialEq)]
+pub enum FilenameStyle {
+    /// As given on the command line or found while recursing
+    #[default]
+    Path,
+    /// Canonical absolute path
+    Absolute,
+    /// Final path component only
+    Basename,
+    /// No filename at all
+    None,
+}
+
+/// When `--warn` highlights counts in color.
+#[derive(Debug, Clone, Copy, Default, ValueEnum, PartialEq)]
+pub enum ColorChoice {
+    /// When writing to a terminal and NO_COLOR isn't set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// A summary row printed alongside the total.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+pub enum Aggregate {
+    Max,
+    Min,
+    Mean,
+    Median,
+}
+
 #[derive(Debug, Clone, ValueEnum)]
 pub enum OutputFormat {
     Plain,
     Human,
     Json,
+    /// One compact JSON object per line
+    Ndjson,
+    /// Prometheus text exposition format, e.g. for a node_exporter textfile
+    Prometheus,
+    /// The JSON document as MessagePack (needs the `msgpack` feature)
+    Msgpack,
+    /// The JSON document as CBOR (needs the `cbor` feature)
+    Cbor,
+    /// One row per file with a typed column per metric (needs the `parquet`
+    /// feature)
+    Parquet,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Compare the counts of two inputs, printing per-metric deltas
+    Diff {
+        /// Baseline input
+        old: PathBuf,
+
+        /// Input compared against the baseline
+        new: PathBuf,
+    },
+
+    /// Record counts to a snapshot, or check current counts against one
+    Snapshot {
+        /// Write the counts of FILES to this snapshot
+        #[arg(long, value_name = "SNAPSHOT", conflicts_with = "check", required_unless_present = "check")]
+        save: Option<PathBuf>,
+
+        /// Compare counts against this snapshot, failing if any file grew
+        #[arg(long, value_name = "SNAPSHOT")]
+        check: Option<PathBuf>,
+
+        /// Allowed change in percent before a file is reported
+        #[arg(long, value_name = "PERCENT", default_value = "0", value_parser = parse_percent)]
+        tolerance: f64,
+
+        /// Files to record or check (defaults to the files in the snapshot when checking)
+        #[arg(value_name = "FILE", required_unless_present = "check")]
+        files: Vec<PathBuf>,
+    },
+
+    /// Score how easy FILES are to read (Flesch reading ease, Flesch-Kincaid
+    /// grade, Gunning fog), from their sentence, word and syllable counts
+    Readability {
+        /// Files to score
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<PathBuf>,
+    },
+
+    /// List the lines of FILES longer than the limit, failing if there are any
+    Lint {
+        /// Longest line allowed, measured as -L does; with --editorconfig,
+        /// only for files whose .editorconfig sets no max_line_length
+        // Its own id and name, as the global -L is already --max-line-length
+        #[arg(id = "lint_max_line_length", long = "limit", value_name = "N", required_unless_present = "editorconfig")]
+        limit: Option<usize>,
+
+        /// Files to check
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<PathBuf>,
+    },
+
+    /// Count the lines and words each file gains and loses between two
+    /// revisions, from `git diff`
+    GitDiff {
+        /// Revisions to compare, e.g. main..HEAD, or one revision to compare
+        /// the working tree against
+        #[arg(value_name = "RANGE")]
+        range: String,
+
+        /// Only count changes to these paths
+        #[arg(value_name = "PATH")]
+        paths: Vec<PathBuf>,
+    },
+
+    /// Serve counts as JSON over HTTP at /counts and /counts/<file>
+    Serve {
+        /// Address to listen on
+        #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+        listen: String,
+
+        /// Files or directories to serve counts for
+        #[arg(value_name = "PATH", required = true)]
+        paths: Vec<PathBuf>,
+    },
+
+    /// Answer newline-delimited JSON count requests on a Unix socket
+    #[cfg(unix)]
+    Daemon {
+        /// Path of the socket to listen on
+        #[arg(long, value_name = "PATH")]
+        socket: PathBuf,
+    },
 }
 
 #[derive(Parser, Debug)]
@@ -24,37 +245,427 @@ pub enum OutputFormat {
     long_about = "Counts lines, words, bytes, and characters in files or stdin.",
 )]
 pub struct Cli {
-    /// Print the new line counts
-    #[arg(short = 'l', long)]
+    /// Print the line counts; unlike wc, an unterminated final line counts
+    /// too (--compat counts only newlines)
+    #[arg(short = 'l', long, global = true)]
     pub lines: bool,
     
     /// Print the word counts
-    #[arg(short = 'w', long)]
+    #[arg(short = 'w', long, global = true)]
     pub words: bool,
     
     /// Print the byte counts
-    #[arg(short = 'c', long)]
+    #[arg(short = 'c', long, global = true)]
     pub bytes: bool,
     
-    /// Print the character counts
-    #[arg(short = 'm', long)]
-    pub chars: bool,
+    /// Print the character counts, counting UNIT as a character (default: codepoints)
+    #[arg(
+        short = 'm',
+        long,
+        value_name = "UNIT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "codepoints",
+        global = true,
+    )]
+    pub chars: Option<CharUnit>,
     
     /// Print maximum line length
-    #[arg(short = 'L', long)]
+    #[arg(short = 'L', long, global = true)]
     pub max_line_length: bool,
     
+    /// Treat NUL, not newline, as the line terminator (for find -print0 style input)
+    #[arg(short = 'z', long, global = true, conflicts_with = "line_delimiter")]
+    pub zero_terminated: bool,
+
+    /// Byte that ends a line, e.g. ';', '\x1e' or 0x1e (default newline)
+    #[arg(long, value_name = "BYTE", value_parser = parse_delimiter, global = true)]
+    pub line_delimiter: Option<u8>,
+
+    /// Split words on these bytes instead of whitespace, e.g. ',;|' or '\t'
+    #[arg(long, value_name = "BYTES", global = true)]
+    pub word_delimiters: Option<EscapedBytes>,
+
+    /// Also count occurrences of a record separator, e.g. '\n\n' or '</record>'
+    #[arg(long = "records", value_name = "SEP", global = true)]
+    pub record_separator: Option<EscapedBytes>,
+
+    /// Also count matches of a regular expression, and the lines they are on
+    #[arg(long, value_name = "PATTERN", value_parser = parse_regex, global = true)]
+    pub count_matches: Option<Regex>,
+
+    /// Count only lines matching a regular expression; bytes still cover the
+    /// whole input
+    #[arg(long, value_name = "PATTERN", value_parser = parse_regex, global = true)]
+    pub filter: Option<Regex>,
+
+    /// Leave the first N lines of each input, such as a CSV header, out of
+    /// every count but bytes
+    #[arg(long, value_name = "N", default_value_t = 0, global = true)]
+    pub skip_lines: usize,
+
+    /// Leave the last N lines of each input out of every count but bytes
+    #[arg(long, value_name = "N", default_value_t = 0, global = true)]
+    pub skip_footer: usize,
+
+    /// Count only lines START:END of each input, from zero and excluding
+    /// END, e.g. 100:200 or :1000
+    #[arg(long, value_name = "START:END", value_parser = parse_line_range, global = true)]
+    pub lines_range: Option<InputRange>,
+
+    /// Count only bytes START:END of each input, from zero and excluding
+    /// END, e.g. 0:1M; files are read no further than END
+    #[arg(long, value_name = "START:END", value_parser = parse_byte_range, global = true)]
+    pub bytes_range: Option<InputRange>,
+
+    /// Count only lines that don't match the --filter pattern
+    #[arg(long, requires = "filter", global = true)]
+    pub invert_filter: bool,
+
+    /// Also parse input as CSV, counting records, fields and empty cells
+    #[arg(long, global = true)]
+    pub csv: bool,
+
+    /// Also parse input as JSON, counting keys, array elements, depth and string chars
+    #[arg(long, global = true)]
+    pub json_stats: bool,
+
+    /// Report the longest line's number, and its text cut to WIDTH chars if given
+    #[arg(long, value_name = "WIDTH", num_args = 0..=1, require_equals = true, global = true)]
+    pub show_longest: Option<Option<usize>>,
+
+    /// Report mean, median and standard deviation of line lengths
+    #[arg(long, global = true)]
+    pub line_stats: bool,
+
+    /// Print the display width of every line instead of counts, e.g. to plot
+    /// line lengths; wide characters take two columns and tabs reach the next stop
+    #[arg(long, conflicts_with_all = ["stream", "group_by"])]
+    pub per_line: bool,
+
+    /// After the counts, chart each file's line lengths (and all files'
+    /// together) in BUCKETS ranges, 10 by default
+    #[arg(long, value_name = "BUCKETS", num_args = 0..=1, require_equals = true,
+          default_missing_value = "10", conflicts_with_all = ["stream", "per_line"])]
+    pub histogram: Option<usize>,
+
+    /// Number the lines printed by --per-line
+    #[arg(short = 'n', long, requires = "per_line")]
+    pub line_numbers: bool,
+
+    /// Report empty lines and, separately, lines with only whitespace on them
+    #[arg(long, global = true)]
+    pub blank_lines: bool,
+
+    /// Report how many lines are indented with tabs, spaces or both, and the
+    /// most common indent width
+    #[arg(long, global = true)]
+    pub indent_stats: bool,
+
+    /// Report line length percentiles, e.g. 50,90,99 (estimated within 1%)
+    #[arg(long, value_name = "LIST", value_delimiter = ',', value_parser = parse_percentile, global = true)]
+    pub percentiles: Vec<f64>,
+
+    /// Report byte entropy (bits per byte) and whether the input looks binary
+    #[arg(long, global = true)]
+    pub entropy: bool,
+
+    /// Report the length of the shortest line, optionally leaving out empty lines
+    #[arg(long, value_name = "LINES", num_args = 0..=1, require_equals = true,
+          default_missing_value = "all", global = true)]
+    pub min_line_length: Option<MinLineScope>,
+
+    /// Report the length in characters of the longest word, e.g. to spot
+    /// minified lines or overlong identifiers
+    #[arg(long, global = true)]
+    pub max_word_length: bool,
+
+    /// Report the type-token ratio: distinct words (ignoring case and
+    /// surrounding punctuation) over all words
+    #[arg(long, global = true)]
+    pub ttr: bool,
+
+    /// Report the longest word and its length, per file and over all files,
+    /// showing up to WIDTH chars of it (default 40)
+    #[arg(long, value_name = "WIDTH", num_args = 0..=1, require_equals = true,
+          default_missing_value = "40", global = true)]
+    pub show_longest_word: Option<usize>,
+
+    /// Read input as a unified diff (e.g. from `git diff`) and count the
+    /// lines and words it adds and removes, and the files it touches
+    #[arg(long, global = true)]
+    pub diff_input: bool,
+
+    /// Read input as HTML and count only the text a browser shows, without
+    /// tags, comments, scripts or styles; byte counts stay raw
+    #[arg(long, global = true)]
+    pub html: bool,
+
+    /// Count only the text of comments, read with the comment syntax of each
+    /// file's extension, or of LANG for every input; files in other languages
+    /// count as empty, and byte counts stay raw
+    #[arg(long, value_name = "LANG", num_args = 0..=1, require_equals = true,
+          conflicts_with = "html", global = true)]
+    pub comments_only: Option<Option<Language>>,
+
+    /// Count emoji, each ZWJ family, skin-tone variant, flag or keycap as one
+    #[arg(long, global = true)]
+    pub emoji: bool,
+
+    /// Count C0 and C1 control characters, by default other than newlines and tabs
+    #[arg(long, value_name = "WHICH", num_args = 0..=1, require_equals = true,
+          default_missing_value = "text", global = true)]
+    pub control_chars: Option<ControlScope>,
+
+    /// Also hash each input's content, in the same pass as counting
+    #[arg(long, value_name = "ALGORITHM", global = true)]
+    pub hash: Option<HashAlgorithm>,
+
+    /// Decode input from this encoding (e.g. latin1, shift_jis, auto) before
+    /// counting chars and words; byte counts stay raw
+    #[arg(long, value_name = "NAME", global = true)]
+    pub encoding: Option<InputEncoding>,
+
+    /// How to treat a UTF-8 or UTF-16 byte order mark
+    #[arg(long, value_name = "POLICY", default_value = "count", global = true)]
+    pub bom: BomPolicy,
+
+    /// How -m counts malformed UTF-8; also reports how many sequences were invalid
+    #[arg(long, value_name = "POLICY", global = true)]
+    pub invalid_utf8: Option<InvalidUtf8>,
+
+    /// Split words on ASCII whitespace only, leaving Unicode spaces (e.g. NBSP) inside words
+    #[arg(long, global = true)]
+    pub posix: bool,
+
+    /// Count and print exactly like FLAVOR's wc: lines are newlines, and chars come before bytes
+    #[arg(long, value_name = "FLAVOR", global = true)]
+    pub compat: Option<Compat>,
+
+    /// Count LF, CRLF and lone CR line endings separately
+    #[arg(long, global = true)]
+    pub line_endings: bool,
+
     /// Print all counts (lines, words, bytes)
-    #[arg(short = 'a', long)]
+    #[arg(short = 'a', long, global = true)]
     pub all: bool,
     
+    /// Print only the counts: no filenames and no total
+    #[arg(short = 'q', long, global = true)]
+    pub quiet: bool,
+
+    /// Separate thousands in plain and human counts, with SEP if given
+    #[arg(
+        long,
+        value_name = "SEP",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ",",
+        global = true
+    )]
+    pub group_digits: Option<String>,
+
+    /// Print each file's row as soon as it is counted, in order of completion
+    #[arg(long, conflicts_with_all = ["percent", "group_by"])]
+    pub stream: bool,
+
+    /// Show each file's share of the total for the first count column
+    #[arg(long, global = true)]
+    pub percent: bool,
+
+    /// Show how long each file took to count, and the whole run in the total
+    /// row, which is then shown even for one file
+    #[arg(long, global = true)]
+    pub time: bool,
+
+    /// When the arguments were parsed, which the --time total is measured from
+    #[arg(skip = Instant::now())]
+    pub started: Instant,
+
+    /// Write output to FILE instead of stdout, replacing it only once the
+    /// output is complete
+    #[arg(short = 'o', long, value_name = "FILE", global = true)]
+    pub output: Option<PathBuf>,
+
+    /// Add to the end of the --output file instead of replacing it
+    #[arg(long, requires = "output", global = true)]
+    pub append: bool,
+
+    /// Also record each file's counts in the SQLite database DB, as a new
+    /// run of its `counts` table (needs the `sqlite` feature)
+    #[arg(long, value_name = "DB")]
+    pub output_sqlite: Option<PathBuf>,
+
+    /// Leave out the total row, in every output format
+    #[arg(long, global = true)]
+    pub no_total: bool,
+
+    /// Show filenames as given (path), canonical (absolute), file name only (basename) or not at all (none)
+    #[arg(long, value_name = "STYLE", default_value = "path", global = true)]
+    pub filename_style: FilenameStyle,
+
+    /// Add summary rows after the total, e.g. max,min,mean
+    #[arg(long, value_name = "LIST", value_delimiter = ',', global = true)]
+    pub aggregate: Vec<Aggregate>,
+
+    /// Add min, max, mean and median rows after the total, across all files
+    #[arg(long, global = true)]
+    pub summary: bool,
+
+    /// Like --summary, but leave out the per-file rows
+    #[arg(long, conflicts_with = "stream")]
+    pub summary_only: bool,
+
     /// Print output format (plain, human, json)
-    #[arg(short = 'f', long, default_value = "plain")]
+    #[arg(short = 'f', long, default_value = "plain", global = true)]
     pub format: OutputFormat,
+
+    /// With --format json, print the bare array of results instead of an
+    /// object that also records the schema version, rs-wc version and run
+    #[arg(long, global = true)]
+    pub json_array: bool,
+
+    /// With --format json, print minified JSON on a single line
+    #[arg(long, global = true)]
+    pub compact: bool,
     
+    /// Print one aggregated row per group instead of per file (ext, dir[=DEPTH])
+    #[arg(long, value_name = "KEY")]
+    pub group_by: Option<GroupBy>,
+
+    /// Exit non-zero when any file or the total matches EXPR (e.g. 'lines>1000')
+    #[arg(long = "fail-if", value_name = "EXPR")]
+    pub fail_if: Vec<Threshold>,
+
+    /// Take line length limits from each file's .editorconfig: `lint` checks
+    /// max_line_length and indent_style, and -L highlights longer files
+    #[arg(long, global = true)]
+    pub editorconfig: bool,
+
+    /// Highlight counts matching EXPR (e.g. 'lines>500') in plain and human
+    /// output, without failing the run
+    #[arg(long, value_name = "EXPR")]
+    pub warn: Vec<Threshold>,
+
+    /// Color --warn highlights (auto, always, never); without color they're
+    /// marked with a trailing '!'
+    #[arg(long, value_name = "WHEN", default_value = "auto")]
+    pub color: ColorChoice,
+
+    /// Exit non-zero, listing offenders, when a file has line endings other than STYLE
+    #[arg(long, value_name = "STYLE")]
+    pub check_line_endings: Option<LineEndingStyle>,
+
+    /// Report files whose last line has no terminating newline
+    #[arg(long)]
+    pub check_final_newline: bool,
+
+    /// Like --check-final-newline, but also exit non-zero when any are found
+    #[arg(long)]
+    pub fail_on_missing_newline: bool,
+
+    /// Report how many lines end in spaces or tabs
+    #[arg(long, global = true)]
+    pub trailing_whitespace: bool,
+
+    /// Like --trailing-whitespace, but also exit non-zero, listing the files, when any are found
+    #[arg(long)]
+    pub fail_on_trailing_whitespace: bool,
+
+    /// Count and locate bidi controls, zero-width and other invisible
+    /// characters, exiting non-zero when any are found
+    #[arg(long)]
+    pub scan_unicode: bool,
+
+    /// Limit the bytes memory-mapped at once across files (e.g. 512M, 2GiB)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub max_mapped: Option<u64>,
+
+    /// Keep at most SIZE of input in memory at once, reading larger inputs
+    /// piece by piece (e.g. 64M)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub max_memory: Option<u64>,
+
+    /// Count PDF and DOCX files' bytes rather than their text (text is only
+    /// extracted when rs-wc is built with `--features pdf` or `docx`)
+    #[arg(long, global = true)]
+    pub raw: bool,
+
+    /// Read files instead of memory-mapping them (automatic on NFS and FUSE)
+    #[arg(long)]
+    pub no_mmap: bool,
+
+    /// With only -c, read files instead of trusting their size (for sparse or growing files)
+    #[arg(long)]
+    pub force_read: bool,
+
+    /// Count the files beneath directory arguments
+    #[arg(short = 'r', long)]
+    pub recursive: bool,
+
+    /// With -r, only count files matching GLOB (later --include/--exclude win)
+    #[arg(long, value_name = "GLOB")]
+    pub include: Vec<String>,
+
+    /// With -r, skip files matching GLOB (later --include/--exclude win)
+    #[arg(long, value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// With -r, also count files excluded by .gitignore, .ignore and global excludes
+    #[arg(long)]
+    pub no_ignore: bool,
+
+    /// With -r, descend at most N directory levels (1 = immediate children)
+    #[arg(long, value_name = "N")]
+    pub max_depth: Option<usize>,
+
+    /// Follow symbolic links in arguments and while recursing (skipped otherwise)
+    #[arg(long)]
+    pub follow_symlinks: bool,
+
+    /// With -r, skip files that look binary (NUL bytes or mostly invalid UTF-8)
+    #[arg(long)]
+    pub text_only: bool,
+
+    /// Count each file once when it is named more than once or also found by -r
+    #[arg(long)]
+    pub dedup_inputs: bool,
+
+    /// Leave files whose content matches an earlier file's out of the total,
+    /// marking them as duplicates (hashes with blake3 unless --hash is given)
+    #[arg(long)]
+    pub dedup_content: bool,
+
+    /// --include and --exclude merged in command-line order
+    #[arg(skip)]
+    pub path_filters: Vec<PathFilter>,
+
+    /// Read from stdin without the hint shown when it is a terminal
+    #[arg(long)]
+    pub stdin: bool,
+
+    /// Log each file's progress and timings to stderr (-vv for each step)
+    #[arg(short = 'v', long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+
+    /// Browse live counts in an interactive table (needs the `tui` feature)
+    #[arg(long)]
+    pub tui: bool,
+
+    /// Count the files listed in FILE, one per line ('-' reads the list from stdin)
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["files", "files0_from"])]
+    pub files_from: Option<PathBuf>,
+
+    /// Count the files listed in FILE, separated by NUL bytes ('-' reads the list from stdin)
+    #[arg(long, value_name = "FILE", conflicts_with = "files")]
+    pub files0_from: Option<PathBuf>,
+
     /// Input files (read from stdin if none specified)
     #[arg(value_name = "FILE", default_value = "-")]
     pub files: Vec<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
 }
 
 impl Default for Cli {
@@ -64,6 +675,130 @@ impl Default for Cli {
 }
 
 impl Cli {
+    /// Parses the process arguments. Prefer this over `Cli::parse`, which
+    /// can't see the relative order of `--include` and `--exclude`.
+    pub fn parse_args() -> Self {
+        Self::parse_args_from(std::env::args_os())
+    }
+
+    pub fn parse_args_from<I, T>(args: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let matches = Self::command().get_matches_from(args);
+        let mut cli = Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        if cli.compact && !matches!(cli.format, OutputFormat::Json) {
+            Self::command().error(ErrorKind::ArgumentConflict, "--compact only applies to --format json").exit();
+        }
+        cli.path_filters = ordered_filters(&matches);
+        cli
+    }
+
+    pub fn walk_options(&self) -> WalkOptions {
+        WalkOptions {
+            recursive: self.recursive,
+            filters: self.path_filters.clone(),
+            no_ignore: self.no_ignore,
+            max_depth: self.max_depth,
+            follow_symlinks: self.follow_symlinks,
+            text_only: self.text_only,
+            dedup_inputs: self.dedup_inputs,
+        }
+    }
+
+    // --aggregate rows, then any --summary rows not already asked for
+    fn aggregates(&self) -> Vec<Aggregate> {
+        let mut aggregates = self.aggregate.clone();
+        if self.summary || self.summary_only {
+            for kind in [Aggregate::Min, Aggregate::Max, Aggregate::Mean, Aggregate::Median] {
+                if !aggregates.contains(&kind) {
+                    aggregates.push(kind);
+                }
+            }
+        }
+        aggregates
+    }
+
+    pub fn format_options(&self) -> FormatOptions {
+        // `-L` on its own shows only the max line length
+        let only_max_line_length = self.max_line_length && !self.lines && !self.words
+            && !self.bytes && self.chars.is_none() && !self.all;
+
+        FormatOptions {
+            format: self.format.clone(),
+            modes: if only_max_line_length { Vec::new() } else { self.get_count_modes() },
+            max_line_length: self.max_line_length,
+            listed_modes: only_max_line_length.then(|| self.get_count_modes()),
+            totals: if self.no_total { Totals::Never } else { Totals::Auto },
+            aggregates: self.aggregates(),
+            summary_only: self.summary_only,
+            quiet: self.quiet,
+            filename_style: self.filename_style,
+            group_digits: self.group_digits.clone(),
+            compat: self.compat,
+            percent: self.percent,
+            percentiles: self.percentiles.clone(),
+            min_line_length: self.min_line_length.is_some(),
+            line_endings: self.line_endings,
+            invalid_utf8: self.invalid_utf8.is_some(),
+            hash: self.hash,
+            dedup_content: self.dedup_content,
+            time: self.time,
+            started: self.started,
+            json_array: self.json_array,
+            compact: self.compact,
+            append: self.append,
+            line_numbers: self.line_numbers,
+            line_stats: self.line_stats,
+            histogram: self.histogram,
+            warn: self.warn.clone(),
+            color: self.uses_color(),
+        }
+    }
+
+    // Whether --color, and where output goes, allow escape codes
+    fn uses_color(&self) -> bool {
+        match self.color {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => self.output.is_none()
+                && std::env::var_os("NO_COLOR").is_none()
+                && io::stdout().is_terminal(),
+        }
+    }
+
+    /// Where output goes: the --output file, or stdout.
+    pub fn destination(&self) -> WcResult<Destination> {
+        match &self.output {
+            Some(path) => Destination::open(path, self.append),
+            None => Ok(Destination::stdout()),
+        }
+    }
+
+    /// Where --files-from or --files0-from reads its list, if either was given.
+    pub fn file_list(&self) -> Option<(&Path, ListSeparator)> {
+        match (&self.files_from, &self.files0_from) {
+            (Some(source), _) => Some((source, ListSeparator::Newline)),
+            (None, Some(source)) => Some((source, ListSeparator::Nul)),
+            (None, None) => None,
+        }
+    }
+
+    /// Whether no files were named, leaving just the default `-`.
+    pub fn names_no_files(&self) -> bool {
+        self.files.is_empty() || (self.files.len() == 1 && self.files[0] == Path::new("-"))
+    }
+
+    /// What `--tui` browses: the named files and directories, or the
+    /// current directory when none are named.
+    pub fn tui_roots(&self) -> Vec<PathBuf> {
+        match self.names_no_files() {
+            true => vec![PathBuf::from(".")],
+            false => self.files.clone(),
+        }
+    }
+
     pub fn get_count_modes(&self) -> Vec<CountMode> {
         if self.all {
             return vec![CountMode::Lines, CountMode::Words, CountMode::Bytes];
@@ -73,8 +808,14 @@ impl Cli {
         
         if self.lines { modes.push(CountMode::Lines); }
         if self.words { modes.push(CountMode::Words); }
-        if self.bytes { modes.push(CountMode::Bytes); }
-        if self.chars { modes.push(CountMode::Chars); }
+        // wc prints characters before bytes
+        if self.compat.is_some() {
+            if self.chars.is_some() { modes.push(CountMode::Chars); }
+            if self.bytes { modes.push(CountMode::Bytes); }
+        } else {
+            if self.bytes { modes.push(CountMode::Bytes); }
+            if self.chars.is_some() { modes.push(CountMode::Chars); }
+        }
 
         if modes.is_empty() {
             vec![CountMode::Lines, CountMode::Words, CountMode::Bytes]
@@ -82,6 +823,124 @@ impl Cli {
             modes
         }
     }
+
+    pub fn count_options(&self) -> CountOptions {
+        CountOptions {
+            max_mapped: self.max_mapped,
+            max_memory: self.max_memory,
+            no_mmap: self.no_mmap,
+            raw: self.raw,
+            force_read: self.force_read,
+            line_terminator: match (self.line_delimiter, self.zero_terminated) {
+                (Some(delimiter), _) => delimiter,
+                (None, true) => b'\0',
+                (None, false) => b'\n',
+            },
+            word_delimiters: self.word_delimiters.as_ref().map(|d| d.0.clone()),
+            record_separator: self.record_separator.as_ref().map(|s| s.0.clone()),
+            match_pattern: self.count_matches.clone(),
+            bytes_range: self.bytes_range,
+            lines_range: self.lines_range,
+            skip_lines: self.skip_lines,
+            skip_footer: self.skip_footer,
+            line_filter: self.filter.clone().map(|regex| LineFilter { regex, invert: self.invert_filter }),
+            csv: self.csv,
+            json_stats: self.json_stats,
+            show_longest: self.show_longest.is_some(),
+            longest_content_width: self.show_longest.flatten(),
+            line_stats: self.line_stats || self.histogram.is_some(),
+            per_line: self.per_line,
+            long_lines: None,
+            indent_style: None,
+            blank_lines: self.blank_lines,
+            indent_stats: self.indent_stats,
+            trailing_whitespace: self.trailing_whitespace || self.fail_on_trailing_whitespace,
+            line_percentiles: !self.percentiles.is_empty(),
+            entropy: self.entropy,
+            hash: self.hash.or(self.dedup_content.then_some(HashAlgorithm::Blake3)),
+            max_word_length: self.max_word_length,
+            show_longest_word: self.show_longest_word,
+            vocabulary: self.ttr,
+            min_line_length: self.min_line_length,
+            emoji: self.emoji,
+            diff_input: self.diff_input,
+            html: self.html,
+            comments_only: self.comments_only.is_some(),
+            comment_language: self.comments_only.flatten(),
+            readability: false,
+            control_chars: self.control_chars,
+            scan_unicode: self.scan_unicode,
+            encoding: self.encoding,
+            bom: self.bom,
+            invalid_utf8: self.invalid_utf8.or(match self.compat {
+                Some(Compat::Gnu) => Some(InvalidUtf8::Skip),
+                Some(Compat::Bsd) => Some(InvalidUtf8::Replace),
+                Some(Compat::Posix) | None => None,
+            }),
+            posix: self.posix || matches!(self.compat, Some(Compat::Posix | Compat::Bsd)),
+            newlines_only: self.compat.is_some(),
+            char_unit: self.chars.unwrap_or_default(),
+            max_line_length: self.max_line_length
+                || self.fail_if.iter().chain(&self.warn).any(|threshold| threshold.metric == Metric::MaxLineLength),
+            line_endings: self.line_endings || self.check_line_endings.is_some(),
+            final_newline: self.check_final_newline || self.fail_on_missing_newline,
+            time: self.time,
+        }
+    }
+
+    /// Metrics shown in output: the count modes plus max line length if requested.
+    pub fn get_metrics(&self) -> Vec<Metric> {
+        let mut metrics: Vec<Metric> = self.get_count_modes().iter().map(Metric::from).collect();
+        if self.max_line_length {
+            metrics.push(Metric::MaxLineLength);
+        }
+        metrics
+    }
+
+    /// Modes that must be counted: the displayed ones plus any needed by
+    /// `--fail-if` and `--warn` thresholds.
+    pub fn get_required_modes(&self) -> Vec<CountMode> {
+        let mut modes = self.get_count_modes();
+        // GNU wc sizes its columns from the total size of the inputs
+        if self.compat == Some(Compat::Gnu) && !modes.contains(&CountMode::Bytes) {
+            modes.push(CountMode::Bytes);
+        }
+        for threshold in self.fail_if.iter().chain(&self.warn) {
+            let mode = threshold.metric.count_mode();
+            if !modes.contains(&mode) {
+                modes.push(mode);
+            }
+        }
+        modes
+    }
+}
+
+fn parse_percentile(input: &str) -> WcResult<f64> {
+    let percentile = parse_percent(input)?;
+    if !(0.0..=100.0).contains(&percentile) {
+        return Err(WcError::invalid_argument(format!(
+            "invalid percentile '{}': must be between 0 and 100", input
+        )));
+    }
+    Ok(percentile)
+}
+
+fn parse_regex(input: &str) -> WcResult<Regex> {
+    Regex::new(input).map_err(|e| WcError::invalid_argument(format!("invalid pattern '{}': {}", input, e)))
+}
+
+fn ordered_filters(matches: &ArgMatches) -> Vec<PathFilter> {
+    let collect = |id: &str, make: fn(String) -> PathFilter| -> Vec<(usize, PathFilter)> {
+        match (matches.indices_of(id), matches.get_many::<String>(id)) {
+            (Some(indices), Some(values)) => indices.zip(values.cloned().map(make)).collect(),
+            _ => Vec::new(),
+        }
+    };
+
+    let mut filters = collect("include", PathFilter::Include);
+    filters.extend(collect("exclude", PathFilter::Exclude));
+    filters.sort_by_key(|(index, _)| *index);
+    filters.into_iter().map(|(_, filter)| filter).collect()
 }
 
 #[cfg(test)]
@@ -91,16 +950,24 @@ mod cli_tests {
 
     #[test]
     fn test_cli_default_modes() {
-        let cli = Cli::parse_from(&["rs-wc"]);
+        let cli = Cli::parse_from(["rs-wc"]);
         let modes = cli.get_count_modes();
         assert!(modes.contains(&CountMode::Lines));
         assert!(modes.contains(&CountMode::Words));
         assert!(modes.contains(&CountMode::Bytes));
     }
 
+    #[test]
+    fn test_tui_roots() {
+        assert_eq!(Cli::parse_from(["rs-wc", "--tui"]).tui_roots(), [PathBuf::from(".")]);
+        assert_eq!(Cli::parse_from(["rs-wc", "--tui", "-"]).tui_roots(), [PathBuf::from(".")]);
+        assert_eq!(Cli::parse_from(["rs-wc", "--tui", "src", "README.md"]).tui_roots(),
+                   [PathBuf::from("src"), PathBuf::from("README.md")]);
+    }
+
     #[test]
     fn test_cli_specific_modes() {
-        let cli = Cli::parse_from(&["rs-wc", "-l", "-m"]);
+        let cli = Cli::parse_from(["rs-wc", "-l", "-m"]);
         let modes = cli.get_count_modes();
         assert!(modes.contains(&CountMode::Lines));
         assert!(!modes.contains(&CountMode::Words));
@@ -110,10 +977,56 @@ mod cli_tests {
 
     #[test]
     fn test_cli_all_flag() {
-        let cli = Cli::parse_from(&["rs-wc", "-a"]);
+        let cli = Cli::parse_from(["rs-wc", "-a"]);
         let modes = cli.get_count_modes();
         assert!(modes.contains(&CountMode::Lines));
         assert!(modes.contains(&CountMode::Words));
         assert!(modes.contains(&CountMode::Bytes));
     }
+
+    #[test]
+    fn test_cli_diff_subcommand() {
+        let cli = Cli::parse_from(["rs-wc", "diff", "-l", "a.rs", "b.rs"]);
+        assert!(cli.lines);
+        assert!(matches!(cli.command, Some(Command::Diff { .. })));
+    }
+
+    #[test]
+    fn test_cli_path_filters_keep_order() {
+        let cli = Cli::parse_args_from([
+            "rs-wc", "-r", "--exclude", "target/**", "--include", "*.rs", "--exclude", "*_test.rs",
+        ]);
+        assert_eq!(cli.path_filters, [
+            PathFilter::Exclude("target/**".into()),
+            PathFilter::Include("*.rs".into()),
+            PathFilter::Exclude("*_test.rs".into()),
+        ]);
+    }
+
+    #[test]
+    fn test_cli_line_delimiter() {
+        let cli = Cli::parse_from(["rs-wc", "--line-delimiter", r"\x1e"]);
+        assert_eq!(cli.count_options().line_terminator, 0x1e);
+
+        let cli = Cli::parse_from(["rs-wc", "-z"]);
+        assert_eq!(cli.count_options().line_terminator, 0);
+
+        assert!(Cli::try_parse_from(["rs-wc", "-z", "--line-delimiter", ";"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_percentiles() {
+        let cli = Cli::parse_from(["rs-wc", "--percentiles", "50,90,99.9"]);
+        assert_eq!(cli.percentiles, [50.0, 90.0, 99.9]);
+        assert!(cli.count_options().line_percentiles);
+
+        assert!(Cli::try_parse_from(["rs-wc", "--percentiles", "50,101"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_fail_if_requires_modes() {
+        let cli = Cli::parse_from(["rs-wc", "-l", "--fail-if", "bytes>=1MB"]);
+        assert_eq!(cli.get_count_modes(), vec![CountMode::Lines]);
+        assert_eq!(cli.get_required_modes(), vec![CountMode::Lines, CountMode::Bytes]);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,245 @@
+use crate::error::{WcError, WcResult};
+
+// Multiplier used for bare single-letter suffixes such as `2M`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BareSuffix {
+    Binary,
+    Decimal,
+}
+
+const GROUP_SEPARATORS: [char; 5] = ['_', ' ', '\'', '\u{a0}', '\u{202f}'];
+
+/// Parses a human-readable byte size such as `1.5GiB`, `2M` or `1,5 MB`.
+///
+/// Follows the coreutils convention: `KiB`/`MiB`/... and bare `K`/`M`/...
+/// are powers of 1024, while `KB`/`MB`/... are powers of 1000.
+pub fn parse_size(input: &str) -> WcResult<u64> {
+    parse_with(input, BareSuffix::Binary)
+}
+
+/// Parses a human-readable count such as `1000`, `1.5k` or `2M`.
+///
+/// Identical to [`parse_size`] except that bare `k`/`M`/... are powers of 1000.
+pub fn parse_number(input: &str) -> WcResult<u64> {
+    parse_with(input, BareSuffix::Decimal)
+}
+
+/// Parses a non-negative percentage such as `5`, `2.5%` or `2,5 %`.
+pub fn parse_percent(input: &str) -> WcResult<f64> {
+    let number = input.trim().trim_end_matches('%').trim().replace(',', ".");
+    match number.parse::<f64>() {
+        Ok(value) if value.is_finite() && value >= 0.0 => Ok(value),
+        _ => Err(WcError::invalid_argument(format!("invalid percentage '{}'", input))),
+    }
+}
+
+/// A `START:END` slice of an input, counted from zero and excluding `END`.
+/// Either side may be left out to start at the beginning or run to the end.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InputRange {
+    pub start: u64,
+    pub end: Option<u64>,
+}
+
+impl InputRange {
+    /// The part of `0..len` the range covers.
+    pub fn clamp(&self, len: usize) -> std::ops::Range<usize> {
+        let bound = |n: u64| usize::try_from(n).unwrap_or(usize::MAX).min(len);
+        let end = self.end.map_or(len, bound);
+        bound(self.start).min(end)..end
+    }
+}
+
+/// Parses a byte range such as `0:1M` or `4096:`, each side a size as for
+/// [`parse_size`].
+pub fn parse_byte_range(input: &str) -> WcResult<InputRange> {
+    parse_range(input, parse_size)
+}
+
+/// Parses a line range such as `100:200` or `:1k`, each side a count as for
+/// [`parse_number`].
+pub fn parse_line_range(input: &str) -> WcResult<InputRange> {
+    parse_range(input, parse_number)
+}
+
+fn parse_range(input: &str, parse: fn(&str) -> WcResult<u64>) -> WcResult<InputRange> {
+    let invalid = |reason: &str| WcError::invalid_argument(format!("invalid range '{}': {}", input, reason));
+
+    let (start, end) = input.split_once(':').ok_or_else(|| invalid("expected START:END"))?;
+    let start = if start.trim().is_empty() { 0 } else { parse(start)? };
+    let end = if end.trim().is_empty() { None } else { Some(parse(end)?) };
+    if end.is_some_and(|end| end < start) {
+        return Err(invalid("END is before START"));
+    }
+    Ok(InputRange { start, end })
+}
+
+fn parse_with(input: &str, bare: BareSuffix) -> WcResult<u64> {
+    let invalid = |reason: &str| WcError::invalid_argument(format!("invalid size '{}': {}", input, reason));
+
+    let trimmed = input.trim();
+    let split = trimmed
+        .find(|c: char| c.is_alphabetic())
+        .unwrap_or(trimmed.len());
+    let (number, suffix) = trimmed.split_at(split);
+
+    let multiplier = suffix_multiplier(suffix.trim(), bare)
+        .ok_or_else(|| invalid(&format!("unknown suffix '{}'", suffix)))?;
+    let (integer, fraction) = split_decimal(number.trim()).map_err(invalid)?;
+
+    if integer.is_empty() && fraction.is_empty() {
+        return Err(invalid("missing number"));
+    }
+
+    let digits = format!("{}{}", integer, fraction);
+    let mantissa: u128 = digits.parse().map_err(|_| invalid("not a number"))?;
+    let scale = 10u128
+        .checked_pow(fraction.len() as u32)
+        .ok_or_else(|| invalid("too many decimal places"))?;
+    let scaled = mantissa
+        .checked_mul(multiplier)
+        .ok_or_else(|| invalid("value too large"))?;
+
+    if scaled % scale != 0 {
+        return Err(invalid("value must be a whole number"));
+    }
+
+    u64::try_from(scaled / scale).map_err(|_| invalid("value too large"))
+}
+
+// Splits a number into integer and fraction digits, accepting either `.` or
+// `,` as the decimal separator. When both appear, the last one is the decimal
+// separator and the other groups digits; a separator repeated more than once
+// is always a group separator, and so is a lone comma followed by exactly
+// three digits, as in `1,000`.
+fn split_decimal(number: &str) -> Result<(String, String), &'static str> {
+    let dots = number.matches('.').count();
+    let commas = number.matches(',').count();
+    let thousands = number
+        .split_once(',')
+        .is_some_and(|(_, rest)| rest.len() == 3 && rest.chars().all(|c| c.is_ascii_digit()));
+
+    let decimal = match (dots, commas) {
+        (0, 0) => None,
+        (1, 0) => Some('.'),
+        (0, 1) if thousands => None,
+        (0, 1) => Some(','),
+        (_, 0) | (0, _) => None,
+        _ => number.chars().rev().find(|c| *c == '.' || *c == ','),
+    };
+
+    if let Some(sep) = decimal
+        && number.matches(sep).count() > 1
+    {
+        return Err("ambiguous decimal separator");
+    }
+
+    let mut integer = String::new();
+    let mut fraction = String::new();
+    let mut seen_decimal = false;
+
+    for c in number.chars() {
+        if Some(c) == decimal {
+            seen_decimal = true;
+        } else if c.is_ascii_digit() {
+            if seen_decimal { fraction.push(c) } else { integer.push(c) }
+        } else if c == '.' || c == ',' || GROUP_SEPARATORS.contains(&c) {
+            if seen_decimal {
+                return Err("digit grouping after decimal separator");
+            }
+        } else {
+            return Err("not a number");
+        }
+    }
+
+    Ok((integer, fraction))
+}
+
+fn suffix_multiplier(suffix: &str, bare: BareSuffix) -> Option<u128> {
+    let lower = suffix.to_ascii_lowercase();
+    let (prefix, base) = match lower.as_str() {
+        "" | "b" => return Some(1),
+        s if s.len() == 3 && s.ends_with("ib") => (&s[..1], 1024),
+        s if s.len() == 2 && s.ends_with('b') => (&s[..1], 1000),
+        s if s.len() == 1 => (s, if bare == BareSuffix::Binary { 1024 } else { 1000 }),
+        _ => return None,
+    };
+
+    let exponent = match prefix {
+        "k" => 1,
+        "m" => 2,
+        "g" => 3,
+        "t" => 4,
+        "p" => 5,
+        "e" => 6,
+        _ => return None,
+    };
+
+    Some(u128::pow(base, exponent))
+}
+
+#[cfg(test)]
+mod size_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_byte_range("0:1M").unwrap(), InputRange { start: 0, end: Some(1 << 20) });
+        assert_eq!(parse_line_range("100:").unwrap(), InputRange { start: 100, end: None });
+        assert_eq!(parse_line_range(":1k").unwrap(), InputRange { start: 0, end: Some(1000) });
+        assert!(parse_line_range("200:100").is_err());
+        assert!(parse_line_range("100").is_err());
+
+        let range = parse_line_range("2:5").unwrap();
+        assert_eq!(range.clamp(10), 2..5);
+        assert_eq!(range.clamp(3), 2..3);
+        assert_eq!(range.clamp(1), 1..1);
+    }
+
+    #[test]
+    fn test_parse_plain_numbers() {
+        assert_eq!(parse_size("0").unwrap(), 0);
+        assert_eq!(parse_size("1024").unwrap(), 1024);
+        assert_eq!(parse_size(" 512B ").unwrap(), 512);
+        assert_eq!(parse_number("1_000_000").unwrap(), 1_000_000);
+    }
+
+    #[test]
+    fn test_parse_suffixes() {
+        assert_eq!(parse_size("2M").unwrap(), 2 * 1024 * 1024);
+        assert_eq!(parse_size("1MB").unwrap(), 1_000_000);
+        assert_eq!(parse_size("1.5GiB").unwrap(), 1_610_612_736);
+        assert_eq!(parse_size("4 kB").unwrap(), 4000);
+        assert_eq!(parse_number("2M").unwrap(), 2_000_000);
+        assert_eq!(parse_number("1.5k").unwrap(), 1500);
+    }
+
+    #[test]
+    fn test_parse_locale_separators() {
+        assert_eq!(parse_size("1,5GiB").unwrap(), 1_610_612_736);
+        assert_eq!(parse_number("1.234.567").unwrap(), 1_234_567);
+        assert_eq!(parse_number("1,234,567").unwrap(), 1_234_567);
+        assert_eq!(parse_number("1.234,5k").unwrap(), 1_234_500);
+        assert_eq!(parse_number("1,234.5k").unwrap(), 1_234_500);
+        assert_eq!(parse_number("1\u{a0}000").unwrap(), 1000);
+        assert_eq!(parse_number("1,000").unwrap(), 1000);
+        assert_eq!(parse_size("1,000k").unwrap(), 1_024_000);
+        assert_eq!(parse_number("1,25k").unwrap(), 1250);
+    }
+
+    #[test]
+    fn test_parse_percent() {
+        assert_eq!(parse_percent("5").unwrap(), 5.0);
+        assert_eq!(parse_percent("2,5 %").unwrap(), 2.5);
+        assert!(parse_percent("-1%").is_err());
+        assert!(parse_percent("lots").is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for input in ["", "MB", "1.5", "12XB", "abc", "1.2.3,4.5", "99999999999EiB"] {
+            let err = parse_size(input).unwrap_err();
+            assert!(matches!(err, WcError::InvalidArgument(_)), "{input}");
+        }
+    }
+}
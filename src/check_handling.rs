@@ -0,0 +1,4 @@
+pub mod endings;
+pub mod lint;
+pub mod threshold;
+pub mod unicode;
\ No newline at end of file
@@ -0,0 +1,172 @@
+use std::fmt;
+
+use crate::counter::WcCounter;
+use crate::line_endings::LineEndings;
+use crate::parser::LineEndingStyle;
+
+/// A file whose line endings don't all match the expected style.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndingMismatch {
+    pub filename: String,
+    pub expected: LineEndingStyle,
+    pub endings: LineEndings,
+}
+
+impl LineEndingStyle {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LineEndingStyle::Lf => "LF",
+            LineEndingStyle::Crlf => "CRLF",
+        }
+    }
+
+    /// Whether every terminator in `endings` has this style. Input without
+    /// any terminator matches either style.
+    pub fn matches(&self, endings: &LineEndings) -> bool {
+        match self {
+            LineEndingStyle::Lf => endings.crlf == 0 && endings.cr == 0,
+            LineEndingStyle::Crlf => endings.lf == 0 && endings.cr == 0,
+        }
+    }
+}
+
+impl fmt::Display for EndingMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let found: Vec<String> = [("LF", self.endings.lf), ("CRLF", self.endings.crlf), ("CR", self.endings.cr)]
+            .iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(name, count)| format!("{} {}", count, name))
+            .collect();
+
+        write!(
+            f,
+            "{}: expected {} line endings, found {}",
+            self.filename,
+            self.expected.name(),
+            found.join(", "),
+        )
+    }
+}
+
+/// A non-empty file whose last line has no terminator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissingNewline {
+    pub filename: String,
+}
+
+impl fmt::Display for MissingNewline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: no newline at end of file", self.filename)
+    }
+}
+
+/// A file with lines that end in spaces or tabs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrailingWhitespace {
+    pub filename: String,
+    pub lines: usize,
+}
+
+impl fmt::Display for TrailingWhitespace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = if self.lines == 1 { "" } else { "s" };
+        write!(f, "{}: {} line{} with trailing whitespace", self.filename, self.lines, plural)
+    }
+}
+
+/// Lists the results whose line endings don't match `expected`. Results
+/// without line ending counts are not checked.
+pub fn check_endings(expected: LineEndingStyle, results: &[WcCounter]) -> Vec<EndingMismatch> {
+    results.iter()
+        .filter_map(|result| {
+            let endings = result.line_endings.as_ref()?;
+            (!expected.matches(endings)).then(|| EndingMismatch {
+                filename: result.filename.clone().unwrap_or_else(|| "-".to_string()),
+                expected,
+                endings: endings.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Lists the results known to be missing a final newline.
+pub fn check_final_newline(results: &[WcCounter]) -> Vec<MissingNewline> {
+    results.iter()
+        .filter(|result| result.missing_final_newline == Some(true))
+        .map(|result| MissingNewline {
+            filename: result.filename.clone().unwrap_or_else(|| "-".to_string()),
+        })
+        .collect()
+}
+
+/// Lists the results known to have lines with trailing whitespace.
+pub fn check_trailing_whitespace(results: &[WcCounter]) -> Vec<TrailingWhitespace> {
+    results.iter()
+        .filter_map(|result| {
+            let lines = result.trailing_whitespace.filter(|&lines| lines > 0)?;
+            Some(TrailingWhitespace {
+                filename: result.filename.clone().unwrap_or_else(|| "-".to_string()),
+                lines,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod endings_tests {
+    use super::*;
+
+    fn counter(name: &str, bytes: &[u8]) -> WcCounter {
+        WcCounter { line_endings: Some(LineEndings::from_bytes(bytes)), ..WcCounter::named(name, 0) }
+    }
+
+    #[test]
+    fn test_check_endings() {
+        let results = [
+            counter("unix.txt", b"a\nb\n"),
+            counter("dos.txt", b"a\r\nb\r\n"),
+            counter("mixed.txt", b"a\r\nb\nc\r"),
+            counter("empty.txt", b""),
+        ];
+
+        let lf: Vec<String> = check_endings(LineEndingStyle::Lf, &results)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(lf, [
+            "dos.txt: expected LF line endings, found 2 CRLF",
+            "mixed.txt: expected LF line endings, found 1 LF, 1 CRLF, 1 CR",
+        ]);
+
+        let crlf = check_endings(LineEndingStyle::Crlf, &results);
+        let names: Vec<&str> = crlf.iter().map(|m| m.filename.as_str()).collect();
+        assert_eq!(names, ["unix.txt", "mixed.txt"]);
+    }
+
+    #[test]
+    fn test_check_final_newline() {
+        let result = |name: &str, missing| WcCounter {
+            filename: Some(name.to_string()),
+            missing_final_newline: missing,
+            ..WcCounter::default()
+        };
+        let results = [result("ok.txt", Some(false)), result("bad.txt", Some(true)), result("unchecked", None)];
+
+        let missing = check_final_newline(&results);
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].to_string(), "bad.txt: no newline at end of file");
+    }
+
+    #[test]
+    fn test_check_trailing_whitespace() {
+        let result = |name: &str, lines| WcCounter {
+            filename: Some(name.to_string()),
+            trailing_whitespace: lines,
+            ..WcCounter::default()
+        };
+        let results = [result("clean.rs", Some(0)), result("one.rs", Some(1)), result("many.rs", Some(3)), result("x", None)];
+
+        let found: Vec<String> = check_trailing_whitespace(&results).iter().map(ToString::to_string).collect();
+        assert_eq!(found, ["one.rs: 1 line with trailing whitespace", "many.rs: 3 lines with trailing whitespace"]);
+    }
+}
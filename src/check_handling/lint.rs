@@ -0,0 +1,77 @@
+use std::fmt;
+
+use crate::counter::WcCounter;
+use crate::editorconfig::{EditorConfig, IndentStyle};
+
+/// A rule `rs-wc lint` found broken in a file, with the 1-based numbers of
+/// the offending lines.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LintFinding {
+    /// Lines longer than the max line length
+    LongLines { filename: String, limit: usize, lines: Vec<usize> },
+    /// Lines not indented in the indent style
+    WrongIndent { filename: String, style: IndentStyle, lines: Vec<usize> },
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (filename, lines) = match self {
+            LintFinding::LongLines { filename, lines, .. } | LintFinding::WrongIndent { filename, lines, .. } => {
+                (filename, lines)
+            }
+        };
+        let plural = if lines.len() == 1 { "" } else { "s" };
+        let numbers: Vec<String> = lines.iter().map(ToString::to_string).collect();
+
+        match self {
+            LintFinding::LongLines { limit, .. } => write!(f, "{}: {} line{} longer than {}", filename, lines.len(), plural, limit)?,
+            LintFinding::WrongIndent { style, .. } => write!(f, "{}: {} line{} not indented with {}", filename, lines.len(), plural, style.name())?,
+        }
+        write!(f, ": {}", numbers.join(", "))
+    }
+}
+
+/// Checks a result counted with `rules` as its long line limit and indent
+/// style.
+pub fn check_file(result: &WcCounter, rules: &EditorConfig) -> Vec<LintFinding> {
+    let filename = || result.filename.clone().unwrap_or_else(|| "-".to_string());
+    let mut findings = Vec::new();
+
+    if let (Some(limit), Some(lines)) = (rules.max_line_length, &result.long_lines)
+        && !lines.is_empty()
+    {
+        findings.push(LintFinding::LongLines { filename: filename(), limit, lines: lines.clone() });
+    }
+    if let (Some(style), Some(lines)) = (rules.indent_style, &result.wrong_indent)
+        && !lines.is_empty()
+    {
+        findings.push(LintFinding::WrongIndent { filename: filename(), style, lines: lines.clone() });
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod lint_tests {
+    use super::*;
+
+    #[test]
+    fn test_check_file() {
+        let result = |name: &str, long: Vec<usize>, indent: Vec<usize>| WcCounter {
+            filename: Some(name.to_string()),
+            long_lines: Some(long),
+            wrong_indent: Some(indent),
+            ..WcCounter::default()
+        };
+        let rules = EditorConfig { max_line_length: Some(80), indent_style: Some(IndentStyle::Space) };
+
+        assert!(check_file(&result("ok.rs", vec![], vec![]), &rules).is_empty());
+        assert!(check_file(&WcCounter::default(), &rules).is_empty());
+
+        let found: Vec<String> = check_file(&result("bad.rs", vec![2, 9, 40], vec![7]), &rules)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(found, ["bad.rs: 3 lines longer than 80: 2, 9, 40", "bad.rs: 1 line not indented with spaces: 7"]);
+    }
+}
@@ -0,0 +1,248 @@
+use std::{fmt, str::FromStr};
+
+use crate::counter::{self, WcCounter};
+use crate::error::{WcError, WcResult};
+use crate::parser::CountMode;
+use crate::size::{parse_number, parse_size};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metric {
+    Lines,
+    Words,
+    Bytes,
+    Chars,
+    MaxLineLength,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+    Equal,
+    NotEqual,
+}
+
+/// A limit such as `lines>1000` or `bytes>=1MB` checked against a counter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Threshold {
+    pub metric: Metric,
+    pub comparison: Comparison,
+    pub limit: u64,
+}
+
+/// A counter (file or total) that violated a threshold.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub filename: String,
+    pub threshold: Threshold,
+    pub value: usize,
+}
+
+impl Metric {
+    const NAMES: [(&'static str, Metric); 6] = [
+        ("lines", Metric::Lines),
+        ("words", Metric::Words),
+        ("bytes", Metric::Bytes),
+        ("chars", Metric::Chars),
+        ("max-line-length", Metric::MaxLineLength),
+        ("max_line_length", Metric::MaxLineLength),
+    ];
+
+    pub fn value(&self, counter: &WcCounter) -> usize {
+        match self {
+            Metric::Lines => counter.lines,
+            Metric::Words => counter.words,
+            Metric::Bytes => counter.bytes,
+            Metric::Chars => counter.chars,
+            Metric::MaxLineLength => counter.max_line_length,
+        }
+    }
+
+    // Max line length is computed by the same pass as lines
+    pub fn count_mode(&self) -> CountMode {
+        match self {
+            Metric::Lines | Metric::MaxLineLength => CountMode::Lines,
+            Metric::Words => CountMode::Words,
+            Metric::Bytes => CountMode::Bytes,
+            Metric::Chars => CountMode::Chars,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        Self::NAMES.iter()
+            .find(|(_, metric)| metric == self)
+            .map(|(name, _)| *name)
+            .unwrap_or_default()
+    }
+}
+
+impl From<&CountMode> for Metric {
+    fn from(mode: &CountMode) -> Self {
+        match mode {
+            CountMode::Lines => Metric::Lines,
+            CountMode::Words => Metric::Words,
+            CountMode::Bytes => Metric::Bytes,
+            CountMode::Chars => Metric::Chars,
+        }
+    }
+}
+
+impl Comparison {
+    // Two-character operators first so `>=` isn't read as `>`
+    const OPERATORS: [(&'static str, Comparison); 7] = [
+        (">=", Comparison::GreaterOrEqual),
+        ("<=", Comparison::LessOrEqual),
+        ("==", Comparison::Equal),
+        ("!=", Comparison::NotEqual),
+        (">", Comparison::Greater),
+        ("<", Comparison::Less),
+        ("=", Comparison::Equal),
+    ];
+
+    pub fn holds(&self, value: u64, limit: u64) -> bool {
+        match self {
+            Comparison::Greater => value > limit,
+            Comparison::GreaterOrEqual => value >= limit,
+            Comparison::Less => value < limit,
+            Comparison::LessOrEqual => value <= limit,
+            Comparison::Equal => value == limit,
+            Comparison::NotEqual => value != limit,
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        Self::OPERATORS.iter()
+            .find(|(_, comparison)| comparison == self)
+            .map(|(symbol, _)| *symbol)
+            .unwrap_or_default()
+    }
+}
+
+impl Threshold {
+    /// Parses `METRIC OP VALUE`, e.g. `lines>1000` or `bytes >= 1.5MiB`.
+    pub fn parse(expr: &str) -> WcResult<Self> {
+        let (position, symbol, comparison) = Comparison::OPERATORS.iter()
+            .filter_map(|(symbol, comparison)| expr.find(symbol).map(|pos| (pos, *symbol, *comparison)))
+            .min_by_key(|(pos, symbol, _)| (*pos, usize::MAX - symbol.len()))
+            .ok_or_else(|| WcError::invalid_argument(format!(
+                "invalid threshold '{}': expected METRIC OP VALUE, e.g. lines>1000", expr
+            )))?;
+
+        let name = expr[..position].trim().to_ascii_lowercase();
+        let metric = Metric::NAMES.iter()
+            .find(|(candidate, _)| *candidate == name)
+            .map(|(_, metric)| *metric)
+            .ok_or_else(|| WcError::invalid_argument(format!(
+                "invalid threshold '{}': unknown metric '{}'", expr, name
+            )))?;
+
+        let value = &expr[position + symbol.len()..];
+        let limit = match metric {
+            Metric::Bytes => parse_size(value)?,
+            _ => parse_number(value)?,
+        };
+
+        Ok(Threshold { metric, comparison, limit })
+    }
+
+    pub fn is_violated_by(&self, counter: &WcCounter) -> bool {
+        self.comparison.holds(self.metric.value(counter) as u64, self.limit)
+    }
+}
+
+impl FromStr for Threshold {
+    type Err = WcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Threshold::parse(s)
+    }
+}
+
+impl fmt::Display for Threshold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.metric.name(), self.comparison.symbol(), self.limit)
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} is {} (limit {})",
+            self.filename,
+            self.threshold.metric.name(),
+            self.value,
+            self.threshold,
+        )
+    }
+}
+
+/// Checks every result, and the total when there is more than one, against
+/// the thresholds.
+pub fn check(thresholds: &[Threshold], results: &[WcCounter]) -> Vec<Violation> {
+    let mut targets: Vec<(String, &WcCounter)> = results.iter()
+        .map(|r| (r.filename.clone().unwrap_or_else(|| "-".to_string()), r))
+        .collect();
+
+    let total = counter::total(results);
+    if results.len() > 1 {
+        targets.push(("total".to_string(), &total));
+    }
+
+    targets.iter()
+        .flat_map(|(filename, counter)| {
+            thresholds.iter()
+                .filter(|threshold| threshold.is_violated_by(counter))
+                .map(|threshold| Violation {
+                    filename: filename.clone(),
+                    threshold: threshold.clone(),
+                    value: threshold.metric.value(counter),
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod threshold_tests {
+    use super::*;
+
+    fn counter(name: &str, lines: usize, bytes: usize) -> WcCounter {
+        WcCounter { bytes, ..WcCounter::named(name, lines) }
+    }
+
+    #[test]
+    fn test_parse_threshold() {
+        let threshold = Threshold::parse("lines>1000").unwrap();
+        assert_eq!(threshold.metric, Metric::Lines);
+        assert_eq!(threshold.comparison, Comparison::Greater);
+        assert_eq!(threshold.limit, 1000);
+
+        let threshold = Threshold::parse("bytes >= 1MB").unwrap();
+        assert_eq!(threshold.comparison, Comparison::GreaterOrEqual);
+        assert_eq!(threshold.limit, 1_000_000);
+
+        let threshold: Threshold = "max-line-length<=120".parse().unwrap();
+        assert_eq!(threshold.metric, Metric::MaxLineLength);
+        assert_eq!(threshold.to_string(), "max-line-length<=120");
+    }
+
+    #[test]
+    fn test_parse_threshold_errors() {
+        for expr in ["lines", "pages>3", "lines>lots", ">10"] {
+            assert!(matches!(Threshold::parse(expr), Err(WcError::InvalidArgument(_))), "{expr}");
+        }
+    }
+
+    #[test]
+    fn test_check_reports_files_and_total() {
+        let thresholds = [Threshold::parse("lines>10").unwrap()];
+        let results = [counter("a.txt", 8, 100), counter("b.txt", 12, 100)];
+
+        let violations = check(&thresholds, &results);
+        let names: Vec<&str> = violations.iter().map(|v| v.filename.as_str()).collect();
+        assert_eq!(names, ["b.txt", "total"]);
+        assert_eq!(violations[1].value, 20);
+    }
+}
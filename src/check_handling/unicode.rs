@@ -0,0 +1,72 @@
+use std::fmt;
+
+use crate::counter::WcCounter;
+use crate::unicode_scan::InvisibleChar;
+
+/// Something `--scan-unicode` turned up in a file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnicodeFinding {
+    /// A located invisible character
+    Found { filename: String, location: InvisibleChar },
+    /// Characters found past the located ones
+    Omitted { filename: String, count: usize },
+}
+
+impl fmt::Display for UnicodeFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnicodeFinding::Found { filename, location } => write!(f, "{}:{}", filename, location),
+            UnicodeFinding::Omitted { filename, count } => {
+                write!(f, "{}: {} more invisible or bidi characters", filename, count)
+            }
+        }
+    }
+}
+
+/// Lists every invisible character found in `results`, file by file.
+/// Results that weren't scanned are not checked.
+pub fn check_unicode(results: &[WcCounter]) -> Vec<UnicodeFinding> {
+    let mut findings = Vec::new();
+
+    for result in results {
+        let Some(scan) = &result.unicode_scan else { continue };
+        let filename = result.filename.clone().unwrap_or_else(|| "-".to_string());
+        findings.extend(scan.locations.iter().map(|location| UnicodeFinding::Found {
+            filename: filename.clone(),
+            location: location.clone(),
+        }));
+        let omitted = scan.total() - scan.locations.len();
+        if omitted > 0 {
+            findings.push(UnicodeFinding::Omitted { filename, count: omitted });
+        }
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod unicode_tests {
+    use super::*;
+    use crate::unicode_scan::{scan_unicode, MAX_LOCATIONS};
+
+    #[test]
+    fn test_check_unicode() {
+        let result = |name: &str, text: &str| WcCounter {
+            filename: Some(name.to_string()),
+            unicode_scan: Some(scan_unicode(text.as_bytes(), b'\n')),
+            ..WcCounter::default()
+        };
+        let results = [
+            result("clean.rs", "let x = 1;\n"),
+            result("evil.rs", "a\nb\u{202E}c\n"),
+            result("many.txt", &"\u{200B}".repeat(MAX_LOCATIONS + 2)),
+            WcCounter { filename: Some("unscanned".into()), ..WcCounter::default() },
+        ];
+
+        let findings: Vec<String> = check_unicode(&results).iter().map(ToString::to_string).collect();
+        assert_eq!(findings.len(), MAX_LOCATIONS + 2);
+        assert_eq!(findings[0], "evil.rs:2:2: U+202E RIGHT-TO-LEFT OVERRIDE (bidi control)");
+        assert_eq!(findings[1], "many.txt:1:1: U+200B ZERO WIDTH SPACE (zero-width character)");
+        assert_eq!(findings[MAX_LOCATIONS + 1], "many.txt: 2 more invisible or bidi characters");
+    }
+}
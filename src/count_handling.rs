@@ -1 +1,26 @@
-pub mod counter;
\ No newline at end of file
+pub mod aggregate;
+pub mod cache;
+pub mod comments;
+pub mod control;
+pub mod counter;
+pub mod csv_stats;
+pub mod diff;
+pub mod emoji;
+pub mod encoding;
+pub mod entropy;
+pub mod group;
+pub mod hash;
+pub mod html;
+pub mod json_stats;
+pub mod line_endings;
+pub mod line_stats;
+pub mod longest;
+pub mod matches;
+pub mod patch;
+pub mod readability;
+pub mod records;
+pub mod sketch;
+pub mod snapshot;
+pub mod unicode_scan;
+pub mod vocabulary;
+pub mod whitespace;
\ No newline at end of file
@@ -0,0 +1,95 @@
+use crate::counter::WcCounter;
+use crate::parser::Aggregate;
+
+impl Aggregate {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aggregate::Max => "max",
+            Aggregate::Min => "min",
+            Aggregate::Mean => "mean",
+            Aggregate::Median => "median",
+        }
+    }
+}
+
+/// Summarises one metric's values across results, or `None` if there are
+/// none. The median of an even number of values is the mean of the middle
+/// two.
+pub fn aggregate_values(mut values: Vec<f64>, kind: Aggregate) -> Option<f64> {
+    let count = values.len();
+    if count == 0 {
+        return None;
+    }
+    let value = match kind {
+        Aggregate::Max => values.into_iter().fold(f64::MIN, f64::max),
+        Aggregate::Min => values.into_iter().fold(f64::MAX, f64::min),
+        Aggregate::Mean => values.iter().sum::<f64>() / count as f64,
+        Aggregate::Median => {
+            values.sort_unstable_by(f64::total_cmp);
+            match count % 2 {
+                1 => values[count / 2],
+                _ => (values[count / 2 - 1] + values[count / 2]) / 2.0,
+            }
+        }
+    };
+    Some(value)
+}
+
+/// Summarises the base counts (lines, words, bytes, chars and max line
+/// length) of `results` field by field. Means, and medians of an even number
+/// of results, are rounded to the nearest whole count; optional metrics are
+/// left unset.
+pub fn aggregate(results: &[WcCounter], kind: Aggregate) -> WcCounter {
+    let fields = |counter: &WcCounter| {
+        [counter.lines, counter.words, counter.bytes, counter.chars, counter.max_line_length]
+    };
+
+    let values: [usize; 5] = std::array::from_fn(|field| {
+        let column = results.iter().map(|result| fields(result)[field] as f64).collect();
+        aggregate_values(column, kind).map_or(0, |value| value.round() as usize)
+    });
+
+    let [lines, words, bytes, chars, max_line_length] = values;
+    WcCounter {
+        lines,
+        words,
+        bytes,
+        chars,
+        max_line_length,
+        ..WcCounter::default()
+    }
+}
+
+#[cfg(test)]
+mod aggregate_tests {
+    use super::*;
+
+    fn counter(lines: usize, bytes: usize) -> WcCounter {
+        WcCounter { lines, bytes, ..WcCounter::default() }
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let results = [counter(10, 300), counter(4, 100), counter(7, 201)];
+
+        let max = aggregate(&results, Aggregate::Max);
+        let min = aggregate(&results, Aggregate::Min);
+        let mean = aggregate(&results, Aggregate::Mean);
+        let median = aggregate(&results, Aggregate::Median);
+
+        assert_eq!((max.lines, max.bytes), (10, 300));
+        assert_eq!((min.lines, min.bytes), (4, 100));
+        assert_eq!((mean.lines, mean.bytes), (7, 200));
+        assert_eq!((median.lines, median.bytes), (7, 201));
+        assert_eq!(aggregate(&results[..2], Aggregate::Median).lines, 7);
+        assert_eq!(aggregate(&[], Aggregate::Mean).lines, 0);
+    }
+
+    #[test]
+    fn test_aggregate_values() {
+        assert_eq!(aggregate_values(vec![0.5, 2.0, 1.0], Aggregate::Max), Some(2.0));
+        assert_eq!(aggregate_values(vec![0.5, 2.0, 1.0], Aggregate::Median), Some(1.0));
+        assert_eq!(aggregate_values(vec![0.5, 2.0], Aggregate::Median), Some(1.25));
+        assert_eq!(aggregate_values(Vec::new(), Aggregate::Min), None);
+    }
+}
@@ -0,0 +1,123 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::counter::{count_file_with, CountOptions, WcCounter};
+use crate::error::{WcError, WcResult};
+use crate::parser::CountMode;
+
+/// Files remembered by `CountCache::new`.
+pub const DEFAULT_CAPACITY: usize = 10_000;
+
+// A file's last count, reused until its size or modification time changes
+struct CachedCount {
+    modified: SystemTime,
+    len: u64,
+    counter: WcCounter,
+    // When the count was last served, to find the least recently used
+    used: u64,
+}
+
+/// Remembers the counts of files that are counted again and again, such as
+/// by a long-running server, recounting a file only once it has changed.
+/// Files that can no longer be counted are forgotten, and past `capacity`
+/// files the least recently used count makes way for a new one.
+pub struct CountCache {
+    entries: HashMap<PathBuf, CachedCount>,
+    capacity: usize,
+    uses: u64,
+}
+
+impl Default for CountCache {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl CountCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { entries: HashMap::new(), capacity: capacity.max(1), uses: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn count(&mut self, path: &Path, modes: &[CountMode], options: &CountOptions) -> WcResult<WcCounter> {
+        self.uses += 1;
+        let counted = self.count_uncached(path, modes, options);
+        if counted.is_err() {
+            self.entries.remove(path);
+        }
+        counted
+    }
+
+    fn count_uncached(&mut self, path: &Path, modes: &[CountMode], options: &CountOptions) -> WcResult<WcCounter> {
+        let inspect_error = |e| WcError::from(e).with_path(path.display().to_string(), "inspect");
+        let metadata = path.metadata().map_err(inspect_error)?;
+        let modified = metadata.modified().map_err(inspect_error)?;
+
+        if let Some(cached) = self.entries.get_mut(path)
+            && cached.modified == modified
+            && cached.len == metadata.len()
+        {
+            cached.used = self.uses;
+            return Ok(cached.counter.clone());
+        }
+
+        let counter = count_file_with(path, modes, options)?;
+        if !self.entries.contains_key(path) && self.entries.len() >= self.capacity {
+            let oldest = self.entries.iter().min_by_key(|(_, cached)| cached.used).map(|(path, _)| path.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        let cached = CachedCount { modified, len: metadata.len(), counter: counter.clone(), used: self.uses };
+        self.entries.insert(path.to_path_buf(), cached);
+        Ok(counter)
+    }
+}
+
+#[cfg(test)]
+mod cache_tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_cache_is_bounded() {
+        let dir = std::env::temp_dir().join(format!("rs-wc-cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let paths: Vec<PathBuf> = (0..3).map(|index| dir.join(format!("{}.txt", index))).collect();
+        for path in &paths {
+            fs::write(path, "one two\n").unwrap();
+        }
+
+        let mut cache = CountCache::with_capacity(2);
+        let modes = [CountMode::Words];
+        let options = CountOptions::default();
+        cache.count(&paths[0], &modes, &options).unwrap();
+        cache.count(&paths[1], &modes, &options).unwrap();
+        cache.count(&paths[0], &modes, &options).unwrap();
+        // The least recently used count, of paths[1], makes way
+        cache.count(&paths[2], &modes, &options).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert!(cache.entries.contains_key(&paths[0]) && !cache.entries.contains_key(&paths[1]));
+
+        // A file that is gone is forgotten
+        fs::remove_file(&paths[2]).unwrap();
+        assert!(cache.count(&paths[2], &modes, &options).is_err());
+        assert_eq!(cache.len(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
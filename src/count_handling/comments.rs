@@ -0,0 +1,254 @@
+use clap::ValueEnum;
+use std::path::Path;
+
+/// Comment syntax `--comments-only` reads a file with, named after the
+/// language it's best known from.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum Language {
+    /// `//` and `/* */`: C, C++, C#, Java, JavaScript, TypeScript, Go, Kotlin, Swift and the like
+    C,
+    /// `//` and nested `/* */`
+    Rust,
+    /// `/* */` only
+    Css,
+    /// `#`
+    Python,
+    /// `#` at the start of a word: shell scripts, Ruby, Perl, R, YAML, TOML, Makefiles, Dockerfiles
+    Shell,
+    /// `--` and `/* */`
+    Sql,
+    /// `--` and `--[[ ]]`
+    Lua,
+    /// `--` and nested `{- -}`
+    Haskell,
+    /// `<!-- -->`: HTML, XML and SVG
+    Html,
+}
+
+// How comments and strings are written; strings are skipped so that a
+// comment marker inside one isn't taken for a comment
+struct Syntax {
+    line: Option<&'static str>,
+    block: Option<(&'static str, &'static str)>,
+    nested: bool,
+    quotes: &'static [u8],
+    // Rust's `'` starts a char literal only when one closes right after it,
+    // since it also starts lifetimes
+    char_literals: bool,
+    // A line comment marker only counts at the start of a word, as in
+    // shell, where `$#` and `a#b` aren't comments
+    line_needs_space: bool,
+}
+
+impl Language {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::C => "c",
+            Language::Rust => "rust",
+            Language::Css => "css",
+            Language::Python => "python",
+            Language::Shell => "shell",
+            Language::Sql => "sql",
+            Language::Lua => "lua",
+            Language::Haskell => "haskell",
+            Language::Html => "html",
+        }
+    }
+
+    /// The comment syntax of the file `path` names, by its extension or, for
+    /// files such as `Makefile`, its name.
+    pub fn for_path(path: &Path) -> Option<Language> {
+        let name = path.file_name()?.to_str()?;
+        match name {
+            "Makefile" | "makefile" | "GNUmakefile" | "Dockerfile" | "CMakeLists.txt" => return Some(Language::Shell),
+            _ => {}
+        }
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        let language = match extension.as_str() {
+            "c" | "h" | "cc" | "cpp" | "cxx" | "hh" | "hpp" | "hxx" | "cs" | "java" | "kt" | "kts" | "scala"
+            | "swift" | "go" | "js" | "mjs" | "cjs" | "jsx" | "ts" | "tsx" | "dart" | "php" | "proto" => Language::C,
+            "rs" => Language::Rust,
+            "css" => Language::Css,
+            "py" | "pyi" => Language::Python,
+            "sh" | "bash" | "zsh" | "fish" | "rb" | "pl" | "pm" | "r" | "yaml" | "yml" | "toml" | "mk"
+            | "cmake" | "dockerfile" => Language::Shell,
+            "sql" => Language::Sql,
+            "lua" => Language::Lua,
+            "hs" => Language::Haskell,
+            "html" | "htm" | "xhtml" | "xml" | "svg" => Language::Html,
+            _ => return None,
+        };
+        Some(language)
+    }
+
+    fn syntax(&self) -> Syntax {
+        let c_block = Some(("/*", "*/"));
+        let plain = Syntax { line: None, block: None, nested: false, quotes: b"\"'", char_literals: false, line_needs_space: false };
+        match self {
+            Language::C => Syntax { line: Some("//"), block: c_block, ..plain },
+            Language::Rust => Syntax { line: Some("//"), block: c_block, nested: true, quotes: b"\"", char_literals: true, ..plain },
+            Language::Css => Syntax { block: c_block, ..plain },
+            Language::Python => Syntax { line: Some("#"), ..plain },
+            // Apostrophes are common in YAML and TOML values
+            Language::Shell => Syntax { line: Some("#"), quotes: b"\"", line_needs_space: true, ..plain },
+            Language::Sql => Syntax { line: Some("--"), block: c_block, ..plain },
+            Language::Lua => Syntax { line: Some("--"), block: Some(("--[[", "]]")), ..plain },
+            // `'` also ends names, as in `x'`
+            Language::Haskell => Syntax { line: Some("--"), block: Some(("{-", "-}")), nested: true, quotes: b"\"", ..plain },
+            Language::Html => Syntax { block: Some(("<!--", "-->")), quotes: b"", ..plain },
+        }
+    }
+}
+
+// The index just past the string that starts with the quote at `start`
+fn string_end(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes[start];
+    let mut index = start + 1;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            byte if byte == quote => return index + 1,
+            _ => index += 1,
+        }
+    }
+    bytes.len()
+}
+
+// Where a marker's decoration ends: the extra `/` and `!` of `///` and `//!`
+// doc comments, the `*` of `/**`, and the like
+fn skip_decoration(bytes: &[u8], mut index: usize, marker: &str) -> usize {
+    let last = marker.as_bytes()[marker.len() - 1];
+    while index < bytes.len() && (bytes[index] == last || bytes[index] == b'!') {
+        index += 1;
+    }
+    index
+}
+
+/// The text of `bytes`' comments in `language`, for `--comments-only`:
+/// each source line with a comment on it, or inside one, becomes a line of
+/// its comment text, without the comment markers or the `*` that starts
+/// each line of a `/** */` block.
+pub fn comment_text(bytes: &[u8], language: Language) -> Vec<u8> {
+    let syntax = language.syntax();
+    let mut out = Vec::new();
+    // Whether the current line has comment text, and so ends a line of output
+    let mut open_line = false;
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let byte = bytes[index];
+        let rest = &bytes[index..];
+        let word_start = index == 0 || bytes[index - 1].is_ascii_whitespace();
+
+        if byte == b'\n' {
+            if open_line {
+                out.push(b'\n');
+                open_line = false;
+            }
+            index += 1;
+        } else if let Some((open, close)) = syntax.block.filter(|(open, _)| rest.starts_with(open.as_bytes())) {
+            if open_line {
+                out.push(b' ');
+            }
+            open_line = true;
+            index = skip_decoration(bytes, index + open.len(), open);
+            let mut depth = 1;
+            while index < bytes.len() {
+                let rest = &bytes[index..];
+                if rest.starts_with(close.as_bytes()) {
+                    depth -= 1;
+                    index += close.len();
+                    if depth == 0 {
+                        break;
+                    }
+                    out.extend_from_slice(close.as_bytes());
+                } else if syntax.nested && rest.starts_with(open.as_bytes()) {
+                    depth += 1;
+                    index += open.len();
+                    out.extend_from_slice(open.as_bytes());
+                } else if rest[0] == b'\n' {
+                    out.push(b'\n');
+                    index += 1;
+                    // Drop the indentation and `*` that lines of a block
+                    // comment often start with
+                    let text = index + bytes[index..].iter().take_while(|&&b| b == b' ' || b == b'\t').count();
+                    if bytes.get(text) == Some(&b'*') && !bytes[text..].starts_with(close.as_bytes()) {
+                        index = text + 1;
+                    }
+                } else {
+                    out.push(rest[0]);
+                    index += 1;
+                }
+            }
+        } else if let Some(marker) = syntax.line.filter(|marker| rest.starts_with(marker.as_bytes()))
+            && (word_start || !syntax.line_needs_space)
+        {
+            if open_line {
+                out.push(b' ');
+            }
+            open_line = true;
+            let start = skip_decoration(bytes, index + marker.len(), marker);
+            let end = memchr::memchr(b'\n', &bytes[start..]).map_or(bytes.len(), |offset| start + offset);
+            out.extend_from_slice(&bytes[start..end]);
+            index = end;
+        } else if syntax.quotes.contains(&byte)
+            || (syntax.char_literals && byte == b'\'' && (rest.get(1) == Some(&b'\\') || rest.get(2) == Some(&b'\'')))
+        {
+            index = string_end(bytes, index);
+        } else {
+            index += 1;
+        }
+    }
+
+    if open_line {
+        out.push(b'\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod comments_tests {
+    use super::*;
+
+    fn comments(source: &str, language: Language) -> String {
+        String::from_utf8(comment_text(source.as_bytes(), language)).unwrap()
+    }
+
+    #[test]
+    fn test_for_path() {
+        assert_eq!(Language::for_path(Path::new("src/main.rs")), Some(Language::Rust));
+        assert_eq!(Language::for_path(Path::new("App.TSX")), Some(Language::C));
+        assert_eq!(Language::for_path(Path::new("build/Makefile")), Some(Language::Shell));
+        assert_eq!(Language::for_path(Path::new("notes.txt")), None);
+        assert_eq!(Language::for_path(Path::new("-")), None);
+    }
+
+    #[test]
+    fn test_c_comments() {
+        let source = r#"/**
+ * Adds two numbers.
+ */
+int add(int a, int b) { return a + b; } // the sum
+char *url = "http://example.com"; /* not // a line comment */
+char c = '"'; /*a*/ x /*b*/
+"#;
+        assert_eq!(comments(source, Language::C), "\n Adds two numbers.\n \n the sum\n not // a line comment \na b\n");
+    }
+
+    #[test]
+    fn test_rust_comments() {
+        let source = "/// Docs\nfn f<'a>(s: &'a str) -> char { '\\'' } // quote\n/* outer /* inner */ still */ let x = \"// no\";\n";
+        assert_eq!(comments(source, Language::Rust), " Docs\n quote\n outer /* inner */ still \n");
+    }
+
+    #[test]
+    fn test_other_comments() {
+        assert_eq!(comments("echo $# '#' a#b # real\n", Language::Shell), " real\n");
+        assert_eq!(comments("x = '#' # note\n", Language::Python), " note\n");
+        assert_eq!(comments("SELECT '--' -- why\n", Language::Sql), " why\n");
+        assert_eq!(comments("--[[ block\nends ]] -- line\n", Language::Lua), " block\nends   line\n");
+        assert_eq!(comments("f x' = {- a {- b -} -} x'\n", Language::Haskell), " a {- b -} \n");
+        assert_eq!(comments("<p>don't</p><!-- hidden -->\n", Language::Html), " hidden \n");
+        assert_eq!(comments("no comments here\n", Language::C), "");
+    }
+}
@@ -0,0 +1,40 @@
+use crate::parser::ControlScope;
+
+/// Counts the C0 (including DEL) and C1 control characters in `bytes`. C1
+/// controls are only recognised in their UTF-8 encoding, U+0080 to U+009F.
+/// Unless `scope` is `All`, newlines and tabs are left out, as expected in
+/// plain text.
+pub fn count_control_chars(bytes: &[u8], scope: ControlScope) -> usize {
+    let mut count = 0;
+    let mut index = 0;
+
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\n' | b'\t' => count += usize::from(scope == ControlScope::All),
+            0x00..=0x1F | 0x7F => count += 1,
+            0xC2 if matches!(bytes.get(index + 1), Some(0x80..=0x9F)) => {
+                count += 1;
+                index += 1;
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+
+    count
+}
+
+#[cfg(test)]
+mod control_tests {
+    use super::*;
+
+    #[test]
+    fn test_count_control_chars() {
+        let text = "a\tb\r\n\u{7}c\u{1b}[0m\u{7f}\u{85}é\n".as_bytes();
+        assert_eq!(count_control_chars(text, ControlScope::Text), 5);
+        assert_eq!(count_control_chars(text, ControlScope::All), 8);
+        assert_eq!(count_control_chars(b"plain text\n", ControlScope::Text), 0);
+        // A stray continuation byte isn't a C1 control
+        assert_eq!(count_control_chars(b"\x85\xc2", ControlScope::Text), 0);
+    }
+}
@@ -1,19 +1,55 @@
+use encoding_rs::{Encoding, UTF_8};
 use memmap::MmapOptions;
+use memchr::memchr_iter;
 use rayon::prelude::*;
+use regex::bytes::Regex;
+use tracing::{debug, debug_span, info, info_span};
 use std::{
     fs,
-    io::{self, BufRead},
-    path::Path,
+    io::{self, BufRead, Cursor, Read},
+    fmt,
+    path::{Path, PathBuf},
+    cmp::Reverse,
+    collections::BinaryHeap,
+    sync::{mpsc, Condvar, Mutex},
+    thread,
+    time::{Duration, Instant},
 };
 
+use crate::comments::{comment_text, Language};
+use crate::csv_stats::{csv_stats, CsvStats};
+use crate::editorconfig::IndentStyle;
+use crate::extract::Document;
+use crate::encoding::{chars_under, count_chars, count_chars_as, decode, InputEncoding};
+use crate::control::count_control_chars;
+use crate::emoji::count_emoji;
+use crate::entropy::ByteHistogram;
 use crate::error::{WcError, WcResult};
-use crate::parser::CountMode;
+use crate::filesystem;
+use crate::hash::{hash_bytes, ContentHasher, HashAlgorithm};
+use crate::html::rendered_text;
+use crate::json_stats::{json_stats, JsonStats};
+use crate::line_endings::LineEndings;
+use crate::line_stats::{line_widths, long_lines, select_lines, shortest_line, trim_lines, LineStats};
+use crate::longest::{longest_line, truncate, LongestLine, LongestWord};
+use crate::matches::{count_matches, LineFilter, MatchCounter, MatchCounts};
+use crate::parser::{BomPolicy, CharUnit, ControlScope, CountMode, InvalidUtf8, MinLineScope};
+use crate::patch::PatchStats;
+use crate::readability::Readability;
+use crate::records::{count_records, RecordCounter};
+use crate::size::InputRange;
+use crate::sketch::QuantileSketch;
+use crate::unicode_scan::{scan_unicode, UnicodeScan};
+use crate::vocabulary::Vocabulary;
+use crate::whitespace::{trailing_whitespace_lines, wrong_indent_lines, BlankLines, IndentStats};
 
 use proptest::arbitrary::Arbitrary;
 use proptest::strategy::{Strategy, BoxedStrategy};
 use proptest::prelude::any;
 
-#[derive(Debug, Default, Clone)]
+/// Equality and hashing cover every field, the filename included; see
+/// [`WcCounter::counts_eq`] to compare counts alone.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct WcCounter {
     pub lines: usize,
     pub words: usize,
@@ -21,6 +57,432 @@ pub struct WcCounter {
     pub chars: usize,
     pub max_line_length: usize,
     pub filename: Option<String>,
+    /// Occurrences of the `--records` separator
+    pub records: Option<usize>,
+    /// Matches of the `--count-matches` pattern
+    pub matches: Option<MatchCounts>,
+    /// Record and field counts from `--csv`
+    pub csv: Option<CsvStats>,
+    /// Structural counts from `--json-stats`
+    pub json: Option<JsonStats>,
+    /// Where the longest line is, from `--show-longest`. Only meaningful for a
+    /// single input, so it is not carried into totals or groups.
+    pub longest: Option<LongestLine>,
+    /// Line length distribution from `--line-stats`
+    pub line_stats: Option<LineStats>,
+    /// Display width of every line, from `--per-line`; per input only
+    pub line_widths: Option<Vec<usize>>,
+    /// Numbers of the lines over the `rs-wc lint` limit; per input only
+    pub long_lines: Option<Vec<usize>>,
+    /// Numbers of the lines not in the `rs-wc lint` indent style; per input
+    /// only
+    pub wrong_indent: Option<Vec<usize>>,
+    /// The max_line_length `.editorconfig` sets for the input, with
+    /// `--editorconfig`; per input only
+    pub line_length_limit: Option<usize>,
+    /// Empty and whitespace-only lines from `--blank-lines`
+    pub blank_lines: Option<BlankLines>,
+    /// Tab and space indentation from `--indent-stats`
+    pub indent_stats: Option<IndentStats>,
+    /// Lines ending in spaces or tabs, from `--trailing-whitespace`
+    pub trailing_whitespace: Option<usize>,
+    /// Line length sketch for `--percentiles`
+    pub line_percentiles: Option<QuantileSketch>,
+    /// Byte frequencies for `--entropy`
+    pub byte_histogram: Option<ByteHistogram>,
+    /// Hex digest of the input from `--hash`; per input only
+    pub hash: Option<String>,
+    /// The earlier input with the same content, from `--dedup-content`.
+    /// Duplicates are left out of totals and groups.
+    pub duplicate_of: Option<String>,
+    /// Terminators by kind, from `--line-endings`
+    pub line_endings: Option<LineEndings>,
+    /// Whether non-empty input lacks a final line terminator, from
+    /// `--check-final-newline`; per input only
+    pub missing_final_newline: Option<bool>,
+    /// Malformed UTF-8 sequences, when `--invalid-utf8` is given
+    pub invalid_utf8: Option<usize>,
+    /// Encoding the input was decoded from with `--encoding`; per input only,
+    /// so not carried into totals or groups
+    pub encoding: Option<String>,
+    /// Document format the counted text was extracted from; per input only
+    pub extracted_from: Option<&'static str>,
+    /// Comment syntax the input was read with for `--comments-only`
+    pub comment_language: Option<&'static str>,
+    /// Wall time spent reading and counting the input, from `--time`; per
+    /// input only
+    pub duration: Option<Duration>,
+    /// Characters in the longest word, from `--max-word-length`
+    pub max_word_length: Option<usize>,
+    /// Distinct words for the type-token ratio of `--ttr`
+    pub vocabulary: Option<Vocabulary>,
+    /// The longest word itself, from `--show-longest-word`; totals keep the
+    /// longest of all inputs
+    pub longest_word: Option<LongestWord>,
+    /// Length of the shortest line, from `--min-line-length`; unset when
+    /// there was no line to measure
+    pub min_line_length: Option<usize>,
+    /// Emoji, counting each sequence once, from `--emoji`
+    pub emoji: Option<usize>,
+    /// Lines, words and files changed by a unified diff, from `--diff-input`
+    pub patch: Option<PatchStats>,
+    /// Sentence, word and syllable counts for `rs-wc readability`
+    pub readability: Option<Readability>,
+    /// Bidi controls and invisible characters, from `--scan-unicode`
+    pub unicode_scan: Option<UnicodeScan>,
+    /// C0 and C1 control characters, from `--control-chars`
+    pub control_chars: Option<usize>,
+}
+
+/// An input that couldn't be counted, kept so the output can list it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl Failure {
+    pub fn new(path: &Path, error: &WcError) -> Self {
+        Self { path: path.to_path_buf(), message: error.to_string() }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Settings that affect how inputs are read and split, independent of which
+/// counts are requested.
+#[derive(Debug, Clone)]
+pub struct CountOptions {
+    /// Upper bound on the bytes mapped at once while counting files in parallel
+    pub max_mapped: Option<u64>,
+    /// Upper bound on the input held in memory at once; larger inputs are
+    /// read a piece at a time
+    pub max_memory: Option<u64>,
+    /// Read files instead of memory-mapping them
+    pub no_mmap: bool,
+    /// Count documents' bytes instead of their extracted text
+    pub raw: bool,
+    /// Read files even when only their size is needed
+    pub force_read: bool,
+    /// Byte that ends a line (`\n`, or NUL with `-z`); it also separates words
+    pub line_terminator: u8,
+    /// Count line terminators rather than lines, as wc does, so an
+    /// unterminated final line isn't counted
+    pub newlines_only: bool,
+    /// What `-m` counts as a character
+    pub char_unit: CharUnit,
+    /// Track the longest line. Without it, counting only lines just looks
+    /// for terminators instead of scanning every byte.
+    pub max_line_length: bool,
+    /// Bytes that separate words instead of ASCII whitespace
+    pub word_delimiters: Option<Vec<u8>>,
+    /// Separator whose occurrences are counted as records
+    pub record_separator: Option<Vec<u8>>,
+    /// Pattern whose matches are counted, line by line
+    pub match_pattern: Option<Regex>,
+    /// Slice of the input that is counted, applied before anything else
+    pub bytes_range: Option<InputRange>,
+    /// Lines of the input that are counted, applied after `bytes_range`
+    pub lines_range: Option<InputRange>,
+    /// Leading lines left out of every count but bytes
+    pub skip_lines: usize,
+    /// Trailing lines left out of every count but bytes
+    pub skip_footer: usize,
+    /// Lines to keep; the rest are left out of every count but bytes
+    pub line_filter: Option<LineFilter>,
+    /// Parse the input as CSV and count records and fields
+    pub csv: bool,
+    /// Parse the input as JSON and count keys, elements and depth
+    pub json_stats: bool,
+    /// Locate the longest line
+    pub show_longest: bool,
+    /// Also keep the longest line's text, cut to this many characters
+    pub longest_content_width: Option<usize>,
+    /// Collect the line length distribution
+    pub line_stats: bool,
+    /// Measure the display width of every line
+    pub per_line: bool,
+    /// Find the lines longer than this
+    pub long_lines: Option<usize>,
+    /// Find the lines not indented in this style
+    pub indent_style: Option<IndentStyle>,
+    /// Count empty and whitespace-only lines
+    pub blank_lines: bool,
+    /// Classify each line's indentation
+    pub indent_stats: bool,
+    /// Count lines that end in whitespace
+    pub trailing_whitespace: bool,
+    /// Sketch line lengths for percentile estimates
+    pub line_percentiles: bool,
+    /// Collect byte frequencies for entropy and binary detection
+    pub entropy: bool,
+    /// Hash the raw bytes counted
+    pub hash: Option<HashAlgorithm>,
+    /// Find the longest word
+    pub max_word_length: bool,
+    /// Keep the longest word's text, cut to this many characters
+    pub show_longest_word: Option<usize>,
+    /// Collect the distinct words
+    pub vocabulary: bool,
+    /// Find the shortest line, of those in scope
+    pub min_line_length: Option<MinLineScope>,
+    /// Count emoji sequences
+    pub emoji: bool,
+    /// Read input as a unified diff
+    pub diff_input: bool,
+    /// Count only the text an HTML page renders
+    pub html: bool,
+    /// Count only the text of comments
+    pub comments_only: bool,
+    /// Comment syntax to read every input with, rather than going by file
+    /// extension
+    pub comment_language: Option<Language>,
+    /// Count sentences and syllables for readability scores
+    pub readability: bool,
+    /// Find bidi controls, zero-width and other invisible characters
+    pub scan_unicode: bool,
+    /// Count control characters, of those in scope
+    pub control_chars: Option<ControlScope>,
+    /// Transcode input from this encoding before counting anything but bytes
+    pub encoding: Option<InputEncoding>,
+    /// Treatment of a leading byte order mark
+    pub bom: BomPolicy,
+    /// How malformed UTF-8 counts as characters; unset behaves like
+    /// `InvalidUtf8::Bytes` without reporting the malformed sequences
+    pub invalid_utf8: Option<InvalidUtf8>,
+    /// Split words on ASCII whitespace only, not on Unicode spaces such as
+    /// U+00A0 or U+3000
+    pub posix: bool,
+    /// Count LF, CRLF and lone CR terminators separately
+    pub line_endings: bool,
+    /// Note whether the input ends with a line terminator
+    pub final_newline: bool,
+    /// Record how long each file takes
+    pub time: bool,
+}
+
+impl Default for CountOptions {
+    fn default() -> Self {
+        Self {
+            max_mapped: None,
+            max_memory: None,
+            no_mmap: false,
+            raw: false,
+            force_read: false,
+            line_terminator: b'\n',
+            newlines_only: false,
+            char_unit: CharUnit::Codepoints,
+            max_line_length: true,
+            word_delimiters: None,
+            record_separator: None,
+            match_pattern: None,
+            bytes_range: None,
+            lines_range: None,
+            skip_lines: 0,
+            skip_footer: 0,
+            line_filter: None,
+            csv: false,
+            json_stats: false,
+            show_longest: false,
+            longest_content_width: None,
+            line_stats: false,
+            per_line: false,
+            long_lines: None,
+            indent_style: None,
+            blank_lines: false,
+            indent_stats: false,
+            trailing_whitespace: false,
+            line_percentiles: false,
+            entropy: false,
+            hash: None,
+            max_word_length: false,
+            show_longest_word: None,
+            vocabulary: false,
+            min_line_length: None,
+            emoji: false,
+            diff_input: false,
+            html: false,
+            comments_only: false,
+            comment_language: None,
+            readability: false,
+            scan_unicode: false,
+            control_chars: None,
+            encoding: None,
+            bom: BomPolicy::Count,
+            invalid_utf8: None,
+            posix: false,
+            line_endings: false,
+            final_newline: false,
+            time: false,
+        }
+    }
+}
+
+// Per-byte lookup tables for the counting loop, built once per input.
+struct ByteClasses {
+    terminator: u8,
+    separator: [bool; 256],
+    // Also split on the multi-byte UTF-8 encodings of Unicode whitespace
+    unicode: bool,
+}
+
+// Length of the UTF-8 encoded non-ASCII whitespace (per `char::is_whitespace`)
+// at the start of `bytes`, if there is one.
+fn unicode_space_len(bytes: &[u8]) -> Option<usize> {
+    match bytes {
+        [0xC2, 0x85 | 0xA0, ..] => Some(2),
+        [0xE1, 0x9A, 0x80, ..]
+        | [0xE2, 0x80, 0x80..=0x8A | 0xA8 | 0xA9 | 0xAF, ..]
+        | [0xE2, 0x81, 0x9F, ..]
+        | [0xE3, 0x80, 0x80, ..] => Some(3),
+        _ => None,
+    }
+}
+
+impl ByteClasses {
+    fn new(options: &CountOptions) -> Self {
+        let mut separator = [false; 256];
+        match &options.word_delimiters {
+            Some(delimiters) => delimiters.iter().for_each(|&b| separator[b as usize] = true),
+            None => (0..=255u8)
+                .filter(u8::is_ascii_whitespace)
+                .for_each(|b| separator[b as usize] = true),
+        }
+        separator[options.line_terminator as usize] = true;
+
+        Self {
+            terminator: options.line_terminator,
+            separator,
+            unicode: options.word_delimiters.is_none() && !options.posix,
+        }
+    }
+
+    fn is_separator(&self, byte: u8) -> bool {
+        self.separator[byte as usize]
+    }
+
+    // Bytes taken by the separator starting at `bytes[0]`, or 0 for none
+    fn separator_len(&self, bytes: &[u8]) -> usize {
+        match bytes.first() {
+            Some(&byte) if self.is_separator(byte) => 1,
+            Some(&byte) if self.unicode && byte >= 0xC2 => unicode_space_len(bytes).unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    fn ends_with_separator(&self, bytes: &[u8]) -> bool {
+        let ends_with_unicode_space = |len: usize| {
+            bytes.len() >= len && unicode_space_len(&bytes[bytes.len() - len..]) == Some(len)
+        };
+
+        match bytes.last() {
+            Some(&byte) if self.is_separator(byte) => true,
+            Some(_) => self.unicode && (ends_with_unicode_space(2) || ends_with_unicode_space(3)),
+            None => false,
+        }
+    }
+
+    // Characters in the longest run of bytes between separators
+    fn longest_word(&self, bytes: &[u8]) -> usize {
+        self.longest_word_span(bytes).1
+    }
+
+    // The runs of bytes between separators, as `-w` counts them
+    fn words<'a>(&'a self, bytes: &'a [u8]) -> impl Iterator<Item = &'a [u8]> + 'a {
+        let mut index = 0;
+        std::iter::from_fn(move || {
+            // Skip separators up to the next word
+            loop {
+                if index >= bytes.len() {
+                    return None;
+                }
+                match self.separator_len(&bytes[index..]) {
+                    0 => break,
+                    len => index += len,
+                }
+            }
+            let start = index;
+            while index < bytes.len() && self.separator_len(&bytes[index..]) == 0 {
+                index += 1;
+            }
+            Some(&bytes[start..index])
+        })
+    }
+
+    // Where the longest run of bytes between separators is, and its length
+    // in characters; the first of several equally long runs wins
+    fn longest_word_span(&self, bytes: &[u8]) -> (std::ops::Range<usize>, usize) {
+        let (mut longest, mut current, mut index) = ((0..0, 0), 0, 0);
+        let mut start = 0;
+        while index < bytes.len() {
+            match self.separator_len(&bytes[index..]) {
+                0 => {
+                    // Continuation bytes belong to the character before them
+                    if bytes[index] & 0xC0 != 0x80 {
+                        current += 1;
+                    }
+                    index += 1;
+                }
+                len => {
+                    if current > longest.1 {
+                        longest = (start..index, current);
+                    }
+                    current = 0;
+                    index += len;
+                    start = index;
+                }
+            }
+        }
+        if current > longest.1 {
+            longest = (start..index, current);
+        }
+        longest
+    }
+}
+
+// Tracks mapped bytes so that at most `limit` are in use at once. A file
+// larger than the whole budget is still admitted once nothing else is mapped.
+struct MapBudget {
+    limit: u64,
+    in_use: Mutex<u64>,
+    released: Condvar,
+}
+
+struct MapPermit<'a> {
+    budget: &'a MapBudget,
+    bytes: u64,
+}
+
+impl MapBudget {
+    fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            in_use: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+
+    fn acquire(&self, bytes: u64) -> MapPermit<'_> {
+        let bytes = bytes.min(self.limit);
+        let mut in_use = self.in_use.lock().unwrap_or_else(|e| e.into_inner());
+        while *in_use > 0 && *in_use + bytes > self.limit {
+            in_use = self.released.wait(in_use).unwrap_or_else(|e| e.into_inner());
+        }
+        *in_use += bytes;
+        MapPermit { budget: self, bytes }
+    }
+}
+
+impl Drop for MapPermit<'_> {
+    fn drop(&mut self) {
+        let mut in_use = self.budget.in_use.lock().unwrap_or_else(|e| e.into_inner());
+        *in_use -= self.bytes;
+        self.budget.released.notify_all();
+    }
 }
 
 impl WcCounter {
@@ -28,6 +490,38 @@ impl WcCounter {
         Self::default()
     }
 
+    pub fn count(&self, mode: &CountMode) -> usize {
+        match mode {
+            CountMode::Lines => self.lines,
+            CountMode::Words => self.words,
+            CountMode::Bytes => self.bytes,
+            CountMode::Chars => self.chars,
+        }
+    }
+
+    /// Whether `self` and `other` hold the same counts and metrics, whatever
+    /// their filenames, timings, hashes and duplicate markers.
+    pub fn counts_eq(&self, other: &WcCounter) -> bool {
+        let unnamed = |counter: &WcCounter| WcCounter {
+            filename: None,
+            duration: None,
+            duplicate_of: None,
+            hash: None,
+            ..counter.clone()
+        };
+        unnamed(self) == unnamed(other)
+    }
+
+    /// The counts for `modes`, in order, then the filename if there is one,
+    /// separated by spaces as in plain output: `3 12 80 notes.md`.
+    pub fn to_row(&self, modes: &[CountMode]) -> String {
+        let mut parts: Vec<String> = modes.iter().map(|mode| self.count(mode).to_string()).collect();
+        if let Some(filename) = &self.filename {
+            parts.push(filename.clone());
+        }
+        parts.join(" ")
+    }
+
     // Helper method to add counts from another counter
     pub fn add_counts(&mut self, other: &WcCounter) {
         self.lines += other.lines;
@@ -35,6 +529,115 @@ impl WcCounter {
         self.bytes += other.bytes;
         self.chars += other.chars;
         self.max_line_length = self.max_line_length.max(other.max_line_length);
+        self.max_word_length = merge_max(self.max_word_length, other.max_word_length);
+        self.longest_word = match (self.longest_word.take(), other.longest_word.clone()) {
+            (Some(a), Some(b)) => Some(a.longer(b)),
+            (a, b) => a.or(b),
+        };
+        self.min_line_length = match (self.min_line_length, other.min_line_length) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.records = add_optional(self.records, other.records);
+        self.emoji = add_optional(self.emoji, other.emoji);
+        self.control_chars = add_optional(self.control_chars, other.control_chars);
+        self.trailing_whitespace = add_optional(self.trailing_whitespace, other.trailing_whitespace);
+        self.invalid_utf8 = add_optional(self.invalid_utf8, other.invalid_utf8);
+        if let Some(other_matches) = &other.matches {
+            self.matches.get_or_insert_with(MatchCounts::default).add(other_matches);
+        }
+        if let Some(other_csv) = &other.csv {
+            match &mut self.csv {
+                Some(csv) => csv.add(other_csv),
+                None => self.csv = Some(other_csv.clone()),
+            }
+        }
+        if let Some(other_blank) = &other.blank_lines {
+            self.blank_lines.get_or_insert_with(BlankLines::default).add(other_blank);
+        }
+        if let Some(other_indent) = &other.indent_stats {
+            self.indent_stats.get_or_insert_with(IndentStats::default).add(other_indent);
+        }
+        if let Some(other_vocabulary) = &other.vocabulary {
+            self.vocabulary.get_or_insert_with(Vocabulary::default).add(other_vocabulary);
+        }
+        if let Some(other_patch) = &other.patch {
+            self.patch.get_or_insert_with(PatchStats::default).add(other_patch);
+        }
+        if let Some(other_readability) = &other.readability {
+            self.readability.get_or_insert_with(Readability::default).add(other_readability);
+        }
+        if let Some(other_scan) = &other.unicode_scan {
+            self.unicode_scan.get_or_insert_with(UnicodeScan::default).add(other_scan);
+        }
+        if let Some(other_json) = &other.json {
+            match &mut self.json {
+                Some(json) => json.add(other_json),
+                None => self.json = Some(other_json.clone()),
+            }
+        }
+        if let Some(other_stats) = &other.line_stats {
+            match &mut self.line_stats {
+                Some(stats) => stats.add(other_stats),
+                None => self.line_stats = Some(other_stats.clone()),
+            }
+        }
+        if let Some(other_sketch) = &other.line_percentiles {
+            match &mut self.line_percentiles {
+                Some(sketch) => sketch.add(other_sketch),
+                None => self.line_percentiles = Some(other_sketch.clone()),
+            }
+        }
+        if let Some(other_histogram) = &other.byte_histogram {
+            match &mut self.byte_histogram {
+                Some(histogram) => histogram.add(other_histogram),
+                None => self.byte_histogram = Some(other_histogram.clone()),
+            }
+        }
+        if let Some(other_endings) = &other.line_endings {
+            match &mut self.line_endings {
+                Some(endings) => endings.add(other_endings),
+                None => self.line_endings = Some(other_endings.clone()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+impl WcCounter {
+    /// A result for `name` with only a line count, for tests that need a
+    /// few named results.
+    pub(crate) fn named(name: &str, lines: usize) -> Self {
+        WcCounter { lines, filename: Some(name.to_string()), ..WcCounter::default() }
+    }
+}
+
+/// Sums `results` into one counter with no filename, as for a total row.
+pub fn total(results: &[WcCounter]) -> WcCounter {
+    results.iter().sum()
+}
+
+/// Combines two optional maxima, such as the longest word of two inputs:
+/// the larger one, or whichever was measured if only one was.
+pub fn merge_max(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+// Optional counts stay unset unless at least one side was counted
+fn add_optional(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a + b),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Lines, words and bytes, then the filename, like `wc` with no options.
+impl fmt::Display for WcCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_row(&[CountMode::Lines, CountMode::Words, CountMode::Bytes]))
     }
 }
 
@@ -44,6 +647,49 @@ impl std::ops::AddAssign<&WcCounter> for WcCounter {
     }
 }
 
+impl std::ops::AddAssign for WcCounter {
+    fn add_assign(&mut self, other: WcCounter) {
+        self.add_counts(&other);
+    }
+}
+
+impl std::ops::Add<&WcCounter> for WcCounter {
+    type Output = WcCounter;
+
+    fn add(mut self, other: &WcCounter) -> WcCounter {
+        self.add_counts(other);
+        self
+    }
+}
+
+impl std::ops::Add for WcCounter {
+    type Output = WcCounter;
+
+    fn add(self, other: WcCounter) -> WcCounter {
+        self + &other
+    }
+}
+
+/// The total of a set of results, with no filename; see `add_counts` for
+/// how each count is combined.
+impl<'a> std::iter::Sum<&'a WcCounter> for WcCounter {
+    fn sum<I: Iterator<Item = &'a WcCounter>>(iter: I) -> Self {
+        iter.fold(WcCounter::new(), |total, result| total + result)
+    }
+}
+
+impl std::iter::Sum for WcCounter {
+    fn sum<I: Iterator<Item = WcCounter>>(iter: I) -> Self {
+        iter.fold(WcCounter::new(), |total, result| total + &result)
+    }
+}
+
+impl FromIterator<WcCounter> for WcCounter {
+    fn from_iter<I: IntoIterator<Item = WcCounter>>(iter: I) -> Self {
+        iter.into_iter().sum()
+    }
+}
+
 impl Arbitrary for WcCounter {
     type Parameters = ();
     type Strategy = BoxedStrategy<Self>;
@@ -68,22 +714,109 @@ impl Arbitrary for WcCounter {
                     bytes,
                     chars,
                     max_line_length: max_len,
-                    filename 
+                    filename,
+                    ..WcCounter::default()
                 }
             })
             .boxed()
     }
 }
 
+const CHUNK_SIZE: usize = 1024 * 1024; // 1 MB
+
+// Piece size when counting a reader as it is read; several chunks, so each
+// piece is still counted in parallel
+const READ_PIECE_SIZE: usize = 4 * CHUNK_SIZE;
+
+// Where chunk `index` of `bytes` starts: a multiple of CHUNK_SIZE, moved
+// past any UTF-8 continuation bytes so no character is split between chunks.
+// Chunk `index` ends where chunk `index + 1` starts.
+fn chunk_start(bytes: &[u8], index: usize) -> usize {
+    let mut start = index.saturating_mul(CHUNK_SIZE).min(bytes.len());
+    let limit = (start + 3).min(bytes.len());
+    while start < limit && bytes[start] & 0xC0 == 0x80 {
+        start += 1;
+    }
+    start
+}
+
+// Counts for one chunk, plus the lengths of the partial lines at its edges so
+// lines spanning chunks can be stitched back together.
+#[derive(Default)]
+struct ChunkCounts {
+    counts: WcCounter,
+    first_line_length: usize,
+    last_line_length: usize,
+    has_terminator: bool,
+}
+
+impl ChunkCounts {
+    // The counts of `self` followed directly by `next`. Merging is
+    // associative with the default as identity, so chunks can be folded in
+    // any grouping as long as their order is kept.
+    fn merge(mut self, next: ChunkCounts) -> ChunkCounts {
+        // A line open at the end of `self` continues into `next`
+        let spanning = self.last_line_length + next.first_line_length;
+        self.counts += &next.counts;
+        self.counts.max_line_length = self.counts.max_line_length.max(spanning);
+
+        if !self.has_terminator {
+            self.first_line_length = spanning;
+        }
+        self.last_line_length = match next.has_terminator {
+            true => next.last_line_length,
+            false => spanning,
+        };
+        self.has_terminator |= next.has_terminator;
+        self
+    }
+}
+
+// Counts `bytes` a chunk at a time in parallel, folding the chunks' counts
+// together as they finish. `in_word` says whether the byte before `bytes`
+// was part of a word.
+fn scan_chunks(bytes: &[u8], in_word: bool, classes: &ByteClasses) -> ChunkCounts {
+    (0..bytes.len().div_ceil(CHUNK_SIZE))
+        .into_par_iter()
+        .map(|index| {
+            let (start, end) = (chunk_start(bytes, index), chunk_start(bytes, index + 1));
+            // A chunk that starts mid-word continues the word from the previous chunk
+            let in_word = match start {
+                0 => in_word,
+                _ => !classes.ends_with_separator(&bytes[..start]),
+            };
+            process_chunk(&bytes[start..end], in_word, 0, classes)
+        })
+        .reduce(ChunkCounts::default, ChunkCounts::merge)
+}
+
+// Lines-only counting: terminators found with memchr, plus an unterminated
+// final line unless only terminators count
+fn count_lines(bytes: &[u8], terminator: u8, newlines_only: bool) -> usize {
+    let terminated: usize = bytes.par_chunks(CHUNK_SIZE)
+        .map(|chunk| memchr_iter(terminator, chunk).count())
+        .sum();
+    terminated + usize::from(!newlines_only && bytes.last().is_some_and(|&b| b != terminator))
+}
+
 // Common counting logic extracted to a separate function
-fn process_chunk(chunk: &[u8], initial_in_word: bool, initial_line_length: usize) -> WcCounter {
+fn process_chunk(
+    chunk: &[u8],
+    initial_in_word: bool,
+    initial_line_length: usize,
+    classes: &ByteClasses,
+) -> ChunkCounts {
     let mut partial = WcCounter::new();
     let mut in_word = initial_in_word;
     let mut current_line_length = initial_line_length;
+    let mut first_line_length = None;
+    // Remaining bytes of a multi-byte separator being skipped
+    let mut separator_bytes = 0;
 
-    for &byte in chunk {
-        if byte == b'\n' {
+    for (index, &byte) in chunk.iter().enumerate() {
+        if byte == classes.terminator {
             // Capture length before the newline
+            first_line_length.get_or_insert(current_line_length);
             partial.max_line_length = partial.max_line_length.max(current_line_length);
             partial.lines += 1;
             current_line_length = 0;
@@ -94,7 +827,17 @@ fn process_chunk(chunk: &[u8], initial_in_word: bool, initial_line_length: usize
             }
         }
         
-        if byte.is_ascii_whitespace() {
+        let is_separator = if separator_bytes > 0 {
+            separator_bytes -= 1;
+            true
+        } else if byte.is_ascii() && !classes.is_separator(byte) {
+            false
+        } else {
+            separator_bytes = classes.separator_len(&chunk[index..]).saturating_sub(1);
+            classes.is_separator(byte) || separator_bytes > 0
+        };
+
+        if is_separator {
             if in_word {
                 partial.words += 1;
             }
@@ -106,141 +849,1056 @@ fn process_chunk(chunk: &[u8], initial_in_word: bool, initial_line_length: usize
     }
 
     partial.max_line_length = partial.max_line_length.max(current_line_length);
-    partial
+    ChunkCounts {
+        counts: partial,
+        first_line_length: first_line_length.unwrap_or(current_line_length),
+        last_line_length: current_line_length,
+        has_terminator: first_line_length.is_some(),
+    }
+}
+
+fn display_path(path: &Path) -> String {
+    path.to_str()
+        .map(ToString::to_string)
+        .unwrap_or_else(|| path.display().to_string())
 }
 
 pub fn count_file<P: AsRef<Path>>(
     path: P,
     modes: &[CountMode],
+) -> WcResult<WcCounter> {
+    count_file_with(path, modes, &CountOptions::default())
+}
+
+pub fn count_file_with<P: AsRef<Path>>(
+    path: P,
+    modes: &[CountMode],
+    options: &CountOptions,
 ) -> WcResult<WcCounter> {
     let path = path.as_ref();
-    let filename = path.to_str()
-        .map(ToString::to_string)
-        .unwrap_or_else(|| path.display().to_string());
+    let filename = display_path(path);
+    let _span = info_span!("file", path = %filename).entered();
+
+    let started = Instant::now();
+    let mut counter = open_and_count(path, filename, modes, options)?;
+    if options.time {
+        counter.duration = Some(started.elapsed());
+    }
+    Ok(counter)
+}
 
+fn open_and_count(path: &Path, filename: String, modes: &[CountMode], options: &CountOptions) -> WcResult<WcCounter> {
     if path == Path::new("-") {
-        return count_reader(io::stdin().lock(), Some(filename), modes);
+        return count_reader_with(io::stdin().lock(), Some(filename), modes, options);
     }
 
-    let file = fs::File::open(path)
+    let file = debug_span!("open").in_scope(|| fs::File::open(path))
         .map_err(|e| match e.kind() {
             io::ErrorKind::NotFound => WcError::file_not_found(&filename),
             io::ErrorKind::PermissionDenied => WcError::permission_denied(&filename),
-            _ => WcError::Io(e),
+            _ => WcError::from(e).with_path(&filename, "open"),
         })?;
-    
-    let mmap = unsafe { MmapOptions::new().map(&file)? };
-    count_bytes(&mmap, Some(filename), modes)
+
+    let metadata = file.metadata()
+        .map_err(|e| WcError::from(e).with_path(&filename, "inspect"))?;
+    if metadata.is_dir() {
+        return Err(WcError::is_a_directory(filename));
+    }
+    let len = metadata.len();
+
+    if let Some(document) = Document::for_path(path).filter(|_| !options.raw) {
+        return debug_span!("extract", document = document.name()).in_scope(|| {
+            count_document(file, document, filename, modes, options)
+        });
+    }
+
+    // Files in /proc and the like claim to be empty, so those are read
+    if metadata.is_file() && len > 0 && size_suffices(modes, options) {
+        debug!(len, "byte count from metadata");
+        return Ok(WcCounter { bytes: usize::try_from(len).unwrap_or(usize::MAX), filename: Some(filename), ..WcCounter::default() });
+    }
+
+    if let Some(limit) = options.max_memory
+        && len > limit
+    {
+        info!(len, limit, "streaming: larger than --max-memory");
+        return debug_span!("count").in_scope(|| {
+            count_stream(file, Some(filename.clone()), modes, options, stream_buffer_size(limit))
+        })
+        .map_err(|e| e.with_path(filename, "read"));
+    }
+
+    // Empty files can't be mapped, and mappings on network filesystems can
+    // fault if the file is truncated while it is being counted
+    let unmapped_reason = if len == 0 {
+        Some("empty file")
+    } else if options.no_mmap {
+        Some("--no-mmap")
+    } else if filesystem::is_remote(&file) {
+        Some("network filesystem")
+    } else {
+        None
+    };
+    if let Some(reason) = unmapped_reason {
+        info!(reason, "reading instead of mapping");
+        return debug_span!("count").in_scope(|| {
+            count_reader_with(io::BufReader::new(file), Some(filename.clone()), modes, options)
+        })
+        .map_err(|e| e.with_path(filename, "read"));
+    }
+
+    let mmap = debug_span!("map", len).in_scope(|| unsafe { MmapOptions::new().map(&file) })
+        .map_err(|e| WcError::from(e).with_path(&filename, "map"))?;
+    debug_span!("count").in_scope(|| count_bytes_with(&mmap, Some(filename), modes, options))
+}
+
+// Counts a document's text, or its content as is when it doesn't look like
+// the document its name says
+fn count_document(mut file: fs::File, document: Document, filename: String, modes: &[CountMode], options: &CountOptions) -> WcResult<WcCounter> {
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).map_err(|e| WcError::from(e).with_path(&filename, "read"))?;
+    let text = document.extract(&bytes).map_err(|e| match e {
+        WcError::Extract(message) => WcError::Extract(format!("{}: {}", filename, message)),
+        other => other,
+    })?;
+
+    match text {
+        Some(text) => count_bytes_with(text.as_bytes(), Some(filename), modes, options).map(|mut counter| {
+            counter.extracted_from = Some(document.name());
+            counter
+        }),
+        None => count_bytes_with(&bytes, Some(filename), modes, options),
+    }
 }
 
 pub fn count_reader<R: BufRead>(
-    mut reader: R,
+    reader: R,
     filename: Option<String>,
     modes: &[CountMode],
 ) -> WcResult<WcCounter> {
-    let mut buffer = Vec::new();
-    reader.read_to_end(&mut buffer)?;
-    count_bytes(&buffer, filename, modes)
+    count_reader_with(reader, filename, modes, &CountOptions::default())
 }
 
-pub fn count_bytes(
-    bytes: &[u8],
+pub fn count_reader_with<R: BufRead>(
+    reader: R,
     filename: Option<String>,
     modes: &[CountMode],
+    options: &CountOptions,
 ) -> WcResult<WcCounter> {
-    let mut counter = WcCounter {
-        filename,
-        ..Default::default()
+    // Nothing past the end of --bytes-range is needed
+    let end = options.bytes_range.and_then(|range| range.end).unwrap_or(u64::MAX);
+    let mut reader = reader.take(end);
+    let mut buffer = Vec::new();
+    let Some(limit) = options.max_memory else {
+        // Count as the input is read when nothing needs all of it at once;
+        // a stripped BOM may mean decoding UTF-16, which does
+        if whole_input_option(options).is_none() && options.bom != BomPolicy::Strip {
+            return count_stream(reader, filename, modes, options, READ_PIECE_SIZE);
+        }
+        reader.read_to_end(&mut buffer)?;
+        return count_bytes_with(&buffer, filename, modes, options);
     };
 
-    if modes.contains(&CountMode::Bytes) {
-        counter.bytes = bytes.len();
+    // Only inputs that turn out to be over the budget are streamed
+    reader.by_ref().take(limit.saturating_add(1)).read_to_end(&mut buffer)?;
+    if buffer.len() as u64 <= limit {
+        return count_bytes_with(&buffer, filename, modes, options);
     }
+    count_stream(Cursor::new(buffer).chain(reader), filename, modes, options, stream_buffer_size(limit))
+}
 
-    if modes.iter().any(|m| matches!(m, CountMode::Lines | CountMode::Words | CountMode::Chars)) {
-        // Process chunks in parallel for large files
-        const CHUNK_SIZE: usize = 1024 * 1024; // 1 MB
-        
-        let chunks = bytes.par_chunks(CHUNK_SIZE);
-        let partial_counts: Vec<_> = chunks
-            .map(|chunk| process_chunk(chunk, false, 0))
-            .collect();
+// Whether a regular file's size is all there is to count: bytes only, and
+// nothing that has to look at the content (such as a BOM check)
+fn size_suffices(modes: &[CountMode], options: &CountOptions) -> bool {
+    modes.iter().all(|mode| *mode == CountMode::Bytes)
+        && !options.force_read
+        && options.bom != BomPolicy::Error
+        && options.record_separator.is_none()
+        && options.match_pattern.is_none()
+        && options.bytes_range.is_none()
+        && options.hash.is_none()
+        && whole_input_option(options).is_none()
+        && options.control_chars.is_none()
+        && !(options.entropy || options.line_endings || options.final_newline)
+}
 
-        for partial in &partial_counts {
-            counter += partial;
-        }
+// Piece size for streaming under a --max-memory budget of `limit` bytes
+fn stream_buffer_size(limit: u64) -> usize {
+    usize::try_from(limit).unwrap_or(usize::MAX).clamp(64, CHUNK_SIZE)
+}
 
-        // Handle potential partial word at the end
-        if bytes.last().map_or(false, |&b| !b.is_ascii_whitespace()) {
-            counter.words += 1;
-        }
+// The option, if any, whose count needs the whole input in memory at once,
+// often because what it counts can run across the pieces streamed input is
+// read in, such as a grapheme or emoji sequence, a sentence, an HTML tag or
+// script, or a block comment or string
+fn whole_input_option(options: &CountOptions) -> Option<&'static str> {
+    [
+        (options.csv, "--csv"),
+        (options.json_stats, "--json-stats"),
+        (options.show_longest, "--show-longest"),
+        (options.line_stats, "--line-stats"),
+        (options.per_line, "--per-line"),
+        (options.long_lines.is_some() || options.indent_style.is_some(), "lint"),
+        (options.blank_lines, "--blank-lines"),
+        (options.indent_stats, "--indent-stats"),
+        (options.trailing_whitespace, "--trailing-whitespace"),
+        (options.line_percentiles, "--percentiles"),
+        (options.max_word_length, "--max-word-length"),
+        (options.show_longest_word.is_some(), "--show-longest-word"),
+        (options.vocabulary, "--ttr"),
+        (options.min_line_length.is_some(), "--min-line-length"),
+        (options.encoding.is_some(), "--encoding"),
+        (options.line_filter.is_some(), "--filter"),
+        (options.lines_range.is_some(), "--lines-range"),
+        (options.skip_lines > 0, "--skip-lines"),
+        (options.skip_footer > 0, "--skip-footer"),
+        (options.char_unit == CharUnit::Graphemes, "--chars=graphemes"),
+        (options.emoji, "--emoji"),
+        // A hunk header says how many of the following lines it covers
+        (options.diff_input, "--diff-input"),
+        (options.html, "--html"),
+        (options.comments_only, "--comments-only"),
+        (options.readability, "readability"),
+        (options.scan_unicode, "--scan-unicode"),
+    ]
+    .into_iter()
+    .find_map(|(enabled, flag)| enabled.then_some(flag))
+}
 
-        if modes.contains(&CountMode::Chars) {
-            counter.chars = match std::str::from_utf8(bytes) {
-                Ok(s) => s.chars().count(),
-                Err(_) => bytes.len(),
-            }
+// Length of the prefix of `bytes` that doesn't end partway through a UTF-8
+// sequence; the rest is carried over to the next piece.
+fn complete_prefix_len(bytes: &[u8]) -> usize {
+    let tail_start = bytes.len().saturating_sub(3);
+    for index in (tail_start..bytes.len()).rev() {
+        let byte = bytes[index];
+        if byte & 0xC0 != 0x80 {
+            let needed = match byte {
+                0xF0.. => 4,
+                0xE0.. => 3,
+                0xC0.. => 2,
+                _ => 1,
+            };
+            return if bytes.len() - index < needed { index } else { bytes.len() };
         }
     }
-
-    Ok(counter)
+    bytes.len()
 }
 
-pub fn count_files<P: AsRef<Path> + Sync>(
-    paths: &[P],
-    modes: &[CountMode]
-) -> WcResult<Vec<WcCounter>> {
-    paths.par_iter()
-        .map(|path| count_file(path, modes))
-        .collect()
-}
+// Counts `reader` a piece of `buffer_size` bytes at a time, for inputs over
+// the --max-memory budget and readers that needn't be buffered. Gives the
+// same counts as `count_bytes_with`, but refuses counts that need the whole
+// input at once.
+fn count_stream<R: Read>(
+    mut reader: R,
+    filename: Option<String>,
+    modes: &[CountMode],
+    options: &CountOptions,
+    buffer_size: usize,
+) -> WcResult<WcCounter> {
+    let name = filename.as_deref().unwrap_or("-").to_string();
+    if let Some(flag) = whole_input_option(options) {
+        return Err(WcError::invalid_argument(format!(
+            "{} is larger than --max-memory, but {} needs the whole input in memory", name, flag
+        )));
+    }
 
+    let (skip, limit) = options.bytes_range.map_or((0, u64::MAX), |range| {
+        (range.start, range.end.map_or(u64::MAX, |end| end - range.start))
+    });
+    io::copy(&mut reader.by_ref().take(skip), &mut io::sink())?;
+    let mut reader = reader.take(limit);
 
-#[cfg(test)]
-mod counter_tests {
-    use super::*;
-    use std::io::Cursor;
+    let classes = ByteClasses::new(options);
+    let counts_text = modes.iter().any(|m| matches!(m, CountMode::Lines | CountMode::Words | CountMode::Chars));
+    let scans_bytes = modes.iter().any(|m| matches!(m, CountMode::Words | CountMode::Chars)) || options.max_line_length;
+    let policy = options.invalid_utf8.unwrap_or(InvalidUtf8::Bytes);
+    let mut counter = WcCounter { filename, ..Default::default() };
+    let mut records = options.record_separator.as_deref().map(RecordCounter::new);
+    let mut matches = options.match_pattern.as_ref().map(|regex| MatchCounter::new(regex, options.line_terminator));
+    let mut endings = options.line_endings.then(LineEndings::default);
+    let mut histogram = options.entropy.then(ByteHistogram::default);
+    let mut control_chars = options.control_chars.map(|scope| (scope, 0));
+    let mut hasher = options.hash.map(ContentHasher::new);
 
-    #[test]
-    fn test_wc_counter() {
-        let counter = WcCounter::new();
-        assert_eq!(counter.lines, 0);
-        assert_eq!(counter.words, 0);
-        assert_eq!(counter.bytes, 0);
-        assert_eq!(counter.chars, 0);
-        assert_eq!(counter.max_line_length, 0);
-        assert!(counter.filename.is_none());
-    }
+    let mut buffer = vec![0; buffer_size];
+    let mut filled = 0;
+    let mut first_piece = true;
+    let mut raw_len = 0;
+    let mut text_len = 0;
+    let (mut chars, mut invalid) = (0, 0);
+    let mut scanned = ChunkCounts::default();
+    let mut in_word = false;
+    let mut last_byte = None;
 
-    #[test]
-    fn test_wc_counter_add_assign() {
-        let mut counter1 = WcCounter {
-            lines: 10,
-            words: 20,
-            bytes: 30,
-            chars: 40,
-            max_line_length: 50,
-            filename: Some("file11".to_string()),
+    loop {
+        let read = match reader.read(&mut buffer[filled..]) {
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
         };
+        if let Some(histogram) = &mut histogram {
+            histogram.add(&ByteHistogram::from_bytes(&buffer[filled..filled + read]));
+        }
+        if let Some(hasher) = &mut hasher {
+            hasher.update(&buffer[filled..filled + read]);
+        }
+        raw_len += read;
+        filled += read;
 
-        let counter2 = WcCounter {
-            lines: 5,
-            words: 15,
-            bytes: 25,
-            chars: 35,
-            max_line_length: 60,
-            filename: Some("file2".to_string()),
-        };
+        let at_end = read == 0;
+        if !at_end && filled < buffer.len() {
+            continue;
+        }
 
-        counter1 += &counter2;
-        assert_eq!(counter1.lines, 15);
-        assert_eq!(counter1.words, 35);
-        assert_eq!(counter1.bytes, 55);
-        assert_eq!(counter1.chars, 75);
-        assert_eq!(counter1.max_line_length, 60);
+        let end = if at_end { filled } else { complete_prefix_len(&buffer[..filled]) };
+        let mut start = 0;
+
+        if first_piece {
+            first_piece = false;
+            match (options.bom, Encoding::for_bom(&buffer[..end])) {
+                (BomPolicy::Error, Some((bom, _))) => {
+                    return Err(WcError::UnexpectedBom(format!("{} starts with a {} BOM", name, bom.name())));
+                }
+                (BomPolicy::Strip, Some((bom, length))) if bom == UTF_8 => start = length,
+                (BomPolicy::Strip, Some((bom, _))) => {
+                    return Err(WcError::invalid_argument(format!(
+                        "{} is larger than --max-memory, but decoding its {} BOM needs the whole input in memory",
+                        name, bom.name()
+                    )));
+                }
+                _ => {}
+            }
+        }
+
+        let text = &buffer[start..end];
+        text_len += text.len();
+
+        if let Some(records) = &mut records {
+            records.feed(text);
+        }
+
+        if let Some(matches) = &mut matches {
+            matches.feed(text);
+        }
+
+        if let Some((scope, count)) = &mut control_chars {
+            *count += count_control_chars(text, *scope);
+        }
+
+        if let Some(endings) = &mut endings {
+            let mut piece = LineEndings::from_bytes(text);
+            // A CRLF split between pieces was seen as a CR and then an LF
+            if last_byte == Some(b'\r') && text.first() == Some(&b'\n') {
+                endings.cr -= 1;
+                piece.lf -= 1;
+                piece.crlf += 1;
+            }
+            endings.add(&piece);
+        }
+
+        if counts_text && !scans_bytes {
+            scanned.counts.lines += count_lines(text, classes.terminator, true);
+        } else if counts_text && !text.is_empty() {
+            scanned = scanned.merge(scan_chunks(text, in_word, &classes));
+            in_word = !classes.ends_with_separator(text);
+
+            if modes.contains(&CountMode::Chars) {
+                let piece_policy = if policy == InvalidUtf8::Error { policy } else { InvalidUtf8::Skip };
+                let (piece_chars, piece_invalid) = count_chars(text, piece_policy)?;
+                chars += piece_chars;
+                invalid += piece_invalid;
+            }
+        }
+
+        if let Some(&byte) = text.last() {
+            last_byte = Some(byte);
+        }
+
+        if at_end {
+            break;
+        }
+        buffer.copy_within(end..filled, 0);
+        filled -= end;
+    }
+
+    if modes.contains(&CountMode::Bytes) {
+        counter.bytes = raw_len;
+    }
+    counter.records = records.map(|records| records.count());
+    counter.matches = matches.map(MatchCounter::finish);
+    counter.line_endings = endings;
+    counter.byte_histogram = histogram;
+    counter.control_chars = control_chars.map(|(_, count)| count);
+    counter.hash = hasher.map(ContentHasher::finish);
+    if options.final_newline {
+        counter.missing_final_newline = Some(last_byte.is_some_and(|b| b != options.line_terminator));
+    }
+
+    if counts_text {
+        counter += &scanned.counts;
+        if in_word {
+            counter.words += 1;
+        }
+        if !options.newlines_only && last_byte.is_some_and(|b| b != classes.terminator) {
+            counter.lines += 1;
+        }
+        if modes.contains(&CountMode::Chars) {
+            counter.chars = match options.char_unit {
+                CharUnit::Bytes => text_len,
+                _ => chars_under(policy, chars, invalid, text_len),
+            };
+            if options.invalid_utf8.is_some() {
+                counter.invalid_utf8 = Some(invalid);
+            }
+        }
+    }
+
+    Ok(counter)
+}
+
+pub fn count_bytes(
+    bytes: &[u8],
+    filename: Option<String>,
+    modes: &[CountMode],
+) -> WcResult<WcCounter> {
+    count_bytes_with(bytes, filename, modes, &CountOptions::default())
+}
+
+pub fn count_bytes_with(
+    bytes: &[u8],
+    filename: Option<String>,
+    modes: &[CountMode],
+    options: &CountOptions,
+) -> WcResult<WcCounter> {
+    let classes = ByteClasses::new(options);
+    let mut counter = WcCounter {
+        filename,
+        ..Default::default()
+    };
+
+    // Bytes and entropy describe the raw input, cut to the requested
+    // range; everything else is counted on the text transcoded to UTF-8
+    let raw = match &options.bytes_range {
+        Some(range) => &bytes[range.clamp(bytes.len())],
+        None => bytes,
+    };
+    let raw = match &options.lines_range {
+        Some(range) => select_lines(raw, options.line_terminator, range),
+        None => raw,
+    };
+
+    // A stripped BOM also settles the encoding unless one was named
+    let (content, encoding) = match (options.bom, Encoding::for_bom(raw)) {
+        (BomPolicy::Error, Some((bom, _))) => {
+            return Err(WcError::UnexpectedBom(format!(
+                "{} starts with a {} BOM",
+                counter.filename.as_deref().unwrap_or("-"),
+                bom.name(),
+            )));
+        }
+        (BomPolicy::Strip, Some((bom, length))) => {
+            let encoding = match options.encoding {
+                Some(InputEncoding::Named(named)) => Some(InputEncoding::Named(named)),
+                None if bom == UTF_8 => None,
+                _ => Some(InputEncoding::Named(bom)),
+            };
+            (&raw[length..], encoding)
+        }
+        _ => (raw, options.encoding),
+    };
+
+    let decoded = encoding.map(|encoding| decode(content, &encoding));
+    let bytes = match &decoded {
+        Some((text, encoding)) => {
+            counter.encoding = Some(encoding.name().to_string());
+            text.as_bytes()
+        }
+        None => content,
+    };
+
+    // Line endings and the final newline are checks on the file as it is,
+    // so they look at the text before any of it is rendered or stripped
+    if options.line_endings {
+        counter.line_endings = Some(LineEndings::from_bytes(bytes));
+    }
+
+    if options.final_newline {
+        counter.missing_final_newline = Some(bytes.last().is_some_and(|&b| b != options.line_terminator));
+    }
+
+    let rendered = options.html.then(|| rendered_text(bytes));
+    let bytes = rendered.as_deref().unwrap_or(bytes);
+
+    // Files in a language without a known comment syntax have no comments
+    let comments = options.comments_only.then(|| {
+        let language = options.comment_language
+            .or_else(|| counter.filename.as_deref().and_then(|name| Language::for_path(Path::new(name))));
+        counter.comment_language = language.map(|language| language.name());
+        language.map_or_else(Vec::new, |language| comment_text(bytes, language))
+    });
+    let bytes = comments.as_deref().unwrap_or(bytes);
+
+    let bytes = trim_lines(bytes, options.line_terminator, options.skip_lines, options.skip_footer);
+    let filtered = options.line_filter.as_ref().map(|filter| filter.apply(bytes, options.line_terminator));
+    let bytes = filtered.as_deref().unwrap_or(bytes);
+
+    if modes.contains(&CountMode::Bytes) {
+        counter.bytes = raw.len();
+    }
+
+    if let Some(separator) = &options.record_separator {
+        counter.records = Some(count_records(bytes, separator));
+    }
+
+    if let Some(regex) = &options.match_pattern {
+        counter.matches = Some(count_matches(bytes, regex, options.line_terminator));
+    }
+
+    if options.csv {
+        counter.csv = Some(csv_stats(bytes)?);
+    }
+
+    if options.json_stats {
+        counter.json = Some(json_stats(bytes)?);
+    }
+
+    if options.show_longest {
+        counter.longest = longest_line(bytes, options.line_terminator, options.longest_content_width);
+    }
+
+    if options.line_stats {
+        counter.line_stats = Some(LineStats::from_bytes(bytes, options.line_terminator));
+    }
+
+    if options.per_line {
+        counter.line_widths = Some(line_widths(bytes, options.line_terminator));
+    }
+
+    if let Some(limit) = options.long_lines {
+        counter.long_lines = Some(long_lines(bytes, options.line_terminator, limit));
+    }
+
+    if let Some(style) = options.indent_style {
+        counter.wrong_indent = Some(wrong_indent_lines(bytes, options.line_terminator, style));
+    }
+
+    if options.blank_lines {
+        counter.blank_lines = Some(BlankLines::from_bytes(bytes, options.line_terminator));
+    }
+
+    if options.indent_stats {
+        counter.indent_stats = Some(IndentStats::from_bytes(bytes, options.line_terminator));
+    }
+
+    if options.trailing_whitespace {
+        counter.trailing_whitespace = Some(trailing_whitespace_lines(bytes, options.line_terminator));
+    }
+
+    if options.line_percentiles {
+        counter.line_percentiles = Some(QuantileSketch::of_line_lengths(bytes, options.line_terminator));
+    }
+
+    if options.entropy {
+        counter.byte_histogram = Some(ByteHistogram::from_bytes(raw));
+    }
+
+    if let Some(algorithm) = options.hash {
+        counter.hash = Some(hash_bytes(raw, algorithm));
+    }
+
+    if options.max_word_length {
+        counter.max_word_length = Some(classes.longest_word(bytes));
+    }
+
+    if options.vocabulary {
+        counter.vocabulary = Some(Vocabulary::from_words(classes.words(bytes)));
+    }
+
+    if let Some(width) = options.show_longest_word {
+        let (span, length) = classes.longest_word_span(bytes);
+        counter.longest_word = Some(LongestWord { word: truncate(&bytes[span], width), length });
+    }
+
+    if let Some(scope) = options.min_line_length {
+        counter.min_line_length = shortest_line(bytes, options.line_terminator, scope == MinLineScope::NonEmpty);
+    }
+
+    if options.emoji {
+        counter.emoji = Some(count_emoji(bytes));
+    }
+
+    if options.diff_input {
+        counter.patch = Some(PatchStats::from_bytes(bytes, options.line_terminator));
+    }
+
+    if options.readability {
+        counter.readability = Some(Readability::from_bytes(bytes));
+    }
+
+    if options.scan_unicode {
+        counter.unicode_scan = Some(scan_unicode(bytes, options.line_terminator));
+    }
+
+    if let Some(scope) = options.control_chars {
+        counter.control_chars = Some(count_control_chars(bytes, scope));
+    }
+
+    let scans_bytes = modes.iter().any(|m| matches!(m, CountMode::Words | CountMode::Chars)) || options.max_line_length;
+    if !scans_bytes && modes.contains(&CountMode::Lines) {
+        counter.lines = count_lines(bytes, classes.terminator, options.newlines_only);
+    } else if modes.iter().any(|m| matches!(m, CountMode::Lines | CountMode::Words | CountMode::Chars)) {
+        // Process chunks in parallel for large files
+        counter += &scan_chunks(bytes, false, &classes).counts;
+
+        // Handle potential partial word at the end
+        if !bytes.is_empty() && !classes.ends_with_separator(bytes) {
+            counter.words += 1;
+        }
+
+        // An unterminated final line still counts as a line
+        if !options.newlines_only && bytes.last().is_some_and(|&b| b != classes.terminator) {
+            counter.lines += 1;
+        }
+
+        if modes.contains(&CountMode::Chars) {
+            let policy = options.invalid_utf8.unwrap_or(InvalidUtf8::Bytes);
+            let (chars, invalid) = count_chars_as(bytes, options.char_unit, policy)?;
+            counter.chars = chars;
+            if options.invalid_utf8.is_some() {
+                counter.invalid_utf8 = Some(invalid);
+            }
+        }
+    }
+
+    Ok(counter)
+}
+
+pub fn count_files<P: AsRef<Path> + Sync>(
+    paths: &[P],
+    modes: &[CountMode]
+) -> WcResult<Vec<WcCounter>> {
+    count_files_with(paths, modes, &CountOptions::default())
+}
+
+pub fn count_files_with<P: AsRef<Path> + Sync>(
+    paths: &[P],
+    modes: &[CountMode],
+    options: &CountOptions,
+) -> WcResult<Vec<WcCounter>> {
+    count_files_each(paths, modes, options).into_iter().collect()
+}
+
+/// Counts every path even when some of them fail, returning each file's
+/// result in the order of `paths`.
+pub fn count_files_each<P: AsRef<Path> + Sync>(
+    paths: &[P],
+    modes: &[CountMode],
+    options: &CountOptions,
+) -> Vec<WcResult<WcCounter>> {
+    match budget_limit(options) {
+        Some(limit) => {
+            let (sender, receiver) = mpsc::channel();
+            send_files_bounded(paths, modes, options, &MapBudget::new(limit), sender);
+            in_path_order(receiver.into_iter().collect())
+        }
+        None => count_files_unbounded(paths, modes, options),
+    }
+}
+
+/// Like `count_files_with`, but hands each result to `on_result`, with its
+/// index in `paths`, as soon as it is ready. Results arrive in order of
+/// completion, with the largest files started first.
+pub fn count_files_streaming<P, F>(
+    paths: &[P],
+    modes: &[CountMode],
+    options: &CountOptions,
+    mut on_result: F,
+) where
+    P: AsRef<Path> + Sync,
+    F: FnMut(usize, WcResult<WcCounter>),
+{
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        scope.spawn(move || match budget_limit(options) {
+            Some(limit) => send_files_bounded(paths, modes, options, &MapBudget::new(limit), sender),
+            None => {
+                let order = largest_first((0..paths.len()).collect(), &file_sizes(paths));
+                order.into_par_iter()
+                    .with_max_len(1)
+                    .for_each_with(sender, |sender, index| {
+                        let _ = sender.send((index, count_file_with(&paths[index], modes, options)));
+                    });
+            }
+        });
+
+        for (index, result) in receiver {
+            on_result(index, result);
+        }
+    });
+}
+
+// A file found by the walk and waiting to be counted
+struct Pending<K> {
+    size: u64,
+    found: Reverse<usize>,
+    key: K,
+    path: PathBuf,
+}
+
+// The largest pending file is counted first, then the one found first
+impl<K> Ord for Pending<K> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.size, self.found).cmp(&(other.size, other.found))
+    }
+}
+
+impl<K> PartialOrd for Pending<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K> PartialEq for Pending<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl<K> Eq for Pending<K> {}
+
+enum Admission<K> {
+    Found(Pending<K>),
+    Counted,
+    Walked,
+}
+
+/// Counts files as they arrive on `paths`, so counting starts while they are
+/// still being found, e.g. by a parallel directory walk. Each file comes
+/// with a key that is handed back, along with its path, to `on_result` as
+/// soon as the file is counted. Returns once `paths` is closed and every
+/// file is counted.
+pub fn count_files_pipelined<K, F>(
+    paths: mpsc::Receiver<(K, PathBuf)>,
+    modes: &[CountMode],
+    options: &CountOptions,
+    mut on_result: F,
+) where
+    K: Send,
+    F: FnMut(K, PathBuf, WcResult<WcCounter>),
+{
+    let (sender, receiver) = mpsc::channel();
+    let (admissions, admitted) = mpsc::channel();
+
+    thread::scope(|scope| {
+        let found = admissions.clone();
+        scope.spawn(move || {
+            for (index, (key, path)) in paths.into_iter().enumerate() {
+                let size = fs::metadata(&path).map_or(0, |m| m.len());
+                let _ = found.send(Admission::Found(Pending { size, found: Reverse(index), key, path }));
+            }
+            let _ = found.send(Admission::Walked);
+        });
+
+        // Keeps only as many files running as there are threads, so that
+        // of the files found so far the largest is started first, as
+        // largest_first does for paths known up front
+        scope.spawn(move || {
+            let budget = budget_limit(options).map(MapBudget::new);
+            let slots = rayon::current_num_threads();
+            let mut pending = BinaryHeap::new();
+            let (mut walking, mut running) = (true, 0);
+
+            rayon::in_place_scope(|pool| loop {
+                while running < slots && let Some(file) = pending.pop() {
+                    let Pending { size, key, path, .. } = file;
+                    let permit = budget.as_ref().map(|budget| budget.acquire(budgeted_size(size, options)));
+                    let (sender, admissions) = (sender.clone(), admissions.clone());
+                    running += 1;
+
+                    pool.spawn(move |_| {
+                        let result = count_file_with(&path, modes, options);
+                        drop(permit);
+                        let _ = sender.send((key, path, result));
+                        let _ = admissions.send(Admission::Counted);
+                    });
+                }
+                if !walking && pending.is_empty() {
+                    break;
+                }
+
+                let Ok(next) = admitted.recv() else { break };
+                for admission in std::iter::once(next).chain(admitted.try_iter()) {
+                    match admission {
+                        Admission::Found(file) => pending.push(file),
+                        Admission::Counted => running -= 1,
+                        Admission::Walked => walking = false,
+                    }
+                }
+            });
+        });
+
+        for (key, path, result) in receiver {
+            on_result(key, path, result);
+        }
+    });
+}
+
+// Bytes that may be mapped or held at once, from --max-mapped and --max-memory
+fn budget_limit(options: &CountOptions) -> Option<u64> {
+    match (options.max_mapped, options.max_memory) {
+        (Some(mapped), Some(memory)) => Some(mapped.min(memory)),
+        (mapped, memory) => mapped.or(memory),
+    }
+}
+
+// Sizes of `paths`, with 0 for anything that can't be inspected
+fn file_sizes<P: AsRef<Path>>(paths: &[P]) -> Vec<u64> {
+    paths.iter()
+        .map(|path| fs::metadata(path.as_ref()).map_or(0, |m| m.len()))
+        .collect()
+}
+
+// `indices` reordered from the largest file to the smallest
+fn largest_first(mut indices: Vec<usize>, sizes: &[u64]) -> Vec<usize> {
+    indices.sort_by_key(|&index| Reverse(sizes[index]));
+    indices
+}
+
+// Counts the paths at `indices`, each as its own task in the given order.
+// Starting with the largest files keeps one big file from being left to run
+// alone at the end; count_bytes_with splits big files across threads too.
+fn count_indices<P: AsRef<Path> + Sync>(
+    paths: &[P],
+    indices: Vec<usize>,
+    modes: &[CountMode],
+    options: &CountOptions,
+) -> Vec<(usize, WcResult<WcCounter>)> {
+    indices.into_par_iter()
+        .with_max_len(1)
+        .map(|index| (index, count_file_with(&paths[index], modes, options)))
+        .collect()
+}
+
+// Puts results back in the order of the paths they came from
+fn in_path_order(mut results: Vec<(usize, WcResult<WcCounter>)>) -> Vec<WcResult<WcCounter>> {
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(not(all(feature = "io-uring", target_os = "linux")))]
+fn count_files_unbounded<P: AsRef<Path> + Sync>(
+    paths: &[P],
+    modes: &[CountMode],
+    options: &CountOptions,
+) -> Vec<WcResult<WcCounter>> {
+    let order = largest_first((0..paths.len()).collect(), &file_sizes(paths));
+    in_path_order(count_indices(paths, order, modes, options))
+}
+
+// Small files are read a batch at a time through io_uring and counted in
+// parallel, alongside the larger files counted the usual way. Everything is
+// counted the usual way when the ring is unavailable.
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+fn count_files_unbounded<P: AsRef<Path> + Sync>(
+    paths: &[P],
+    modes: &[CountMode],
+    options: &CountOptions,
+) -> Vec<WcResult<WcCounter>> {
+    use crate::uring::{UringReader, SMALL_FILE_LIMIT};
+
+    let sizes = file_sizes(paths);
+    let count_usual_way = || in_path_order(count_indices(paths, largest_first((0..paths.len()).collect(), &sizes), modes, options));
+    if size_suffices(modes, options) {
+        return count_usual_way();
+    }
+    let Some(mut reader) = UringReader::new() else {
+        info!("io_uring is unavailable, reading small files one at a time");
+        return count_usual_way();
+    };
+
+    // Documents are read whole for their text, so aren't batched
+    let (small, large): (Vec<usize>, Vec<usize>) = (0..paths.len())
+        .partition(|&index| {
+            sizes[index] <= SMALL_FILE_LIMIT && (options.raw || Document::for_path(paths[index].as_ref()).is_none())
+        });
+
+    let (mut results, small_results) = rayon::join(
+        || count_indices(paths, largest_first(large, &sizes), modes, options),
+        || {
+            let mut results = Vec::with_capacity(small.len());
+            for batch in small.chunks(reader.batch_size()) {
+                let batch_paths: Vec<&Path> = batch.iter().map(|&index| paths[index].as_ref()).collect();
+                let contents = reader.read_small_files(&batch_paths);
+                results.par_extend(batch.par_iter().zip(contents).map(|(&index, content)| {
+                    let result = match content {
+                        Some(bytes) => {
+                            let filename = display_path(paths[index].as_ref());
+                            let _span = info_span!("file", path = %filename, io_uring = true).entered();
+                            let started = Instant::now();
+                            count_bytes_with(&bytes, Some(filename), modes, options).map(|mut counter| {
+                                if options.time {
+                                    counter.duration = Some(started.elapsed());
+                                }
+                                counter
+                            })
+                        }
+                        None => count_file_with(&paths[index], modes, options),
+                    };
+                    (index, result)
+                }));
+            }
+            results
+        },
+    );
+
+    results.extend(small_results);
+    in_path_order(results)
+}
+
+// Bytes a file of `size` holds in memory while counted; a streamed file
+// only ever holds one piece
+fn budgeted_size(size: u64, options: &CountOptions) -> u64 {
+    match options.max_memory {
+        Some(memory) if size > memory => stream_buffer_size(memory) as u64,
+        _ => size,
+    }
+}
+
+// Admits files one at a time from the calling thread (not a pool worker), so
+// no counting job ever blocks on the budget while holding a permit itself.
+// Each result is sent with its index as soon as it is ready.
+fn send_files_bounded<P: AsRef<Path> + Sync>(
+    paths: &[P],
+    modes: &[CountMode],
+    options: &CountOptions,
+    budget: &MapBudget,
+    sender: mpsc::Sender<(usize, WcResult<WcCounter>)>,
+) {
+    let sizes = file_sizes(paths);
+
+    rayon::in_place_scope(|scope| {
+        for index in largest_first((0..paths.len()).collect(), &sizes) {
+            let path = &paths[index];
+            let permit = budget.acquire(budgeted_size(sizes[index], options));
+            let sender = sender.clone();
+
+            scope.spawn(move |_| {
+                let result = count_file_with(path, modes, options);
+                drop(permit);
+                let _ = sender.send((index, result));
+            });
+        }
+    });
+}
+
+
+#[cfg(test)]
+mod counter_tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_wc_counter() {
+        let counter = WcCounter::new();
+        assert_eq!(counter.lines, 0);
+        assert_eq!(counter.words, 0);
+        assert_eq!(counter.bytes, 0);
+        assert_eq!(counter.chars, 0);
+        assert_eq!(counter.max_line_length, 0);
+        assert!(counter.filename.is_none());
+    }
+
+    #[test]
+    fn test_wc_counter_add_assign() {
+        let mut counter1 = WcCounter {
+            lines: 10,
+            words: 20,
+            bytes: 30,
+            chars: 40,
+            max_line_length: 50,
+            filename: Some("file11".to_string()),
+            ..WcCounter::default()
+        };
+
+        let counter2 = WcCounter {
+            lines: 5,
+            words: 15,
+            bytes: 25,
+            chars: 35,
+            max_line_length: 60,
+            filename: Some("file2".to_string()),
+            ..WcCounter::default()
+        };
+
+        counter1 += &counter2;
+        assert_eq!(counter1.lines, 15);
+        assert_eq!(counter1.words, 35);
+        assert_eq!(counter1.bytes, 55);
+        assert_eq!(counter1.chars, 75);
+        assert_eq!(counter1.max_line_length, 60);
         assert_eq!(counter1.filename, Some("file11".to_string()));
     }
 
+    #[test]
+    fn test_counter_sum() {
+        let results = [
+            WcCounter { lines: 1, words: 2, max_line_length: 7, filename: Some("a".into()), ..WcCounter::default() },
+            WcCounter { lines: 3, words: 4, max_line_length: 5, filename: Some("b".into()), ..WcCounter::default() },
+        ];
+
+        let total: WcCounter = results.iter().sum();
+        assert_eq!((total.lines, total.words, total.max_line_length), (4, 6, 7));
+        assert_eq!(super::total(&results).words, 6);
+        assert_eq!(total.filename, None);
+
+        let owned: WcCounter = results.clone().into_iter().collect();
+        assert_eq!((owned.lines, owned.words), (4, 6));
+        let added = results[0].clone() + results[1].clone();
+        assert_eq!(added.lines, 4);
+        assert_eq!(added.filename, Some("a".into()));
+        assert_eq!(std::iter::empty::<WcCounter>().sum::<WcCounter>().lines, 0);
+    }
+
+    #[test]
+    fn test_counter_display() {
+        let counter = WcCounter { lines: 3, words: 12, bytes: 80, chars: 78, filename: Some("notes.md".into()), ..WcCounter::default() };
+        assert_eq!(counter.to_string(), "3 12 80 notes.md");
+        assert_eq!(counter.to_row(&[CountMode::Chars, CountMode::Lines]), "78 3 notes.md");
+
+        let stdin = WcCounter { filename: None, ..counter };
+        assert_eq!(stdin.to_row(&[CountMode::Words]), "12");
+    }
+
+    #[test]
+    fn test_counter_eq_and_hash() {
+        use std::collections::HashSet;
+
+        let a = WcCounter { lines: 3, words: 7, filename: Some("a.txt".into()), ..WcCounter::default() };
+        let b = WcCounter { filename: Some("b.txt".into()), ..a.clone() };
+        assert_ne!(a, b);
+        assert!(a.counts_eq(&b));
+        assert!(!a.counts_eq(&WcCounter { words: 8, ..b.clone() }));
+        let timed = WcCounter {
+            duration: Some(Duration::from_millis(5)),
+            duplicate_of: Some("a.txt".into()),
+            hash: Some("abc123".into()),
+            ..b.clone()
+        };
+        assert!(a.counts_eq(&timed));
+
+        let set: HashSet<WcCounter> = [a.clone(), a.clone(), b].into_iter().collect();
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&a));
+    }
+
+    #[test]
+    fn test_merge_max() {
+        assert_eq!(merge_max(Some(3), Some(5)), Some(5));
+        assert_eq!(merge_max(None, Some(2)), Some(2));
+        assert_eq!(merge_max(None, None), None);
+    }
+
     #[test]
     fn test_count_reader_empty() {
         let reader = Cursor::new(b"");
@@ -266,6 +1924,467 @@ mod counter_tests {
         assert_eq!(result.chars, 8); // 7 characters + newline
     }
 
+    #[test]
+    fn test_count_files_with_mapped_budget() {
+        let paths = ["Cargo.toml", "README.md", "Cargo.toml"];
+        let options = CountOptions { max_mapped: Some(1), ..CountOptions::default() };
+
+        let bounded = count_files_with(&paths, &[CountMode::Bytes], &options).unwrap();
+        let unbounded = count_files(&paths, &[CountMode::Bytes]).unwrap();
+
+        let names: Vec<_> = bounded.iter().map(|r| r.filename.clone().unwrap()).collect();
+        assert_eq!(names, paths);
+        for (a, b) in bounded.iter().zip(&unbounded) {
+            assert_eq!(a.bytes, b.bytes);
+        }
+    }
+
+    #[test]
+    fn test_count_stream_matches_in_memory() {
+        let modes = [CountMode::Lines, CountMode::Words, CountMode::Bytes, CountMode::Chars];
+        let options = CountOptions {
+            record_separator: Some(b"\r\n".to_vec()),
+            line_endings: true,
+            final_newline: true,
+            entropy: true,
+            invalid_utf8: Some(InvalidUtf8::Replace),
+            control_chars: Some(ControlScope::Text),
+            ..CountOptions::default()
+        };
+        let text = "\u{feff}héllo wörld\r\nsecond\u{3000}line is longer\r\n\u{1F600} end "
+            .as_bytes()
+            .iter()
+            .chain(b"\xff\xe2\x82 tail\rmore words here")
+            .copied()
+            .collect::<Vec<u8>>();
+
+        let expected = count_bytes_with(&text, None, &modes, &options).unwrap();
+        for buffer_size in 4..text.len() + 2 {
+            let streamed = count_stream(&text[..], None, &modes, &options, buffer_size).unwrap();
+            assert_eq!(
+                (streamed.lines, streamed.words, streamed.bytes, streamed.chars, streamed.max_line_length),
+                (expected.lines, expected.words, expected.bytes, expected.chars, expected.max_line_length),
+                "buffer size {buffer_size}"
+            );
+            assert_eq!(streamed.records, expected.records);
+            assert_eq!(streamed.line_endings, expected.line_endings);
+            assert_eq!(streamed.invalid_utf8, expected.invalid_utf8);
+            assert_eq!(streamed.missing_final_newline, expected.missing_final_newline);
+            assert_eq!(streamed.control_chars, expected.control_chars);
+        }
+    }
+
+    #[test]
+    fn test_max_memory_streams_large_inputs() {
+        let options = CountOptions { max_memory: Some(8), ..CountOptions::default() };
+        let modes = [CountMode::Lines, CountMode::Words];
+
+        let streamed = count_reader_with(Cursor::new("one two\nthree four five\n"), None, &modes, &options).unwrap();
+        assert_eq!((streamed.lines, streamed.words), (2, 5));
+
+        let csv = CountOptions { csv: true, ..options.clone() };
+        assert!(count_reader_with(Cursor::new("a,b\n"), None, &modes, &csv).is_ok());
+        assert!(count_reader_with(Cursor::new("a,b\nc,d\ne,f\n"), None, &modes, &csv).is_err());
+
+        let file = count_file_with("Cargo.toml", &modes, &options).unwrap();
+        let unbounded = count_file("Cargo.toml", &modes).unwrap();
+        assert_eq!((file.lines, file.words), (unbounded.lines, unbounded.words));
+    }
+
+    #[test]
+    fn test_count_file_without_mmap() {
+        let modes = [CountMode::Lines, CountMode::Words, CountMode::Bytes];
+        let options = CountOptions { no_mmap: true, ..CountOptions::default() };
+
+        let read = count_file_with("Cargo.toml", &modes, &options).unwrap();
+        let mapped = count_file("Cargo.toml", &modes).unwrap();
+        assert_eq!((read.lines, read.words, read.bytes), (mapped.lines, mapped.words, mapped.bytes));
+
+        let empty = std::env::temp_dir().join(format!("rs-wc-empty-{}", std::process::id()));
+        fs::write(&empty, "").unwrap();
+        let result = count_file(&empty, &modes).unwrap();
+        assert_eq!((result.lines, result.words, result.bytes), (0, 0, 0));
+        fs::remove_file(&empty).unwrap();
+    }
+
+    #[test]
+    fn test_largest_first_keeps_path_order() {
+        let sizes = [10, 300, 0, 300, 25];
+        assert_eq!(largest_first((0..5).collect(), &sizes), [1, 3, 4, 0, 2]);
+
+        let paths = ["Cargo.toml", "src/count_handling/counter.rs", "README.md"];
+        let results = count_files(&paths, &[CountMode::Bytes]).unwrap();
+        let names: Vec<_> = results.into_iter().map(|r| r.filename.unwrap()).collect();
+        assert_eq!(names, paths);
+        assert!(count_files(&["Cargo.toml", "missing.txt"], &[CountMode::Bytes]).is_err());
+    }
+
+    #[test]
+    fn test_count_files_streaming() {
+        let paths = ["Cargo.toml", "README.md", "missing.txt"];
+        let bounded = CountOptions { max_mapped: Some(1), ..CountOptions::default() };
+
+        for options in [CountOptions::default(), bounded] {
+            let mut seen = Vec::new();
+            count_files_streaming(&paths, &[CountMode::Lines], &options, |index, result| {
+                seen.push((index, result.is_ok()));
+            });
+            seen.sort();
+            assert_eq!(seen, [(0, true), (1, true), (2, false)]);
+        }
+    }
+
+    #[test]
+    fn test_count_files_pipelined() {
+        let bounded = CountOptions { max_mapped: Some(1), ..CountOptions::default() };
+
+        for options in [CountOptions::default(), bounded] {
+            let (sender, paths) = mpsc::channel();
+            for (key, path) in ["Cargo.toml", "missing.txt", "README.md"].into_iter().enumerate() {
+                sender.send((key, PathBuf::from(path))).unwrap();
+            }
+            drop(sender);
+
+            let mut seen = Vec::new();
+            count_files_pipelined(paths, &[CountMode::Lines], &options, |key, path, result| {
+                seen.push((key, path, result.map(|counter| counter.lines).ok()));
+            });
+            seen.sort();
+            let lines = |path| fs::read_to_string(path).unwrap().lines().count();
+            assert_eq!(seen, [
+                (0, PathBuf::from("Cargo.toml"), Some(lines("Cargo.toml"))),
+                (1, PathBuf::from("missing.txt"), None),
+                (2, PathBuf::from("README.md"), Some(lines("README.md"))),
+            ]);
+        }
+    }
+
+    #[test]
+    fn test_bytes_from_metadata() {
+        let path = std::env::temp_dir().join(format!("rs-wc-sparse-{}", std::process::id()));
+        fs::File::create(&path).unwrap().set_len(1 << 32).unwrap();
+
+        // A 4 GiB hole comes back at once, without reading or mapping it
+        let result = count_file(&path, &[CountMode::Bytes]).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(result.bytes, 1 << 32);
+
+        let forced = CountOptions { force_read: true, ..CountOptions::default() };
+        assert!(size_suffices(&[CountMode::Bytes], &CountOptions::default()));
+        assert!(!size_suffices(&[CountMode::Bytes], &forced));
+        assert!(!size_suffices(&[CountMode::Bytes, CountMode::Lines], &CountOptions::default()));
+        assert!(!size_suffices(&[CountMode::Bytes], &CountOptions { bom: BomPolicy::Error, ..CountOptions::default() }));
+    }
+
+    #[test]
+    fn test_count_file_time() {
+        let options = CountOptions { time: true, ..CountOptions::default() };
+        assert!(count_file_with("Cargo.toml", &[CountMode::Lines], &options).unwrap().duration.is_some());
+        assert!(count_file("Cargo.toml", &[CountMode::Lines]).unwrap().duration.is_none());
+    }
+
+    #[test]
+    fn test_count_files_each() {
+        let results = count_files_each(&["Cargo.toml", "missing.txt", "README.md"], &[CountMode::Lines], &CountOptions::default());
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().filename.as_deref(), Some("Cargo.toml"));
+        assert!(matches!(results[1], Err(WcError::FileNotFound(_))));
+        assert_eq!(results[2].as_ref().unwrap().filename.as_deref(), Some("README.md"));
+    }
+
+    #[test]
+    fn test_count_directory() {
+        assert!(matches!(count_file("src", &[CountMode::Lines]), Err(WcError::IsADirectory(_))));
+        let results = count_files_each(&["src", "Cargo.toml"], &[CountMode::Lines], &CountOptions::default());
+        assert!(matches!(results[0], Err(WcError::IsADirectory(_))));
+        assert!(results[1].is_ok());
+    }
+
+    #[test]
+    fn test_count_lines_only() {
+        let options = CountOptions { max_line_length: false, ..CountOptions::default() };
+        let mut text = "line\n".repeat(CHUNK_SIZE / 3);
+        text.push_str("unterminated");
+
+        let result = count_bytes_with(text.as_bytes(), None, &[CountMode::Lines], &options).unwrap();
+        assert_eq!(result.lines, CHUNK_SIZE / 3 + 1);
+        assert_eq!(result.max_line_length, 0);
+
+        let full = count_bytes_with(text.as_bytes(), None, &[CountMode::Lines], &CountOptions::default()).unwrap();
+        assert_eq!(full.lines, result.lines);
+        assert_eq!(full.max_line_length, 12);
+    }
+
+    #[test]
+    fn test_unterminated_final_line() {
+        let modes = [CountMode::Lines];
+        assert_eq!(count_bytes_with(b"one\ntwo", None, &modes, &CountOptions::default()).unwrap().lines, 2);
+        assert_eq!(count_bytes_with(b"one\ntwo\n", None, &modes, &CountOptions::default()).unwrap().lines, 2);
+        let streamed = count_stream(&b"one\ntwo"[..], None, &modes, &CountOptions::default(), 4).unwrap();
+        assert_eq!(streamed.lines, 2);
+    }
+
+    #[test]
+    fn test_count_newlines_only() {
+        let options = CountOptions { newlines_only: true, ..CountOptions::default() };
+        for max_line_length in [true, false] {
+            let options = CountOptions { max_line_length, ..options.clone() };
+            let result = count_bytes_with(b"one\ntwo", None, &[CountMode::Lines], &options).unwrap();
+            assert_eq!(result.lines, 1);
+        }
+
+        let streamed = count_stream(&b"one\ntwo"[..], None, &[CountMode::Lines], &options, 64).unwrap();
+        assert_eq!(streamed.lines, 1);
+    }
+
+    #[test]
+    fn test_max_word_length() {
+        let options = CountOptions { max_word_length: true, ..CountOptions::default() };
+        let result = count_bytes_with("a bb\u{a0}ccc\nnaïveté d".as_bytes(), None, &[CountMode::Words], &options).unwrap();
+        assert_eq!(result.max_word_length, Some(7));
+
+        let mut total = result.clone();
+        total.add_counts(&count_bytes_with(b"abcdefghij", None, &[], &options).unwrap());
+        assert_eq!(total.max_word_length, Some(10));
+
+        let plain = count_bytes_with(b"abc", None, &[CountMode::Words], &CountOptions::default()).unwrap();
+        assert_eq!(plain.max_word_length, None);
+    }
+
+    #[test]
+    fn test_ttr() {
+        let options = CountOptions { vocabulary: true, ..CountOptions::default() };
+        let text = "The cat\u{a0}saw the dog.\nthe DOG ran";
+        let result = count_bytes_with(text.as_bytes(), None, &[CountMode::Words], &options).unwrap();
+        let vocabulary = result.vocabulary.unwrap();
+
+        assert_eq!(vocabulary.tokens, result.words);
+        assert_eq!(vocabulary.words.len(), 5);
+        assert_eq!(vocabulary.ttr(), Some(5.0 / 8.0));
+    }
+
+    #[test]
+    fn test_show_longest_word() {
+        let options = CountOptions { show_longest_word: Some(6), ..CountOptions::default() };
+        let result = count_bytes_with("a naïveté bb\nQUJDREVGR0g= x".as_bytes(), None, &[], &options).unwrap();
+        assert_eq!(result.longest_word, Some(LongestWord { word: "QUJDR…".into(), length: 12 }));
+
+        let short = count_bytes_with(b"one three five", None, &[], &options).unwrap();
+        assert_eq!(short.longest_word.as_ref().unwrap().word, "three");
+
+        // The total keeps the longest word of all inputs
+        let total = short + &result;
+        assert_eq!(total.longest_word.unwrap().length, 12);
+    }
+
+    #[test]
+    fn test_count_filtered_lines() {
+        let regex = regex::bytes::Regex::new("TODO").unwrap();
+        let options = CountOptions {
+            line_filter: Some(LineFilter { regex, invert: false }),
+            ..CountOptions::default()
+        };
+        let input = b"// TODO tidy this up\nlet x = 1;\n// TODO rename";
+        let modes = [CountMode::Lines, CountMode::Words, CountMode::Bytes];
+        let result = count_bytes_with(input, None, &modes, &options).unwrap();
+
+        assert_eq!((result.lines, result.words, result.bytes), (2, 8, input.len()));
+
+        // The final newline and line endings are checked on the whole file
+        let regex = regex::bytes::Regex::new("TODO").unwrap();
+        let options = CountOptions {
+            line_filter: Some(LineFilter { regex, invert: false }),
+            final_newline: true,
+            line_endings: true,
+            ..CountOptions::default()
+        };
+        let result = count_bytes_with(b"TODO\r\nlet x = 1;\r\ndone", None, &[], &options).unwrap();
+        assert_eq!(result.missing_final_newline, Some(true));
+        assert_eq!(result.line_endings.unwrap().crlf, 2);
+    }
+
+    #[test]
+    fn test_count_html() {
+        let options = CountOptions { html: true, ..CountOptions::default() };
+        let input = b"<h1>Title</h1>\n<script>var hidden = 1;</script><p>Two <em>words</em></p>";
+        let modes = [CountMode::Lines, CountMode::Words, CountMode::Chars, CountMode::Bytes];
+        let result = count_bytes_with(input, None, &modes, &options).unwrap();
+
+        assert_eq!((result.lines, result.words, result.chars, result.bytes), (2, 3, 16, input.len()));
+    }
+
+    #[test]
+    fn test_min_line_length() {
+        let input = b"abcd\r\n\nab\nabc";
+        let options = CountOptions { min_line_length: Some(MinLineScope::All), ..CountOptions::default() };
+        assert_eq!(count_bytes_with(input, None, &[], &options).unwrap().min_line_length, Some(0));
+
+        let options = CountOptions { min_line_length: Some(MinLineScope::NonEmpty), ..options };
+        let mut result = count_bytes_with(input, None, &[], &options).unwrap();
+        assert_eq!(result.min_line_length, Some(2));
+
+        let empty = count_bytes_with(b"\n\n", None, &[], &options).unwrap();
+        assert_eq!(empty.min_line_length, None);
+        result.add_counts(&empty);
+        assert_eq!(result.min_line_length, Some(2));
+    }
+
+    #[test]
+    fn test_count_zero_terminated() {
+        let options = CountOptions { line_terminator: 0, ..CountOptions::default() };
+        let modes = [CountMode::Lines, CountMode::Words];
+        let result = count_bytes_with(b"one two\0three\0four", None, &modes, &options).unwrap();
+
+        assert_eq!(result.lines, 3);
+        assert_eq!(result.words, 4);
+        assert_eq!(result.max_line_length, 7);
+    }
+
+    #[test]
+    fn test_count_word_delimiters() {
+        let options = CountOptions {
+            word_delimiters: Some(b",;".to_vec()),
+            ..CountOptions::default()
+        };
+        let text = b"a b,c;;d\ne,f\n";
+        let result = count_bytes_with(text, None, &[CountMode::Words], &options).unwrap();
+
+        assert_eq!(result.words, 5);
+    }
+
+    #[test]
+    fn test_count_records_option() {
+        let options = CountOptions {
+            record_separator: Some(b"\n\n".to_vec()),
+            ..CountOptions::default()
+        };
+        let result = count_bytes_with(b"a\nb\n\nc\n\n", None, &[], &options).unwrap();
+        assert_eq!(result.records, Some(2));
+
+        let result = count_bytes(b"a\n\n", None, &[CountMode::Lines]).unwrap();
+        assert_eq!(result.records, None);
+    }
+
+    #[test]
+    fn test_count_csv_option() {
+        let options = CountOptions { csv: true, ..CountOptions::default() };
+        let text = b"a,\"multi\nline\"\nb,\n";
+        let result = count_bytes_with(text, None, &[CountMode::Lines], &options).unwrap();
+
+        assert_eq!(result.lines, 3);
+        let csv = result.csv.unwrap();
+        assert_eq!((csv.records, csv.fields, csv.empty_cells), (2, 4, 1));
+    }
+
+    #[test]
+    fn test_count_across_chunk_boundaries() {
+        let mut text = vec![b'x'; CHUNK_SIZE + CHUNK_SIZE / 2];
+        text.extend_from_slice(b"\nshort\n");
+        text.extend(std::iter::repeat_n(b'y', CHUNK_SIZE / 2 - 4));
+        text.extend_from_slice(b" z\n");
+
+        let modes = [CountMode::Lines, CountMode::Words];
+        let options = CountOptions { show_longest: true, ..CountOptions::default() };
+        let result = count_bytes_with(&text, None, &modes, &options).unwrap();
+
+        assert_eq!(result.lines, 3);
+        assert_eq!(result.words, 4);
+        assert_eq!(result.max_line_length, CHUNK_SIZE + CHUNK_SIZE / 2);
+        assert_eq!(result.longest.unwrap().number, 1);
+    }
+
+    #[test]
+    fn test_count_with_encoding() {
+        let options = CountOptions {
+            encoding: Some("latin1".parse().unwrap()),
+            ..CountOptions::default()
+        };
+        let modes = [CountMode::Bytes, CountMode::Chars, CountMode::Words];
+        let result = count_bytes_with(b"caf\xe9 cr\xe8me\n", None, &modes, &options).unwrap();
+
+        assert_eq!(result.bytes, 11);
+        assert_eq!(result.chars, 11);
+        assert_eq!(result.words, 2);
+        assert_eq!(result.encoding.as_deref(), Some("windows-1252"));
+    }
+
+    #[test]
+    fn test_count_bom_policies() {
+        let modes = [CountMode::Bytes, CountMode::Chars, CountMode::Words];
+        let with_bom = |bom| CountOptions { bom, ..CountOptions::default() };
+        let utf8 = b"\xef\xbb\xbfhi there\n";
+
+        let counted = count_bytes_with(utf8, None, &modes, &with_bom(BomPolicy::Count)).unwrap();
+        let stripped = count_bytes_with(utf8, None, &modes, &with_bom(BomPolicy::Strip)).unwrap();
+        assert_eq!((counted.bytes, counted.chars), (12, 10));
+        assert_eq!((stripped.bytes, stripped.chars, stripped.words), (12, 9, 2));
+
+        let utf16 = b"\xff\xfeh\0i\0\n\0";
+        let stripped = count_bytes_with(utf16, None, &modes, &with_bom(BomPolicy::Strip)).unwrap();
+        assert_eq!((stripped.chars, stripped.encoding.as_deref()), (3, Some("UTF-16LE")));
+
+        let result = count_bytes_with(utf8, None, &modes, &with_bom(BomPolicy::Error));
+        assert!(matches!(result, Err(WcError::UnexpectedBom(_))));
+    }
+
+    #[test]
+    fn test_count_unicode_whitespace() {
+        let text = "one\u{a0}two\u{3000}three\u{2009}four five\u{200b}six\u{a0}";
+        let modes = [CountMode::Words];
+
+        let unicode = count_bytes(text.as_bytes(), None, &modes).unwrap();
+        let options = CountOptions { posix: true, ..CountOptions::default() };
+        let posix = count_bytes_with(text.as_bytes(), None, &modes, &options).unwrap();
+
+        assert_eq!(unicode.words, 5);
+        assert_eq!(posix.words, 2);
+    }
+
+    #[test]
+    fn test_chunk_counts_merge_across_chunks() {
+        // Lines and words that span chunk and piece boundaries
+        let mut text = b"short\n".to_vec();
+        text.extend(std::iter::repeat_n(b'x', CHUNK_SIZE * 2));
+        text.extend_from_slice(b" end\n\xe3\x80\x80tail");
+        let modes = [CountMode::Lines, CountMode::Words, CountMode::Bytes, CountMode::Chars];
+
+        let expected = count_bytes(&text, None, &modes).unwrap();
+        assert_eq!((expected.lines, expected.words, expected.max_line_length), (3, 4, CHUNK_SIZE * 2 + 4));
+
+        let streamed = count_reader(&text[..], None, &modes).unwrap();
+        assert!(streamed.counts_eq(&expected));
+        let pieces = count_stream(&text[..], None, &modes, &CountOptions::default(), CHUNK_SIZE / 3).unwrap();
+        assert!(pieces.counts_eq(&expected));
+    }
+
+    #[test]
+    fn test_chunk_starts_keep_characters_whole() {
+        let mut text = vec![b'a'; CHUNK_SIZE - 1];
+        text.extend_from_slice("\u{3000}b".as_bytes());
+
+        let starts: Vec<_> = (0..3).map(|index| chunk_start(&text, index)).collect();
+        assert_eq!(starts, [0, CHUNK_SIZE + 2, CHUNK_SIZE + 3]);
+
+        let result = count_bytes(&text, None, &[CountMode::Words]).unwrap();
+        assert_eq!(result.words, 2);
+    }
+
+    #[test]
+    fn test_count_final_newline() {
+        let options = CountOptions { final_newline: true, ..CountOptions::default() };
+        let missing = |bytes: &[u8]| count_bytes_with(bytes, None, &[], &options).unwrap().missing_final_newline;
+
+        assert_eq!(missing(b"a\nb"), Some(true));
+        assert_eq!(missing(b"a\nb\n"), Some(false));
+        assert_eq!(missing(b""), Some(false));
+
+        // Rendering HTML ends the text with a newline the file doesn't have
+        let options = CountOptions { final_newline: true, line_endings: true, html: true, ..CountOptions::default() };
+        let result = count_bytes_with(b"<p>a</p>\r\n<p>b</p>", None, &[], &options).unwrap();
+        assert_eq!(result.missing_final_newline, Some(true));
+        assert_eq!(result.line_endings.unwrap().crlf, 1);
+    }
+
     #[test]
     fn test_count_file_not_found() {
         let result = count_file("/nonexistent/file", &[CountMode::Chars]);
@@ -0,0 +1,68 @@
+use csv::{ByteRecord, ReaderBuilder};
+
+use crate::error::WcResult;
+
+/// Structure of CSV input, counted with quoting taken into account so a
+/// quoted field spanning several lines is still one field of one record.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CsvStats {
+    /// Rows, including any header row
+    pub records: usize,
+    pub fields: usize,
+    /// Most fields seen in a single record
+    pub max_fields: usize,
+    /// Fields with no content, quoted or not
+    pub empty_cells: usize,
+}
+
+impl CsvStats {
+    pub fn add(&mut self, other: &CsvStats) {
+        self.records += other.records;
+        self.fields += other.fields;
+        self.max_fields = self.max_fields.max(other.max_fields);
+        self.empty_cells += other.empty_cells;
+    }
+}
+
+/// Parses `bytes` as comma-separated values. Records may have differing
+/// numbers of fields; blank lines are ignored.
+pub fn csv_stats(bytes: &[u8]) -> WcResult<CsvStats> {
+    let mut reader = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(bytes);
+    let mut record = ByteRecord::new();
+    let mut stats = CsvStats::default();
+
+    while reader.read_byte_record(&mut record)? {
+        stats.records += 1;
+        stats.fields += record.len();
+        stats.max_fields = stats.max_fields.max(record.len());
+        stats.empty_cells += record.iter().filter(|field| field.is_empty()).count();
+    }
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod csv_stats_tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_stats_quoted_newlines() {
+        let input = b"id,note,extra\n1,\"first line\nsecond line\",\n2,\"a, b\",\"\"\n3,x\n";
+        let stats = csv_stats(input).unwrap();
+
+        assert_eq!(stats, CsvStats {
+            records: 4,
+            fields: 11,
+            max_fields: 3,
+            empty_cells: 2,
+        });
+    }
+
+    #[test]
+    fn test_csv_stats_empty() {
+        assert_eq!(csv_stats(b"").unwrap(), CsvStats::default());
+    }
+}
@@ -0,0 +1,65 @@
+use crate::counter::WcCounter;
+use crate::threshold::Metric;
+
+/// The change in a single metric between two counters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricDelta {
+    pub metric: Metric,
+    pub old: usize,
+    pub new: usize,
+}
+
+/// Counts of two inputs being compared, old first.
+#[derive(Debug, Clone)]
+pub struct CountDiff {
+    pub old: WcCounter,
+    pub new: WcCounter,
+}
+
+impl MetricDelta {
+    pub fn delta(&self) -> i128 {
+        self.new as i128 - self.old as i128
+    }
+
+    /// Percentage change relative to the old value, `None` when it was zero.
+    pub fn percent_change(&self) -> Option<f64> {
+        if self.old == 0 {
+            return None;
+        }
+        Some(self.delta() as f64 * 100.0 / self.old as f64)
+    }
+}
+
+impl CountDiff {
+    pub fn new(old: WcCounter, new: WcCounter) -> Self {
+        Self { old, new }
+    }
+
+    pub fn deltas(&self, metrics: &[Metric]) -> Vec<MetricDelta> {
+        metrics.iter()
+            .map(|&metric| MetricDelta {
+                metric,
+                old: metric.value(&self.old),
+                new: metric.value(&self.new),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod diff_tests {
+    use super::*;
+
+    #[test]
+    fn test_deltas_and_percent_change() {
+        let old = WcCounter { lines: 100, words: 0, ..WcCounter::default() };
+        let new = WcCounter { lines: 125, words: 7, ..WcCounter::default() };
+        let diff = CountDiff::new(old, new);
+
+        let deltas = diff.deltas(&[Metric::Lines, Metric::Words]);
+        assert_eq!(deltas[0].delta(), 25);
+        assert_eq!(deltas[0].percent_change(), Some(25.0));
+        assert_eq!(deltas[1].delta(), 7);
+        assert_eq!(deltas[1].percent_change(), None);
+    }
+}
@@ -0,0 +1,112 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+const TEXT_PRESENTATION: char = '\u{FE0E}';
+const EMOJI_PRESENTATION: char = '\u{FE0F}';
+const COMBINING_KEYCAP: char = '\u{20E3}';
+
+// Code points shown as emoji by default (Emoji_Presentation)
+const DEFAULT_EMOJI: &[(u32, u32)] = &[
+    (0x231A, 0x231B), (0x23E9, 0x23EC), (0x23F0, 0x23F0), (0x23F3, 0x23F3),
+    (0x25FD, 0x25FE), (0x2614, 0x2615), (0x2648, 0x2653), (0x267F, 0x267F),
+    (0x2693, 0x2693), (0x26A1, 0x26A1), (0x26AA, 0x26AB), (0x26BD, 0x26BE),
+    (0x26C4, 0x26C5), (0x26CE, 0x26CE), (0x26D4, 0x26D4), (0x26EA, 0x26EA),
+    (0x26F2, 0x26F3), (0x26F5, 0x26F5), (0x26FA, 0x26FA), (0x26FD, 0x26FD),
+    (0x2705, 0x2705), (0x270A, 0x270B), (0x2728, 0x2728), (0x274C, 0x274C),
+    (0x274E, 0x274E), (0x2753, 0x2755), (0x2757, 0x2757), (0x2795, 0x2797),
+    (0x27B0, 0x27B0), (0x27BF, 0x27BF), (0x2B1B, 0x2B1C), (0x2B50, 0x2B50),
+    (0x2B55, 0x2B55), (0x1F004, 0x1F004), (0x1F0CF, 0x1F0CF), (0x1F18E, 0x1F18E),
+    (0x1F191, 0x1F19A), (0x1F1E6, 0x1F1FF), (0x1F201, 0x1F201), (0x1F21A, 0x1F21A),
+    (0x1F22F, 0x1F22F), (0x1F232, 0x1F236), (0x1F238, 0x1F23A), (0x1F250, 0x1F251),
+    (0x1F300, 0x1F320), (0x1F32D, 0x1F335), (0x1F337, 0x1F37C), (0x1F37E, 0x1F393),
+    (0x1F3A0, 0x1F3CA), (0x1F3CF, 0x1F3D3), (0x1F3E0, 0x1F3F0), (0x1F3F4, 0x1F3F4),
+    (0x1F3F8, 0x1F43E), (0x1F440, 0x1F440), (0x1F442, 0x1F4FC), (0x1F4FF, 0x1F53D),
+    (0x1F54B, 0x1F54E), (0x1F550, 0x1F567), (0x1F57A, 0x1F57A), (0x1F595, 0x1F596),
+    (0x1F5A4, 0x1F5A4), (0x1F5FB, 0x1F64F), (0x1F680, 0x1F6C5), (0x1F6CC, 0x1F6CC),
+    (0x1F6D0, 0x1F6D2), (0x1F6D5, 0x1F6D7), (0x1F6DC, 0x1F6DF), (0x1F6EB, 0x1F6EC),
+    (0x1F6F4, 0x1F6FC), (0x1F7E0, 0x1F7EB), (0x1F7F0, 0x1F7F0), (0x1F90C, 0x1F93A),
+    (0x1F93C, 0x1F945), (0x1F947, 0x1F9FF), (0x1FA70, 0x1FAFF),
+];
+
+// Code points that are emoji when followed by U+FE0F, such as ❤ or ©
+// (roughly Extended_Pictographic)
+const PICTOGRAPHIC: &[(u32, u32)] = &[
+    (0x00A9, 0x00A9), (0x00AE, 0x00AE), (0x203C, 0x203C), (0x2049, 0x2049),
+    (0x2122, 0x2122), (0x2139, 0x2139), (0x2194, 0x2199), (0x21A9, 0x21AA),
+    (0x2300, 0x23FF), (0x24C2, 0x24C2), (0x25AA, 0x25FE), (0x2600, 0x27BF),
+    (0x2934, 0x2935), (0x2B05, 0x2B55), (0x3030, 0x3030), (0x303D, 0x303D),
+    (0x3297, 0x3297), (0x3299, 0x3299), (0x1F000, 0x1FAFF),
+];
+
+fn in_table(table: &[(u32, u32)], c: char) -> bool {
+    let c = c as u32;
+    table
+        .binary_search_by(|&(start, end)| {
+            if end < c {
+                std::cmp::Ordering::Less
+            } else if start > c {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+// Whether a grapheme cluster is shown as a single emoji
+fn is_emoji(cluster: &str) -> bool {
+    let mut chars = cluster.chars();
+    let Some(first) = chars.next() else { return false };
+    let second = chars.next();
+
+    if first.is_ascii() {
+        // Keycaps: a digit, # or * with U+20E3
+        return matches!(first, '0'..='9' | '#' | '*') && cluster.ends_with(COMBINING_KEYCAP);
+    }
+    match second {
+        Some(TEXT_PRESENTATION) => false,
+        Some(EMOJI_PRESENTATION) => in_table(PICTOGRAPHIC, first),
+        // A ZWJ sequence is one emoji, whatever its first part defaults to
+        Some(ZERO_WIDTH_JOINER) => in_table(PICTOGRAPHIC, first),
+        _ => in_table(DEFAULT_EMOJI, first),
+    }
+}
+
+/// Counts the emoji in `bytes`. A sequence shown as one emoji counts once:
+/// ZWJ families, skin-tone modified people, flags and keycaps are all single
+/// units. Symbols such as © only count when U+FE0F asks for emoji
+/// presentation. Malformed UTF-8 is ignored.
+pub fn count_emoji(bytes: &[u8]) -> usize {
+    bytes
+        .utf8_chunks()
+        .map(|chunk| chunk.valid().graphemes(true).filter(|cluster| is_emoji(cluster)).count())
+        .sum()
+}
+
+#[cfg(test)]
+mod emoji_tests {
+    use super::*;
+
+    #[test]
+    fn test_count_emoji_sequences() {
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}\u{200D}\u{1F466}";
+        let waving = "\u{1F44B}\u{1F3FD}";
+        let flag = "\u{1F1EC}\u{1F1E7}";
+        let keycap = "1\u{FE0F}\u{20E3}";
+        let text = format!("hi {family} {waving}{flag} {keycap} \u{1F600}!");
+
+        assert_eq!(count_emoji(text.as_bytes()), 5);
+        assert_eq!(count_emoji(b"plain text, 123 #1"), 0);
+    }
+
+    #[test]
+    fn test_count_emoji_presentation() {
+        // Text by default, emoji with U+FE0F
+        assert_eq!(count_emoji("\u{A9} \u{2764}".as_bytes()), 0);
+        assert_eq!(count_emoji("\u{A9}\u{FE0F} \u{2764}\u{FE0F}".as_bytes()), 2);
+        // Emoji by default, text with U+FE0E
+        assert_eq!(count_emoji("\u{231A} \u{231A}\u{FE0E}".as_bytes()), 1);
+        // Malformed bytes between emoji are skipped
+        assert_eq!(count_emoji(b"\xf0\x9f\x98\x80\xff\xf0\x9f\x98\x80"), 2);
+    }
+}
@@ -0,0 +1,152 @@
+use chardetng::EncodingDetector;
+use encoding_rs::Encoding;
+use std::{borrow::Cow, str::FromStr};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::error::{WcError, WcResult};
+use crate::parser::{CharUnit, InvalidUtf8};
+
+/// Character encoding of the input, from `--encoding`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputEncoding {
+    /// A WHATWG encoding label such as `latin1`, `shift_jis` or `utf-16le`
+    Named(&'static Encoding),
+    /// Guess from a byte order mark, or from the content if there is none
+    Auto,
+}
+
+impl FromStr for InputEncoding {
+    type Err = WcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(InputEncoding::Auto);
+        }
+
+        Encoding::for_label(s.as_bytes())
+            .map(InputEncoding::Named)
+            .ok_or_else(|| WcError::invalid_argument(format!("unknown encoding '{}'", s)))
+    }
+}
+
+impl InputEncoding {
+    pub fn resolve(&self, bytes: &[u8]) -> &'static Encoding {
+        match self {
+            InputEncoding::Named(encoding) => encoding,
+            InputEncoding::Auto => detect(bytes),
+        }
+    }
+}
+
+fn detect(bytes: &[u8]) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return encoding;
+    }
+
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    detector.guess(None, true)
+}
+
+/// Transcodes `bytes` to UTF-8, returning the text and the encoding used.
+/// A byte order mark is decoded like any other character; malformed
+/// sequences become U+FFFD.
+pub fn decode<'a>(bytes: &'a [u8], encoding: &InputEncoding) -> (Cow<'a, str>, &'static Encoding) {
+    let encoding = encoding.resolve(bytes);
+    (encoding.decode_without_bom_handling(bytes).0, encoding)
+}
+
+/// Counts the characters in `bytes` read as UTF-8, returning the count and
+/// the number of malformed sequences. A sequence cut off by the end of the
+/// input counts as one malformed sequence.
+pub fn count_chars(bytes: &[u8], policy: InvalidUtf8) -> WcResult<(usize, usize)> {
+    count_valid_runs(bytes, policy, |text| text.chars().count())
+}
+
+/// Like `count_chars`, but counting `unit`s. Every byte is a character
+/// under `CharUnit::Bytes`, so `policy` only applies to the malformed count.
+pub fn count_chars_as(bytes: &[u8], unit: CharUnit, policy: InvalidUtf8) -> WcResult<(usize, usize)> {
+    match unit {
+        CharUnit::Bytes => count_chars(bytes, InvalidUtf8::Skip).map(|(_, invalid)| (bytes.len(), invalid)),
+        CharUnit::Codepoints => count_chars(bytes, policy),
+        CharUnit::Graphemes => count_valid_runs(bytes, policy, |text| text.graphemes(true).count()),
+    }
+}
+
+// Applies `count` to each well-formed stretch of `bytes`, totalling the
+// results along with the malformed sequences between them
+fn count_valid_runs(bytes: &[u8], policy: InvalidUtf8, count: impl Fn(&str) -> usize) -> WcResult<(usize, usize)> {
+    if policy == InvalidUtf8::Error {
+        std::str::from_utf8(bytes)?;
+    }
+
+    let mut chars = 0;
+    let mut invalid = 0;
+    for chunk in bytes.utf8_chunks() {
+        chars += count(chunk.valid());
+        invalid += usize::from(!chunk.invalid().is_empty());
+    }
+
+    Ok((chars_under(policy, chars, invalid, bytes.len()), invalid))
+}
+
+/// The `-m` count under `policy` for `len` bytes holding `chars` well-formed
+/// characters and `invalid` malformed sequences.
+pub fn chars_under(policy: InvalidUtf8, chars: usize, invalid: usize, len: usize) -> usize {
+    match policy {
+        InvalidUtf8::Replace => chars + invalid,
+        InvalidUtf8::Skip | InvalidUtf8::Error => chars,
+        InvalidUtf8::Bytes if invalid > 0 => len,
+        InvalidUtf8::Bytes => chars,
+    }
+}
+
+#[cfg(test)]
+mod encoding_tests {
+    use super::*;
+
+    #[test]
+    fn test_count_chars_policies() {
+        let input = b"h\xc3\xa9 \xff\xfe ok \xe2\x82";
+
+        assert_eq!(count_chars(input, InvalidUtf8::Replace).unwrap(), (10, 3));
+        assert_eq!(count_chars(input, InvalidUtf8::Skip).unwrap(), (7, 3));
+        assert_eq!(count_chars(input, InvalidUtf8::Bytes).unwrap(), (12, 3));
+        assert!(matches!(count_chars(input, InvalidUtf8::Error), Err(WcError::Utf8(_))));
+        assert_eq!(count_chars("héllo".as_bytes(), InvalidUtf8::Error).unwrap(), (5, 0));
+    }
+
+    #[test]
+    fn test_count_chars_as() {
+        // "e" with a combining acute, a flag, then a malformed byte
+        let input = "e\u{301} \u{1F1EC}\u{1F1E7}".as_bytes().iter().copied().chain([0xff]).collect::<Vec<u8>>();
+
+        assert_eq!(count_chars_as(&input, CharUnit::Bytes, InvalidUtf8::Replace).unwrap(), (13, 1));
+        assert_eq!(count_chars_as(&input, CharUnit::Codepoints, InvalidUtf8::Replace).unwrap(), (6, 1));
+        assert_eq!(count_chars_as(&input, CharUnit::Graphemes, InvalidUtf8::Replace).unwrap(), (4, 1));
+        assert!(count_chars_as(&input, CharUnit::Graphemes, InvalidUtf8::Error).is_err());
+    }
+
+    #[test]
+    fn test_parse_encoding() {
+        assert_eq!("latin1".parse::<InputEncoding>().unwrap(), InputEncoding::Named(encoding_rs::WINDOWS_1252));
+        assert_eq!("AUTO".parse::<InputEncoding>().unwrap(), InputEncoding::Auto);
+        assert!("klingon".parse::<InputEncoding>().is_err());
+    }
+
+    #[test]
+    fn test_decode_named_and_detected() {
+        let latin1 = b"caf\xe9 cr\xe8me\n";
+        let (text, encoding) = decode(latin1, &"latin1".parse().unwrap());
+        assert_eq!(text, "café crème\n");
+        assert_eq!(encoding.name(), "windows-1252");
+
+        let (shift_jis, _, _) = encoding_rs::SHIFT_JIS.encode("こんにちは、世界。日本語のテキストです。\n");
+        let (text, encoding) = decode(&shift_jis, &InputEncoding::Auto);
+        assert_eq!(encoding, encoding_rs::SHIFT_JIS);
+        assert_eq!(text.chars().count(), 21);
+
+        let utf16 = b"\xff\xfeh\0i\0";
+        assert_eq!(decode(utf16, &InputEncoding::Auto).1, encoding_rs::UTF_16LE);
+    }
+}
@@ -0,0 +1,134 @@
+/// Entropy (bits per byte) above which data looks compressed or encrypted.
+const BINARY_ENTROPY: f64 = 7.5;
+
+/// Share of control bytes above which input isn't treated as text.
+const BINARY_CONTROL_RATIO: f64 = 0.1;
+
+/// Share of bytes in malformed UTF-8 above which a sniffed block is binary.
+const BINARY_INVALID_UTF8_RATIO: f64 = 0.3;
+
+/// Guesses from the first block of a file whether it is binary: it contains
+/// a NUL byte, or too much of it is not valid UTF-8. A character cut off at
+/// the end of the block is not held against it.
+pub fn looks_binary(block: &[u8]) -> bool {
+    if block.contains(&0) {
+        return true;
+    }
+
+    let mut invalid = 0;
+    let mut rest = block;
+    while let Err(error) = std::str::from_utf8(rest) {
+        let Some(len) = error.error_len() else { break };
+        invalid += len;
+        rest = &rest[error.valid_up_to() + len..];
+    }
+
+    !block.is_empty() && invalid as f64 / block.len() as f64 > BINARY_INVALID_UTF8_RATIO
+}
+
+/// How often each byte value occurs. Histograms of separate inputs add up to
+/// the histogram of their concatenation, so totals get a true entropy.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ByteHistogram {
+    pub counts: [u64; 256],
+}
+
+impl Default for ByteHistogram {
+    fn default() -> Self {
+        Self { counts: [0; 256] }
+    }
+}
+
+// Control characters that still commonly appear in text
+fn is_text_control(byte: u8) -> bool {
+    matches!(byte, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b)
+}
+
+impl ByteHistogram {
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut histogram = Self::default();
+        for &byte in bytes {
+            histogram.counts[byte as usize] += 1;
+        }
+        histogram
+    }
+
+    pub fn add(&mut self, other: &ByteHistogram) {
+        for (count, other) in self.counts.iter_mut().zip(other.counts) {
+            *count += other;
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Shannon entropy in bits per byte, from 0 (one repeated byte) to 8.
+    pub fn entropy(&self) -> f64 {
+        // Each term is -0.0 for a single byte value, and so is an empty
+        // sum, which would print as "-0.00"
+        if self.counts.iter().filter(|&&count| count > 0).count() < 2 {
+            return 0.0;
+        }
+        let total = self.total() as f64;
+        self.counts.iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let p = count as f64 / total;
+                -p * p.log2()
+            })
+            .sum()
+    }
+
+    /// Whether the bytes look like a blob rather than text: any NUL byte,
+    /// too many control bytes, or entropy close to random.
+    pub fn is_binary(&self) -> bool {
+        let total = self.total();
+        if total == 0 {
+            return false;
+        }
+
+        let controls: u64 = (0..32u8)
+            .chain([0x7f])
+            .filter(|&byte| !is_text_control(byte))
+            .map(|byte| self.counts[byte as usize])
+            .sum();
+
+        self.counts[0] > 0
+            || controls as f64 / total as f64 > BINARY_CONTROL_RATIO
+            || self.entropy() >= BINARY_ENTROPY
+    }
+}
+
+#[cfg(test)]
+mod entropy_tests {
+    use super::*;
+
+    #[test]
+    fn test_entropy() {
+        assert_eq!(ByteHistogram::from_bytes(b"aaaa").entropy().to_bits(), 0.0f64.to_bits());
+        assert_eq!(ByteHistogram::from_bytes(b"abab").entropy(), 1.0);
+        assert_eq!(ByteHistogram::default().entropy().to_bits(), 0.0f64.to_bits());
+
+        let all: Vec<u8> = (0..=255).collect();
+        assert_eq!(ByteHistogram::from_bytes(&all).entropy(), 8.0);
+    }
+
+    #[test]
+    fn test_is_binary() {
+        assert!(!ByteHistogram::from_bytes("plain text, ünïcode\r\n\tok\n".as_bytes()).is_binary());
+        assert!(ByteHistogram::from_bytes(b"text\0with nul").is_binary());
+        assert!(ByteHistogram::from_bytes(b"\x01\x02\x03abc").is_binary());
+        assert!(!ByteHistogram::default().is_binary());
+    }
+
+    #[test]
+    fn test_looks_binary() {
+        assert!(!looks_binary("text with ünïcode".as_bytes()));
+        assert!(!looks_binary(&"é".as_bytes()[..1]));
+        assert!(!looks_binary(b"caf\xe9 latin-1 text"));
+        assert!(looks_binary(b"ELF\0\x01"));
+        assert!(looks_binary(b"\xff\xfe\xfd\xfc"));
+        assert!(!looks_binary(b""));
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::counter::WcCounter;
+use crate::error::WcError;
+
+/// Label for files that have no extension.
+pub const NO_EXTENSION: &str = "(none)";
+
+/// How per-file results are rolled up into aggregate rows.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GroupBy {
+    /// One row per file extension, e.g. `.rs`
+    Extension,
+    /// One row per containing directory, truncated to at most this many
+    /// levels (`None` keeps the full parent directory)
+    Directory(Option<usize>),
+}
+
+impl FromStr for GroupBy {
+    type Err = WcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || WcError::invalid_argument(format!(
+            "invalid grouping '{}': expected 'ext' or 'dir[=DEPTH]'", s
+        ));
+
+        match s.split_once('=') {
+            None if s == "ext" || s == "extension" => Ok(GroupBy::Extension),
+            None if s == "dir" => Ok(GroupBy::Directory(None)),
+            Some(("dir", depth)) => match depth.parse() {
+                Ok(depth) if depth > 0 => Ok(GroupBy::Directory(Some(depth))),
+                _ => Err(invalid()),
+            },
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl GroupBy {
+    pub fn key(&self, filename: Option<&str>) -> String {
+        match self {
+            GroupBy::Extension => filename
+                .and_then(|f| Path::new(f).extension())
+                .map(|ext| format!(".{}", ext.to_string_lossy()))
+                .unwrap_or_else(|| NO_EXTENSION.to_string()),
+            GroupBy::Directory(depth) => {
+                let parent = filename
+                    .and_then(|f| Path::new(f).parent())
+                    .unwrap_or(Path::new(""));
+                directory_key(parent, *depth)
+            }
+        }
+    }
+}
+
+// Keeps the first `depth` named components of `dir`, dropping any `./`.
+fn directory_key(dir: &Path, depth: Option<usize>) -> String {
+    let mut key = PathBuf::new();
+    let mut named = 0;
+
+    for component in dir.components() {
+        match component {
+            Component::CurDir => continue,
+            Component::Normal(_) | Component::ParentDir => {
+                if depth.is_some_and(|d| named >= d) {
+                    break;
+                }
+                named += 1;
+            }
+            Component::Prefix(_) | Component::RootDir => {}
+        }
+        key.push(component);
+    }
+
+    if key.as_os_str().is_empty() {
+        ".".to_string()
+    } else {
+        key.display().to_string()
+    }
+}
+
+/// Sums results sharing a group key into one counter per group, sorted by
+/// key. Each counter's filename is set to its key.
+pub fn group_results(results: &[WcCounter], group_by: &GroupBy) -> Vec<WcCounter> {
+    let mut groups: BTreeMap<String, WcCounter> = BTreeMap::new();
+
+    for result in results.iter().filter(|result| result.duplicate_of.is_none()) {
+        let key = group_by.key(result.filename.as_deref());
+        let group = groups.entry(key.clone()).or_insert_with(|| WcCounter {
+            filename: Some(key),
+            ..WcCounter::default()
+        });
+        *group += result;
+    }
+
+    groups.into_values().collect()
+}
+
+#[cfg(test)]
+mod group_tests {
+    use super::*;
+
+    #[test]
+    fn test_group_by_extension() {
+        let results = [
+            WcCounter::named("src/main.rs", 10),
+            WcCounter::named("README.md", 3),
+            WcCounter::named("src/lib.rs", 5),
+            WcCounter::named("Makefile", 2),
+        ];
+
+        let grouped = group_results(&results, &GroupBy::Extension);
+        let rows: Vec<(String, usize)> = grouped.iter()
+            .map(|g| (g.filename.clone().unwrap(), g.lines))
+            .collect();
+
+        assert_eq!(rows, [
+            ("(none)".to_string(), 2),
+            (".md".to_string(), 3),
+            (".rs".to_string(), 15),
+        ]);
+    }
+
+    #[test]
+    fn test_group_by_directory_depth() {
+        let results = [
+            WcCounter::named("./src/count_handling/counter.rs", 10),
+            WcCounter::named("src/count_handling/group.rs", 5),
+            WcCounter::named("src/lib.rs", 3),
+            WcCounter::named("README.md", 1),
+        ];
+
+        let names = |group_by: GroupBy| -> Vec<(String, usize)> {
+            group_results(&results, &group_by).iter()
+                .map(|g| (g.filename.clone().unwrap(), g.lines))
+                .collect()
+        };
+
+        assert_eq!(names(GroupBy::Directory(Some(1))), [
+            (".".to_string(), 1),
+            ("src".to_string(), 18),
+        ]);
+        assert_eq!(names(GroupBy::Directory(None)), [
+            (".".to_string(), 1),
+            ("src".to_string(), 3),
+            ("src/count_handling".to_string(), 15),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_group_by() {
+        assert_eq!("ext".parse::<GroupBy>().unwrap(), GroupBy::Extension);
+        assert_eq!("dir".parse::<GroupBy>().unwrap(), GroupBy::Directory(None));
+        assert_eq!("dir=2".parse::<GroupBy>().unwrap(), GroupBy::Directory(Some(2)));
+        assert!("dir=0".parse::<GroupBy>().is_err());
+        assert!("size".parse::<GroupBy>().is_err());
+    }
+}
@@ -0,0 +1,138 @@
+use clap::ValueEnum;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::counter::WcCounter;
+
+/// Content hash computed by `--hash`.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    /// Name used as the output column, e.g. `sha256`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Blake3 => "blake3",
+        }
+    }
+}
+
+/// Hashes input fed piece by piece.
+pub enum ContentHasher {
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl ContentHasher {
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha256 => ContentHasher::Sha256(Sha256::new()),
+            HashAlgorithm::Blake3 => ContentHasher::Blake3(Box::default()),
+        }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        match self {
+            ContentHasher::Sha256(hasher) => hasher.update(bytes),
+            ContentHasher::Blake3(hasher) => {
+                hasher.update(bytes);
+            }
+        }
+    }
+
+    /// The digest as lowercase hex.
+    pub fn finish(self) -> String {
+        match self {
+            ContentHasher::Sha256(hasher) => hex(&hasher.finalize()),
+            ContentHasher::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::with_capacity(bytes.len() * 2), |mut out, byte| {
+        let _ = write!(out, "{:02x}", byte);
+        out
+    })
+}
+
+/// Hex digest of `bytes`.
+pub fn hash_bytes(bytes: &[u8], algorithm: HashAlgorithm) -> String {
+    let mut hasher = ContentHasher::new(algorithm);
+    hasher.update(bytes);
+    hasher.finish()
+}
+
+/// Remembers the first input seen with each content hash, for
+/// `--dedup-content`.
+#[derive(Default)]
+pub struct DuplicateTracker {
+    first: HashMap<String, String>,
+}
+
+impl DuplicateTracker {
+    /// Marks `result` as a duplicate if an earlier result had the same hash.
+    pub fn mark(&mut self, result: &mut WcCounter) {
+        let (Some(hash), Some(filename)) = (&result.hash, &result.filename) else {
+            return;
+        };
+        match self.first.get(hash) {
+            Some(original) => result.duplicate_of = Some(original.clone()),
+            None => {
+                self.first.insert(hash.clone(), filename.clone());
+            }
+        }
+    }
+}
+
+/// Marks every result whose content matches an earlier one's.
+pub fn mark_duplicates(results: &mut [WcCounter]) {
+    let mut tracker = DuplicateTracker::default();
+    results.iter_mut().for_each(|result| tracker.mark(result));
+}
+
+#[cfg(test)]
+mod hash_tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_bytes() {
+        assert_eq!(
+            hash_bytes(b"abc", HashAlgorithm::Sha256),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hash_bytes(b"", HashAlgorithm::Blake3),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+    }
+
+    #[test]
+    fn test_mark_duplicates() {
+        let counter = |name: &str, content: &[u8]| WcCounter {
+            filename: Some(name.to_string()),
+            hash: Some(hash_bytes(content, HashAlgorithm::Blake3)),
+            ..WcCounter::default()
+        };
+        let mut results = [counter("a", b"one"), counter("b", b"two"), counter("mirror/a", b"one")];
+        mark_duplicates(&mut results);
+
+        let marked: Vec<_> = results.iter().map(|r| r.duplicate_of.as_deref()).collect();
+        assert_eq!(marked, [None, None, Some("a")]);
+    }
+
+    #[test]
+    fn test_hash_in_pieces() {
+        let input = b"the quick brown fox jumps over the lazy dog";
+        for algorithm in [HashAlgorithm::Sha256, HashAlgorithm::Blake3] {
+            let mut hasher = ContentHasher::new(algorithm);
+            input.chunks(5).for_each(|piece| hasher.update(piece));
+            assert_eq!(hasher.finish(), hash_bytes(input, algorithm));
+        }
+    }
+}
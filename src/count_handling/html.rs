@@ -0,0 +1,211 @@
+// Elements whose content is never shown on the page
+const HIDDEN: &[&str] = &["script", "style", "template", "noscript", "title"];
+
+// Elements that start on a line of their own
+const BLOCKS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "body", "dd", "details", "div", "dl", "dt",
+    "figcaption", "figure", "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr",
+    "html", "li", "main", "nav", "ol", "p", "pre", "section", "summary", "table", "tr", "ul",
+];
+
+// Table cells sit side by side, so only need a space between them
+const CELLS: &[&str] = &["td", "th"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Gap {
+    None,
+    Space,
+    Newline,
+}
+
+// Rendered text as it's built, with whitespace collapsed the way browsers
+// do outside <pre>
+struct Text {
+    out: Vec<u8>,
+    gap: Gap,
+}
+
+impl Text {
+    fn push(&mut self, bytes: &[u8]) {
+        match self.gap {
+            Gap::Space if !self.out.is_empty() && !self.out.ends_with(b"\n") => self.out.push(b' '),
+            Gap::Newline if !self.out.is_empty() && !self.out.ends_with(b"\n") => self.out.push(b'\n'),
+            _ => {}
+        }
+        self.gap = Gap::None;
+        self.out.extend_from_slice(bytes);
+    }
+
+    // A <br>, which unlike the end of a block can leave a blank line
+    fn line_break(&mut self) {
+        self.push(b"\n");
+    }
+
+    fn gap(&mut self, gap: Gap) {
+        self.gap = self.gap.max(gap);
+    }
+}
+
+// The index just past the `>` closing a tag that starts at `start`, skipping
+// over quoted attribute values, or the end of input for an unclosed tag
+fn tag_end(bytes: &[u8], start: usize) -> usize {
+    let mut quote = None;
+    for (index, &byte) in bytes.iter().enumerate().skip(start) {
+        match (quote, byte) {
+            (None, b'"' | b'\'') => quote = Some(byte),
+            (Some(open), _) if byte == open => quote = None,
+            (None, b'>') => return index + 1,
+            _ => {}
+        }
+    }
+    bytes.len()
+}
+
+// Where `pattern` next occurs from `start`, ignoring ASCII case
+fn find_ignore_case(bytes: &[u8], start: usize, pattern: &[u8]) -> Option<usize> {
+    bytes.get(start..)?
+        .windows(pattern.len())
+        .position(|window| window.eq_ignore_ascii_case(pattern))
+        .map(|offset| start + offset)
+}
+
+// The character a `&...;` reference at the start of `bytes` stands for, and
+// how many bytes it takes up
+fn entity(bytes: &[u8]) -> Option<(String, usize)> {
+    let end = bytes.iter().take(32).position(|&b| b == b';')?;
+    let name = std::str::from_utf8(&bytes[1..end]).ok()?;
+    let text = if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER).to_string()
+    } else {
+        match name {
+            "amp" => "&",
+            "lt" => "<",
+            "gt" => ">",
+            "quot" => "\"",
+            "apos" => "'",
+            "nbsp" => "\u{a0}",
+            "shy" => "",
+            "ndash" => "–",
+            "mdash" => "—",
+            "hellip" => "…",
+            "lsquo" => "‘",
+            "rsquo" => "’",
+            "ldquo" => "“",
+            "rdquo" => "”",
+            "copy" => "©",
+            "reg" => "®",
+            "trade" => "™",
+            _ => return None,
+        }
+        .to_string()
+    };
+    Some((text, end + 1))
+}
+
+/// The text a browser would show for an HTML page, for `--html`: tags,
+/// comments and the content of scripts, styles and the like are dropped,
+/// character references are decoded, whitespace is collapsed outside
+/// `<pre>`, and block elements such as paragraphs, headings and list items
+/// each start a new line.
+pub fn rendered_text(bytes: &[u8]) -> Vec<u8> {
+    let mut text = Text { out: Vec::with_capacity(bytes.len() / 2), gap: Gap::None };
+    let mut pre_depth = 0usize;
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let byte = bytes[index];
+        let next = bytes.get(index + 1).copied().unwrap_or_default();
+
+        if byte == b'<' && bytes[index..].starts_with(b"<!--") {
+            index = find_ignore_case(bytes, index + 4, b"-->").map_or(bytes.len(), |end| end + 3);
+        } else if byte == b'<' && matches!(next, b'!' | b'?') {
+            // Doctypes, CDATA and processing instructions
+            index = tag_end(bytes, index);
+        } else if byte == b'<' && (next.is_ascii_alphabetic() || next == b'/') {
+            let closing = next == b'/';
+            let name_start = index + 1 + usize::from(closing);
+            let name_len = bytes[name_start..]
+                .iter()
+                .position(|b| !b.is_ascii_alphanumeric() && *b != b'-')
+                .unwrap_or(bytes.len() - name_start);
+            let name = String::from_utf8_lossy(&bytes[name_start..name_start + name_len]).to_ascii_lowercase();
+            let end = tag_end(bytes, index);
+            index = end;
+
+            if name.is_empty() {
+                // `</` not followed by a name, such as `</ >`, is dropped
+            } else if HIDDEN.contains(&name.as_str()) {
+                if !closing && !bytes[..end].ends_with(b"/>") {
+                    let close = format!("</{}", name);
+                    index = find_ignore_case(bytes, end, close.as_bytes()).map_or(bytes.len(), |at| tag_end(bytes, at));
+                }
+            } else if name == "br" {
+                text.line_break();
+            } else if BLOCKS.contains(&name.as_str()) {
+                if name == "pre" {
+                    pre_depth = if closing { pre_depth.saturating_sub(1) } else { pre_depth + 1 };
+                }
+                text.gap(Gap::Newline);
+            } else if CELLS.contains(&name.as_str()) {
+                text.gap(Gap::Space);
+            }
+        } else if byte == b'&' && let Some((decoded, length)) = entity(&bytes[index..]) {
+            text.push(decoded.as_bytes());
+            index += length;
+        } else if pre_depth == 0 && matches!(byte, b' ' | b'\t' | b'\n' | b'\r' | b'\x0c') {
+            text.gap(Gap::Space);
+            index += 1;
+        } else {
+            text.push(&[byte]);
+            index += 1;
+        }
+    }
+
+    if !text.out.is_empty() && !text.out.ends_with(b"\n") {
+        text.out.push(b'\n');
+    }
+    text.out
+}
+
+#[cfg(test)]
+mod html_tests {
+    use super::*;
+
+    fn render(html: &str) -> String {
+        String::from_utf8(rendered_text(html.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn test_rendered_text() {
+        let page = r#"<!DOCTYPE html>
+<html><head><title>Not shown</title>
+<style>p { color: red; }</style>
+<script type="text/javascript">if (a < b && c > "</p>") { run(); }</script>
+</head>
+<body>
+  <h1 class="title">Hello,   world</h1>
+  <!-- a comment with <p>markup</p> -->
+  <p>Fish &amp; chips cost &#163;5&nbsp;each.<br>Line two
+  of the paragraph.</p>
+  <ul><li>one</li><li><a href="x.html" title="a > b">two</a></li></ul>
+  <table><tr><td>a</td><td>b</td></tr></table>
+</body></html>"#;
+        assert_eq!(
+            render(page),
+            "Hello, world\nFish & chips cost £5\u{a0}each.\nLine two of the paragraph.\none\ntwo\na b\n",
+        );
+    }
+
+    #[test]
+    fn test_pre_and_stray_markup() {
+        assert_eq!(render("<pre>  keep\n    this</pre>"), "  keep\n    this\n");
+        assert_eq!(render("1 < 2 &unknown; 3 &#x1F600;"), "1 < 2 &unknown; 3 😀\n");
+        assert_eq!(render("<SCRIPT>hidden</script >shown"), "shown\n");
+        assert_eq!(render("<p>unclosed <b"), "unclosed\n");
+        assert_eq!(render(""), "");
+    }
+}
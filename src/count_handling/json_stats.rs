@@ -0,0 +1,85 @@
+use serde_json::{Deserializer, Value};
+
+use crate::error::WcResult;
+
+/// Structure of JSON input. Concatenated or newline-delimited documents are
+/// all counted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct JsonStats {
+    pub documents: usize,
+    /// Keys across all objects
+    pub keys: usize,
+    /// Elements across all arrays
+    pub array_elements: usize,
+    /// Deepest nesting of objects and arrays; a bare scalar is depth 0
+    pub max_depth: usize,
+    /// Characters in string values, not counting keys
+    pub string_chars: usize,
+}
+
+impl JsonStats {
+    pub fn add(&mut self, other: &JsonStats) {
+        self.documents += other.documents;
+        self.keys += other.keys;
+        self.array_elements += other.array_elements;
+        self.max_depth = self.max_depth.max(other.max_depth);
+        self.string_chars += other.string_chars;
+    }
+
+    // `depth` is the nesting level of the container holding `value`
+    fn visit(&mut self, value: &Value, depth: usize) {
+        match value {
+            Value::Object(map) => {
+                self.max_depth = self.max_depth.max(depth + 1);
+                self.keys += map.len();
+                map.values().for_each(|v| self.visit(v, depth + 1));
+            }
+            Value::Array(items) => {
+                self.max_depth = self.max_depth.max(depth + 1);
+                self.array_elements += items.len();
+                items.iter().for_each(|v| self.visit(v, depth + 1));
+            }
+            Value::String(s) => self.string_chars += s.chars().count(),
+            Value::Null | Value::Bool(_) | Value::Number(_) => {}
+        }
+    }
+}
+
+pub fn json_stats(bytes: &[u8]) -> WcResult<JsonStats> {
+    let mut stats = JsonStats::default();
+
+    for document in Deserializer::from_slice(bytes).into_iter::<Value>() {
+        let document = document?;
+        stats.documents += 1;
+        stats.visit(&document, 0);
+    }
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod json_stats_tests {
+    use super::*;
+
+    #[test]
+    fn test_json_stats() {
+        let input = r#"{"name": "héllo", "tags": ["a", "bc"], "meta": {"nested": [[1, 2]]}}"#;
+        let stats = json_stats(input.as_bytes()).unwrap();
+
+        assert_eq!(stats, JsonStats {
+            documents: 1,
+            keys: 4,
+            array_elements: 5,
+            max_depth: 4,
+            string_chars: 8,
+        });
+    }
+
+    #[test]
+    fn test_json_stats_ndjson_and_errors() {
+        let stats = json_stats(b"{\"a\": 1}\n{\"b\": 2}\n\"x\"\n").unwrap();
+        assert_eq!((stats.documents, stats.keys, stats.max_depth), (3, 2, 1));
+
+        assert!(json_stats(b"{\"a\": ").is_err());
+    }
+}
@@ -0,0 +1,52 @@
+use memchr::memchr2_iter;
+
+/// Line terminators by kind.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct LineEndings {
+    /// Unix `\n` not preceded by `\r`
+    pub lf: usize,
+    /// Windows `\r\n`
+    pub crlf: usize,
+    /// Classic Mac `\r` not followed by `\n`
+    pub cr: usize,
+}
+
+impl LineEndings {
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut endings = Self::default();
+
+        for index in memchr2_iter(b'\n', b'\r', bytes) {
+            match bytes[index] {
+                b'\n' if index > 0 && bytes[index - 1] == b'\r' => endings.crlf += 1,
+                b'\n' => endings.lf += 1,
+                _ if bytes.get(index + 1) == Some(&b'\n') => {}
+                _ => endings.cr += 1,
+            }
+        }
+
+        endings
+    }
+
+    pub fn add(&mut self, other: &LineEndings) {
+        self.lf += other.lf;
+        self.crlf += other.crlf;
+        self.cr += other.cr;
+    }
+
+    pub fn total(&self) -> usize {
+        self.lf + self.crlf + self.cr
+    }
+}
+
+#[cfg(test)]
+mod line_endings_tests {
+    use super::*;
+
+    #[test]
+    fn test_line_endings() {
+        let endings = LineEndings::from_bytes(b"unix\nwindows\r\nmac\rmixed\r\r\n\n");
+        assert_eq!(endings, LineEndings { lf: 2, crlf: 2, cr: 2 });
+        assert_eq!(endings.total(), 6);
+        assert_eq!(LineEndings::from_bytes(b"\r"), LineEndings { lf: 0, crlf: 0, cr: 1 });
+    }
+}
@@ -0,0 +1,267 @@
+use memchr::{memchr_iter, memrchr_iter};
+use std::collections::BTreeMap;
+
+use unicode_width::UnicodeWidthChar;
+
+use crate::size::InputRange;
+
+/// Columns between tab stops, for `display_width`.
+const TAB_WIDTH: usize = 8;
+
+/// Distribution of line lengths, kept as an exact histogram so per-file
+/// statistics can be merged into totals without losing the median.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct LineStats {
+    /// Number of lines with each length
+    pub lengths: BTreeMap<usize, u64>,
+}
+
+/// Splits `bytes` into lines without their terminators, including an
+/// unterminated final line.
+pub fn split_lines(bytes: &[u8], terminator: u8) -> impl Iterator<Item = &[u8]> {
+    let unterminated = !bytes.is_empty() && bytes.last() != Some(&terminator);
+    let mut start = 0;
+
+    memchr_iter(terminator, bytes)
+        .chain(unterminated.then_some(bytes.len()))
+        .map(move |end| {
+            let line = &bytes[start..end];
+            start = end + 1;
+            line
+        })
+}
+
+// Offset of the start of line `line`, counting from zero, or the length of
+// `bytes` if it has fewer lines
+fn line_start(bytes: &[u8], terminator: u8, line: u64) -> usize {
+    match line {
+        0 => 0,
+        _ => memchr_iter(terminator, bytes)
+            .nth(usize::try_from(line - 1).unwrap_or(usize::MAX))
+            .map_or(bytes.len(), |pos| pos + 1),
+    }
+}
+
+/// The lines of `bytes` within `range`, with their terminators.
+pub fn select_lines<'a>(bytes: &'a [u8], terminator: u8, range: &InputRange) -> &'a [u8] {
+    let start = line_start(bytes, terminator, range.start);
+    let end = range.end.map_or(bytes.len(), |end| start + line_start(&bytes[start..], terminator, end - range.start));
+    &bytes[start..end]
+}
+
+/// `bytes` without its first `head` and last `tail` lines.
+pub fn trim_lines(bytes: &[u8], terminator: u8, head: usize, tail: usize) -> &[u8] {
+    let start = line_start(bytes, terminator, head as u64);
+    let body = &bytes[start..];
+    let end = match tail {
+        0 => body.len(),
+        // The terminator of the final line doesn't start another line
+        _ => memrchr_iter(terminator, body.strip_suffix(&[terminator]).unwrap_or(body))
+            .nth(tail - 1)
+            .map_or(0, |pos| pos + 1),
+    };
+    &body[..end]
+}
+
+/// Length of a line as `-L` measures it: bytes, not counting carriage returns.
+pub fn line_length(line: &[u8]) -> usize {
+    line.len() - line.iter().filter(|&&b| b == b'\r').count()
+}
+
+/// Columns a line takes up in a terminal: wide CJK characters count twice,
+/// combining marks and control characters not at all, and tabs advance to
+/// the next multiple of 8. Malformed UTF-8 counts a column per byte.
+pub fn display_width(line: &[u8]) -> usize {
+    let mut width = 0;
+    for chunk in line.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            width += match c {
+                '\t' => TAB_WIDTH - width % TAB_WIDTH,
+                c if c.is_control() => 0,
+                c => c.width().unwrap_or(0),
+            };
+        }
+        width += chunk.invalid().len();
+    }
+    width
+}
+
+/// The display width of each line of `bytes`, for `--per-line`.
+pub fn line_widths(bytes: &[u8], terminator: u8) -> Vec<usize> {
+    split_lines(bytes, terminator).map(display_width).collect()
+}
+
+/// 1-based numbers of the lines of `bytes` longer than `limit`, measured as
+/// `line_length` does.
+pub fn long_lines(bytes: &[u8], terminator: u8, limit: usize) -> Vec<usize> {
+    split_lines(bytes, terminator)
+        .enumerate()
+        .filter(|(_, line)| line_length(line) > limit)
+        .map(|(index, _)| index + 1)
+        .collect()
+}
+
+/// Length of the shortest line, leaving out empty lines if `skip_empty`.
+pub fn shortest_line(bytes: &[u8], terminator: u8, skip_empty: bool) -> Option<usize> {
+    split_lines(bytes, terminator)
+        .map(line_length)
+        .filter(|&length| !skip_empty || length > 0)
+        .min()
+}
+
+impl LineStats {
+    pub fn from_bytes(bytes: &[u8], terminator: u8) -> Self {
+        let mut stats = Self::default();
+        for line in split_lines(bytes, terminator) {
+            *stats.lengths.entry(line_length(line)).or_default() += 1;
+        }
+        stats
+    }
+
+    pub fn add(&mut self, other: &LineStats) {
+        for (&length, &count) in &other.lengths {
+            *self.lengths.entry(length).or_default() += count;
+        }
+    }
+
+    pub fn lines(&self) -> u64 {
+        self.lengths.values().sum()
+    }
+
+    pub fn mean(&self) -> f64 {
+        let lines = self.lines();
+        if lines == 0 {
+            return 0.0;
+        }
+        let total: u128 = self.lengths.iter().map(|(&len, &n)| len as u128 * n as u128).sum();
+        total as f64 / lines as f64
+    }
+
+    /// Population standard deviation.
+    pub fn stddev(&self) -> f64 {
+        let lines = self.lines();
+        if lines == 0 {
+            return 0.0;
+        }
+        let mean = self.mean();
+        let squares: f64 = self.lengths.iter()
+            .map(|(&len, &n)| (len as f64 - mean).powi(2) * n as f64)
+            .sum();
+        (squares / lines as f64).sqrt()
+    }
+
+    /// Splits lengths from 0 to the longest into `count` equally wide ranges
+    /// (fewer if there aren't that many lengths), giving each range's first
+    /// and last length and how many lines fall in it.
+    pub fn buckets(&self, count: usize) -> Vec<(usize, usize, u64)> {
+        let Some(&longest) = self.lengths.keys().next_back() else { return Vec::new() };
+        let width = (longest + 1).div_ceil(count.max(1));
+        let mut buckets: Vec<(usize, usize, u64)> = (0..=longest / width)
+            .map(|index| (index * width, index * width + width - 1, 0))
+            .collect();
+        for (&length, &lines) in &self.lengths {
+            buckets[length / width].2 += lines;
+        }
+        buckets
+    }
+
+    /// Middle length, or the mean of the two middle lengths for an even count.
+    pub fn median(&self) -> f64 {
+        let lines = self.lines();
+        if lines == 0 {
+            return 0.0;
+        }
+        let lower = self.nth_length((lines - 1) / 2);
+        let upper = self.nth_length(lines / 2);
+        (lower + upper) as f64 / 2.0
+    }
+
+    // Length of the line at `rank` (0-based) in sorted order
+    fn nth_length(&self, rank: u64) -> usize {
+        let mut seen = 0;
+        for (&length, &count) in &self.lengths {
+            seen += count;
+            if seen > rank {
+                return length;
+            }
+        }
+        0
+    }
+}
+
+#[cfg(test)]
+mod line_stats_tests {
+    use super::*;
+
+    #[test]
+    fn test_select_lines() {
+        let input = b"zero\none\ntwo\nthree";
+        let range = |start, end| InputRange { start, end };
+        assert_eq!(select_lines(input, b'\n', &range(1, Some(3))), b"one\ntwo\n");
+        assert_eq!(select_lines(input, b'\n', &range(2, None)), b"two\nthree");
+        assert_eq!(select_lines(input, b'\n', &range(0, Some(10))), input);
+        assert_eq!(select_lines(input, b'\n', &range(9, None)), b"");
+    }
+
+    #[test]
+    fn test_trim_lines() {
+        let input = b"header\none\ntwo\nfooter\n";
+        assert_eq!(trim_lines(input, b'\n', 1, 1), b"one\ntwo\n");
+        assert_eq!(trim_lines(input, b'\n', 0, 2), b"header\none\n");
+        assert_eq!(trim_lines(b"a\nb\nc", b'\n', 1, 1), b"b\n");
+        assert_eq!(trim_lines(input, b'\n', 3, 3), b"");
+        assert_eq!(trim_lines(input, b'\n', 0, 0), input);
+    }
+
+    #[test]
+    fn test_buckets() {
+        let stats = LineStats::from_bytes(b"a\nbbbbbbbbbb\n\nccccccccccccccccccc\nddd\n", b'\n');
+        assert_eq!(stats.buckets(4), [(0, 4, 3), (5, 9, 0), (10, 14, 1), (15, 19, 1)]);
+        assert_eq!(stats.buckets(100).len(), 20);
+        assert_eq!(LineStats::from_bytes(b"\n\n", b'\n').buckets(5), [(0, 0, 2)]);
+        assert!(LineStats::default().buckets(5).is_empty());
+    }
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(display_width(b"plain\r"), 5);
+        assert_eq!(display_width("日本語".as_bytes()), 6);
+        assert_eq!(display_width("e\u{301}\u{200B}x".as_bytes()), 2);
+        assert_eq!(display_width(b"ab\tc\t"), 16);
+        assert_eq!(display_width(b"\xffok"), 3);
+        assert_eq!(line_widths("a\n\n日\n".as_bytes(), b'\n'), [1, 0, 2]);
+    }
+
+    #[test]
+    fn test_long_lines() {
+        assert_eq!(long_lines(b"short\nmuch longer\nabcdef\r\nabcdefg", b'\n', 6), [2, 4]);
+        assert!(long_lines(b"", b'\n', 0).is_empty());
+    }
+
+    #[test]
+    fn test_split_lines() {
+        let lines: Vec<&[u8]> = split_lines(b"a\n\nbc", b'\n').collect();
+        assert_eq!(lines, [&b"a"[..], b"", b"bc"]);
+        assert_eq!(split_lines(b"a\n", b'\n').count(), 1);
+        assert_eq!(split_lines(b"", b'\n').count(), 0);
+    }
+
+    #[test]
+    fn test_line_stats() {
+        let stats = LineStats::from_bytes(b"ab\nabcd\r\nabcdef\nabcdefgh\n", b'\n');
+
+        assert_eq!(stats.lines(), 4);
+        assert_eq!(stats.mean(), 5.0);
+        assert_eq!(stats.median(), 5.0);
+        assert!((stats.stddev() - 5f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_line_stats_merge() {
+        let mut stats = LineStats::from_bytes(b"a\nabc\n", b'\n');
+        stats.add(&LineStats::from_bytes(b"abcdefghij\n", b'\n'));
+
+        assert_eq!(stats.median(), 3.0);
+        assert_eq!(LineStats::default().median(), 0.0);
+    }
+}
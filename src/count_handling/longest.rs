@@ -0,0 +1,90 @@
+use crate::line_stats::{line_length, split_lines};
+
+/// The longest line of an input, as reported by `--show-longest`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LongestLine {
+    /// 1-based line number; the first of several equally long lines wins
+    pub number: usize,
+    /// Length measured like `-L`: bytes, not counting carriage returns
+    pub length: usize,
+    /// The line itself, cut to the requested width
+    pub content: Option<String>,
+}
+
+/// The longest word of an input, or of all inputs in a total, as reported
+/// by `--show-longest-word`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LongestWord {
+    /// The word, cut to the requested width
+    pub word: String,
+    /// Length in characters of the whole word
+    pub length: usize,
+}
+
+impl LongestWord {
+    /// Keeps the longer of two words; the first on a tie.
+    pub fn longer(self, other: LongestWord) -> LongestWord {
+        if other.length > self.length { other } else { self }
+    }
+}
+
+const ELLIPSIS: char = '…';
+
+/// Finds the longest line in `bytes`. With `content_width`, the line's text
+/// (lossily decoded, without its terminator) is kept, truncated to that many
+/// characters with a trailing ellipsis.
+pub fn longest_line(bytes: &[u8], terminator: u8, content_width: Option<usize>) -> Option<LongestLine> {
+    let mut best: Option<(usize, usize, &[u8])> = None;
+
+    for (index, line) in split_lines(bytes, terminator).enumerate() {
+        let length = line_length(line);
+        if best.is_none_or(|(_, longest, _)| length > longest) {
+            best = Some((index + 1, length, line));
+        }
+    }
+
+    best.map(|(number, length, line)| LongestLine {
+        number,
+        length,
+        content: content_width.map(|width| truncate(line, width)),
+    })
+}
+
+/// Lossily decodes `line`, without a trailing `\r`, cutting it to `width`
+/// characters with a trailing ellipsis.
+pub fn truncate(line: &[u8], width: usize) -> String {
+    let text = String::from_utf8_lossy(line);
+    let text = text.trim_end_matches('\r');
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+
+    let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+    cut.push(ELLIPSIS);
+    cut
+}
+
+#[cfg(test)]
+mod longest_tests {
+    use super::*;
+
+    #[test]
+    fn test_longest_line() {
+        let text = b"short\nthe longest one\r\nalso fifteen ch\nend";
+        let longest = longest_line(text, b'\n', None).unwrap();
+
+        assert_eq!(longest, LongestLine { number: 2, length: 15, content: None });
+        assert_eq!(longest_line(b"", b'\n', None), None);
+        assert_eq!(longest_line(b"a\nbcd", b'\n', None).unwrap().number, 2);
+    }
+
+    #[test]
+    fn test_longest_line_content() {
+        let text = "ok\nthe longest one\r\n";
+        let full = longest_line(text.as_bytes(), b'\n', Some(80)).unwrap();
+        let cut = longest_line(text.as_bytes(), b'\n', Some(8)).unwrap();
+
+        assert_eq!(full.content.as_deref(), Some("the longest one"));
+        assert_eq!(cut.content.as_deref(), Some("the lon…"));
+    }
+}
@@ -0,0 +1,131 @@
+use memchr::memchr_iter;
+use regex::bytes::Regex;
+
+/// Pattern matches from `--count-matches`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct MatchCounts {
+    /// Non-overlapping matches
+    pub matches: usize,
+    /// Lines with at least one match, as `grep -c` counts them
+    pub lines: usize,
+}
+
+impl MatchCounts {
+    pub fn add(&mut self, other: &MatchCounts) {
+        self.matches += other.matches;
+        self.lines += other.lines;
+    }
+
+    fn add_line(&mut self, regex: &Regex, line: &[u8]) {
+        let found = regex.find_iter(line).count();
+        self.matches += found;
+        self.lines += usize::from(found > 0);
+    }
+}
+
+/// Matches a pattern line by line in input fed piece by piece, so a match
+/// never spans a line terminator, as with grep.
+pub struct MatchCounter<'a> {
+    regex: &'a Regex,
+    terminator: u8,
+    // Start of a line left open at the end of the previous piece
+    carry: Vec<u8>,
+    counts: MatchCounts,
+}
+
+impl<'a> MatchCounter<'a> {
+    pub fn new(regex: &'a Regex, terminator: u8) -> Self {
+        Self { regex, terminator, carry: Vec::new(), counts: MatchCounts::default() }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        let mut start = 0;
+        for end in memchr_iter(self.terminator, chunk) {
+            if self.carry.is_empty() {
+                self.counts.add_line(self.regex, &chunk[start..end]);
+            } else {
+                self.carry.extend_from_slice(&chunk[..end]);
+                self.counts.add_line(self.regex, &self.carry);
+                self.carry.clear();
+            }
+            start = end + 1;
+        }
+        self.carry.extend_from_slice(&chunk[start..]);
+    }
+
+    /// Counts so far, including an unterminated final line.
+    pub fn finish(mut self) -> MatchCounts {
+        if !self.carry.is_empty() {
+            self.counts.add_line(self.regex, &self.carry);
+        }
+        self.counts
+    }
+}
+
+/// Keeps only the lines that match a pattern, or with `invert` only those
+/// that don't, for `--filter`.
+#[derive(Debug, Clone)]
+pub struct LineFilter {
+    pub regex: Regex,
+    pub invert: bool,
+}
+
+impl LineFilter {
+    /// The kept lines of `bytes`, with their terminators.
+    pub fn apply(&self, bytes: &[u8], terminator: u8) -> Vec<u8> {
+        let mut kept = Vec::new();
+        for line in bytes.split_inclusive(|&b| b == terminator) {
+            let text = line.strip_suffix(&[terminator]).unwrap_or(line);
+            if self.regex.is_match(text) != self.invert {
+                kept.extend_from_slice(line);
+            }
+        }
+        kept
+    }
+}
+
+/// Counts matches of `regex` in the lines of `bytes`.
+pub fn count_matches(bytes: &[u8], regex: &Regex, terminator: u8) -> MatchCounts {
+    let mut counter = MatchCounter::new(regex, terminator);
+    counter.feed(bytes);
+    counter.finish()
+}
+
+#[cfg(test)]
+mod matches_tests {
+    use super::*;
+
+    #[test]
+    fn test_count_matches() {
+        let regex = Regex::new(r"TODO|FIXME").unwrap();
+        let counts = count_matches(b"TODO one\nclean\nTODO FIXME\nFIXME", &regex, b'\n');
+        assert_eq!(counts, MatchCounts { matches: 4, lines: 3 });
+
+        let anchored = Regex::new(r"^#").unwrap();
+        assert_eq!(count_matches(b"# a\n b #\n#", &anchored, b'\n').lines, 2);
+        assert_eq!(count_matches(b"", &anchored, b'\n'), MatchCounts::default());
+    }
+
+    #[test]
+    fn test_line_filter() {
+        let filter = LineFilter { regex: Regex::new(r"^// TODO").unwrap(), invert: false };
+        let input = b"// TODO one two\ncode();\n// TODO three";
+        assert_eq!(filter.apply(input, b'\n'), b"// TODO one two\n// TODO three");
+
+        let inverted = LineFilter { invert: true, ..filter };
+        assert_eq!(inverted.apply(input, b'\n'), b"code();\n");
+    }
+
+    #[test]
+    fn test_matches_split_across_pieces() {
+        let regex = Regex::new(r"needle").unwrap();
+        let input = b"a needle\nneedle needle\nhay\nneedle";
+        let expected = count_matches(input, &regex, b'\n');
+
+        for size in 1..input.len() {
+            let mut counter = MatchCounter::new(&regex, b'\n');
+            input.chunks(size).for_each(|piece| counter.feed(piece));
+            assert_eq!(counter.finish(), expected, "piece size {size}");
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use crate::line_stats::split_lines;
+
+/// How big a change is, from unified diff text read with `--diff-input`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PatchStats {
+    /// Files with a `diff --git` or `+++` header
+    pub files: usize,
+    pub added_lines: usize,
+    pub removed_lines: usize,
+    /// Whitespace-separated words on added lines, then on removed ones
+    pub added_words: usize,
+    pub removed_words: usize,
+}
+
+fn words(text: &[u8]) -> usize {
+    text.split(u8::is_ascii_whitespace).filter(|word| !word.is_empty()).count()
+}
+
+// The old and new line counts of a `@@ -1,5 +1,7 @@` hunk header; a count
+// left out is 1
+fn hunk_lengths(header: &[u8]) -> Option<(usize, usize)> {
+    let header = std::str::from_utf8(header).ok()?;
+    let mut ranges = header.strip_prefix("@@ ")?.split(' ');
+    let length = |range: &str, sign: char| -> Option<usize> {
+        let range = range.strip_prefix(sign)?;
+        match range.split_once(',') {
+            Some((_, length)) => length.parse().ok(),
+            None => range.parse::<usize>().ok().map(|_| 1),
+        }
+    };
+    Some((length(ranges.next()?, '-')?, length(ranges.next()?, '+')?))
+}
+
+// A path as git writes it in headers: as is, or in double quotes with C
+// escapes when it has tabs, newlines, quotes, backslashes or (unless
+// core.quotePath is off) other bytes outside ASCII
+fn unquoted(path: &str) -> String {
+    let Some(quoted) = path.strip_prefix('"').and_then(|rest| rest.strip_suffix('"')) else {
+        return path.to_string();
+    };
+    let bytes = quoted.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let byte = bytes[index];
+        index += 1;
+        if byte != b'\\' || index == bytes.len() {
+            out.push(byte);
+            continue;
+        }
+        let escaped = bytes[index];
+        index += 1;
+        out.push(match escaped {
+            b'a' => 0x07,
+            b'b' => 0x08,
+            b'f' => 0x0c,
+            b'n' => b'\n',
+            b'r' => b'\r',
+            b't' => b'\t',
+            b'v' => 0x0b,
+            // Up to three octal digits, as in `\303\251` for é
+            b'0'..=b'7' => {
+                let octal = &bytes[index - 1..];
+                let digits = octal.iter().take(3).take_while(|b| matches!(b, b'0'..=b'7')).count();
+                index += digits - 1;
+                octal[..digits].iter().fold(0u8, |code, b| code.wrapping_mul(8).wrapping_add(b - b'0'))
+            }
+            other => other,
+        });
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// The path a `diff --git` section is about, from its `+++`, `---` or
+// `diff --git` header, without git's a/ and b/ prefixes
+fn file_name(section: &[u8], terminator: u8) -> String {
+    let mut header = "";
+    let (mut old, mut new) = (None, None);
+    for line in split_lines(section, terminator).take_while(|line| !line.starts_with(b"@@ ")) {
+        let line = std::str::from_utf8(line.strip_suffix(b"\r").unwrap_or(line)).unwrap_or_default();
+        // GNU-style headers may end in a tab and a timestamp
+        let path = |rest: &str| rest.split('\t').next().map(unquoted).filter(|path| path != "/dev/null");
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            header = rest;
+        } else if let Some(rest) = line.strip_prefix("--- ") {
+            old = path(rest);
+        } else if let Some(rest) = line.strip_prefix("+++ ") {
+            new = path(rest);
+        }
+    }
+
+    let unprefixed = |path: String, prefix: &str| path.strip_prefix(prefix).map(str::to_string).unwrap_or(path);
+    match (new, old) {
+        (Some(new), _) => unprefixed(new, "b/"),
+        (None, Some(old)) => unprefixed(old, "a/"),
+        // Binary files and pure renames have only `diff --git a/x b/y`
+        (None, None) => {
+            let new = [" \"b/", " b/"].iter().find_map(|marker| header.rfind(marker)).map_or(header, |at| &header[at + 1..]);
+            unprefixed(unquoted(new), "b/")
+        }
+    }
+}
+
+impl PatchStats {
+    /// Reads `bytes` as unified diff output, such as `git diff` or `diff -u`
+    /// prints. Hunk headers say how many lines follow, so a removed line
+    /// that itself starts with `--` isn't taken for a file header.
+    pub fn from_bytes(bytes: &[u8], terminator: u8) -> Self {
+        let mut stats = Self::default();
+        // Old and new lines left in the current hunk
+        let (mut old_left, mut new_left) = (0usize, 0usize);
+        // Whether a `diff --git` header has already counted the file its
+        // `+++` header names
+        let mut git_header = false;
+
+        for line in split_lines(bytes, terminator) {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+            if old_left > 0 || new_left > 0 {
+                match line.first() {
+                    Some(b'+') => {
+                        stats.added_lines += 1;
+                        stats.added_words += words(&line[1..]);
+                        new_left = new_left.saturating_sub(1);
+                    }
+                    Some(b'-') => {
+                        stats.removed_lines += 1;
+                        stats.removed_words += words(&line[1..]);
+                        old_left = old_left.saturating_sub(1);
+                    }
+                    // "\ No newline at end of file"
+                    Some(b'\\') => {}
+                    // Context, which some tools strip to an empty line
+                    _ => {
+                        old_left = old_left.saturating_sub(1);
+                        new_left = new_left.saturating_sub(1);
+                    }
+                }
+                continue;
+            }
+
+            if line.starts_with(b"diff --git ") {
+                stats.files += 1;
+                git_header = true;
+            } else if line.starts_with(b"+++ ") {
+                stats.files += usize::from(!git_header);
+                git_header = false;
+            } else if let Some((old, new)) = hunk_lengths(line) {
+                (old_left, new_left) = (old, new);
+            }
+        }
+
+        stats
+    }
+
+    /// Like `from_bytes`, but for each file of a `git diff`, named by its
+    /// path after the change (before it, for a deleted file). Anything
+    /// before the first `diff --git` header, such as a commit message, is
+    /// left out.
+    pub fn per_file(bytes: &[u8], terminator: u8) -> Vec<(String, PatchStats)> {
+        let mut starts: Vec<usize> = Vec::new();
+        let mut offset = 0;
+        for line in split_lines(bytes, terminator) {
+            if line.starts_with(b"diff --git ") {
+                starts.push(offset);
+            }
+            offset += line.len() + 1;
+        }
+
+        starts.iter()
+            .enumerate()
+            .map(|(index, &start)| {
+                let end = starts.get(index + 1).copied().unwrap_or(bytes.len());
+                let section = &bytes[start..end];
+                (file_name(section, terminator), Self::from_bytes(section, terminator))
+            })
+            .collect()
+    }
+
+    pub fn add(&mut self, other: &PatchStats) {
+        self.files += other.files;
+        self.added_lines += other.added_lines;
+        self.removed_lines += other.removed_lines;
+        self.added_words += other.added_words;
+        self.removed_words += other.removed_words;
+    }
+}
+
+#[cfg(test)]
+mod patch_tests {
+    use super::*;
+
+    #[test]
+    fn test_hunk_lengths() {
+        assert_eq!(hunk_lengths(b"@@ -1,5 +1,7 @@ fn main() {"), Some((5, 7)));
+        assert_eq!(hunk_lengths(b"@@ -3 +3,0 @@"), Some((1, 0)));
+        assert_eq!(hunk_lengths(b"@@ nonsense @@"), None);
+    }
+
+    #[test]
+    fn test_patch_stats() {
+        let git = b"\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,3 @@
+ fn main() {
+-    old();
++    new(a, b);
+ }
+diff --git a/logo.png b/logo.png
+Binary files a/logo.png and b/logo.png differ
+diff --git a/notes.txt b/notes.txt
+--- a/notes.txt
++++ b/notes.txt
+@@ -1 +1,2 @@
+--- not a header
++++ not a header either
++added line
+\\ No newline at end of file
+";
+        let stats = PatchStats::from_bytes(git, b'\n');
+        assert_eq!(stats, PatchStats { files: 3, added_lines: 3, removed_lines: 2, added_words: 9, removed_words: 5 });
+
+        let plain = b"--- a.txt\r\n+++ b.txt\r\n@@ -1 +1 @@\r\n-one\r\n+two words\r\n";
+        let stats = PatchStats::from_bytes(plain, b'\n');
+        assert_eq!((stats.files, stats.added_words, stats.removed_words), (1, 2, 1));
+        assert_eq!(PatchStats::from_bytes(b"not a diff\n", b'\n'), PatchStats::default());
+    }
+
+    #[test]
+    fn test_per_file() {
+        let git = b"\
+commit message
+diff --git a/kept.rs b/kept.rs
+--- a/kept.rs
++++ b/kept.rs
+@@ -1 +1,2 @@
+ context
++added here
+diff --git a/gone.txt b/gone.txt
+deleted file mode 100644
+--- a/gone.txt
++++ /dev/null
+@@ -1,2 +0,0 @@
+-one
+-two
+diff --git a/logo.png b/logo.png
+Binary files a/logo.png and b/logo.png differ
+";
+        let files = PatchStats::per_file(git, b'\n');
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["kept.rs", "gone.txt", "logo.png"]);
+        assert_eq!((files[0].1.added_lines, files[0].1.added_words), (1, 2));
+        assert_eq!((files[1].1.removed_lines, files[1].1.files), (2, 1));
+        assert_eq!(files[2].1.added_lines, 0);
+    }
+
+    #[test]
+    fn test_quoted_file_names() {
+        let git = b"\
+diff --git \"a/tab\\there.txt\" \"b/tab\\there.txt\"
+--- \"a/tab\\there.txt\"
++++ \"b/tab\\there.txt\"
+@@ -0,0 +1 @@
++x
+diff --git \"a/caf\\303\\251 \\\"bar\\\".png\" \"b/caf\\303\\251 \\\"bar\\\".png\"
+Binary files differ
+";
+        let files = PatchStats::per_file(git, b'\n');
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["tab\there.txt", "café \"bar\".png"]);
+    }
+}
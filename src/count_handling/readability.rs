@@ -0,0 +1,154 @@
+/// Sentence, word and syllable counts behind the readability scores of
+/// `rs-wc readability`. Counts add up across inputs, so a total scores the
+/// inputs as one text. Syllables are estimated with English spelling rules.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Readability {
+    pub sentences: usize,
+    /// Runs of letters, with inner apostrophes and hyphens; numbers and
+    /// symbols aren't words here
+    pub words: usize,
+    pub syllables: usize,
+    /// Words of three or more syllables
+    pub complex_words: usize,
+}
+
+// Estimated syllables in `word`: groups of vowels, less a silent final e,
+// and at least one
+fn syllables(word: &str) -> usize {
+    let word = word.to_lowercase();
+    let letters: Vec<char> = word.chars().filter(|c| c.is_alphabetic()).collect();
+    let is_vowel = |c: char| matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
+
+    let mut groups = 0;
+    let mut previous_vowel = false;
+    for &c in &letters {
+        let vowel = is_vowel(c);
+        if vowel && !previous_vowel {
+            groups += 1;
+        }
+        previous_vowel = vowel;
+    }
+
+    // "make" has one syllable, but "table" has two
+    if let [.., before, 'e'] = letters[..]
+        && groups > 1
+        && !is_vowel(before)
+        && !word.ends_with("le")
+    {
+        groups -= 1;
+    }
+    groups.max(1)
+}
+
+impl Readability {
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let text = String::from_utf8_lossy(bytes);
+        let mut counts = Self::default();
+        // Whether words have been seen since the last sentence ended
+        let mut open_sentence = false;
+        let mut word = String::new();
+
+        let finish_word = |word: &mut String, counts: &mut Readability| {
+            let trimmed = word.trim_matches(|c| c == '\'' || c == '-' || c == '’');
+            if !trimmed.is_empty() {
+                let syllables = syllables(trimmed);
+                counts.words += 1;
+                counts.syllables += syllables;
+                counts.complex_words += usize::from(syllables >= 3);
+            }
+            word.clear();
+        };
+
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c.is_alphabetic() || (!word.is_empty() && matches!(c, '\'' | '-' | '’')) {
+                word.push(c);
+                open_sentence = true;
+                continue;
+            }
+            finish_word(&mut word, &mut counts);
+
+            // A run of terminal punctuation ends a sentence when followed by
+            // a space or the end of the text, so "3.5" doesn't
+            if matches!(c, '.' | '!' | '?') && open_sentence {
+                while chars.next_if(|c| matches!(c, '.' | '!' | '?' | '"' | '\'' | ')' | '”' | '’')).is_some() {}
+                if chars.peek().is_none_or(|c| c.is_whitespace()) {
+                    counts.sentences += 1;
+                    open_sentence = false;
+                }
+            }
+        }
+        finish_word(&mut word, &mut counts);
+        // Text that trails off without punctuation is still a sentence
+        counts.sentences += usize::from(open_sentence);
+
+        counts
+    }
+
+    pub fn add(&mut self, other: &Readability) {
+        self.sentences += other.sentences;
+        self.words += other.words;
+        self.syllables += other.syllables;
+        self.complex_words += other.complex_words;
+    }
+
+    // Words per sentence and syllables per word, when there are words
+    fn ratios(&self) -> Option<(f64, f64)> {
+        (self.words > 0 && self.sentences > 0).then(|| (
+            self.words as f64 / self.sentences as f64,
+            self.syllables as f64 / self.words as f64,
+        ))
+    }
+
+    /// Flesch reading ease: higher is easier, 60-70 is plain English
+    pub fn flesch_reading_ease(&self) -> Option<f64> {
+        self.ratios().map(|(words, syllables)| 206.835 - 1.015 * words - 84.6 * syllables)
+    }
+
+    /// Flesch-Kincaid grade level: the US school grade the text suits
+    pub fn flesch_kincaid_grade(&self) -> Option<f64> {
+        self.ratios().map(|(words, syllables)| 0.39 * words + 11.8 * syllables - 15.59)
+    }
+
+    /// Gunning fog index: years of schooling needed on a first reading
+    pub fn gunning_fog(&self) -> Option<f64> {
+        self.ratios().map(|(words, _)| 0.4 * (words + 100.0 * self.complex_words as f64 / self.words as f64))
+    }
+}
+
+#[cfg(test)]
+mod readability_tests {
+    use super::*;
+
+    #[test]
+    fn test_syllables() {
+        let counts: Vec<usize> = ["cat", "make", "table", "reading", "readability", "the", "rhythm", "queue"]
+            .iter()
+            .map(|word| syllables(word))
+            .collect();
+        assert_eq!(counts, [1, 1, 2, 2, 5, 1, 1, 1]);
+    }
+
+    #[test]
+    fn test_readability_counts() {
+        let text = b"The cat sat on the mat. It was 3.5 feet wide!\nDon't stop \"now.\" Trailing words";
+        let counts = Readability::from_bytes(text);
+
+        assert_eq!(counts.sentences, 4);
+        assert_eq!(counts.words, 15);
+        assert_eq!(Readability::from_bytes(b"  42 ...  "), Readability::default());
+    }
+
+    #[test]
+    fn test_readability_scores() {
+        let counts = Readability { sentences: 2, words: 20, syllables: 30, complex_words: 2 };
+        assert!((counts.flesch_reading_ease().unwrap() - 69.785).abs() < 1e-9);
+        assert!((counts.flesch_kincaid_grade().unwrap() - 6.01).abs() < 1e-9);
+        assert!((counts.gunning_fog().unwrap() - 8.0).abs() < 1e-9);
+        assert_eq!(Readability::default().gunning_fog(), None);
+
+        let mut total = counts.clone();
+        total.add(&counts);
+        assert_eq!(total.flesch_reading_ease(), counts.flesch_reading_ease());
+    }
+}
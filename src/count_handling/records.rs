@@ -0,0 +1,100 @@
+use memchr::memmem::Finder;
+
+/// Counts non-overlapping occurrences of a multi-byte separator in input fed
+/// piece by piece, including separators split across two pieces.
+pub struct RecordCounter {
+    finder: Finder<'static>,
+    // Unmatched bytes from the end of the previous piece that could still
+    // begin a separator; always shorter than the separator.
+    carry: Vec<u8>,
+    count: usize,
+}
+
+impl RecordCounter {
+    pub fn new(separator: &[u8]) -> Self {
+        assert!(!separator.is_empty(), "record separator must not be empty");
+        Self {
+            finder: Finder::new(separator).into_owned(),
+            carry: Vec::new(),
+            count: 0,
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        let len = self.finder.needle().len();
+        let keep = len - 1;
+        let mut start = 0;
+
+        // A separator straddling the boundary starts in the carry and ends
+        // within the first `keep` bytes of this piece
+        if !self.carry.is_empty() {
+            let carried = self.carry.len();
+            let head = &chunk[..chunk.len().min(keep)];
+            self.carry.extend_from_slice(head);
+
+            match self.finder.find(&self.carry) {
+                Some(pos) if pos < carried => {
+                    self.count += 1;
+                    start = pos + len - carried;
+                }
+                _ if head.len() == chunk.len() => {
+                    let excess = self.carry.len().saturating_sub(keep);
+                    self.carry.drain(..excess);
+                    return;
+                }
+                _ => {}
+            }
+            self.carry.clear();
+        }
+
+        let rest = &chunk[start..];
+        let mut matched_to = 0;
+        for pos in self.finder.find_iter(rest) {
+            self.count += 1;
+            matched_to = pos + len;
+        }
+
+        let tail = matched_to.max(rest.len().saturating_sub(keep));
+        self.carry.extend_from_slice(&rest[tail..]);
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+/// Counts non-overlapping occurrences of `separator` in `bytes`.
+pub fn count_records(bytes: &[u8], separator: &[u8]) -> usize {
+    let mut counter = RecordCounter::new(separator);
+    counter.feed(bytes);
+    counter.count()
+}
+
+#[cfg(test)]
+mod records_tests {
+    use super::*;
+
+    #[test]
+    fn test_count_records() {
+        assert_eq!(count_records(b"a\n\nb\n\nc", b"\n\n"), 2);
+        assert_eq!(count_records(b"\n\n\n", b"\n\n"), 1);
+        assert_eq!(count_records(b"<r>1</record><r>2</record>", b"</record>"), 2);
+        assert_eq!(count_records(b"", b"</record>"), 0);
+    }
+
+    #[test]
+    fn test_records_split_across_pieces() {
+        let input = b"one</record>two</record>three</rec";
+        let expected = count_records(input, b"</record>");
+
+        for size in 1..input.len() {
+            let mut counter = RecordCounter::new(b"</record>");
+            input.chunks(size).for_each(|piece| counter.feed(piece));
+            assert_eq!(counter.count(), expected, "piece size {size}");
+        }
+
+        let mut counter = RecordCounter::new(b"\n\n");
+        [&b"\n"[..], b"\n", b"\n", b"\n"].iter().for_each(|piece| counter.feed(piece));
+        assert_eq!(counter.count(), 2);
+    }
+}
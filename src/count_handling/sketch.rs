@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+
+use crate::line_stats::{line_length, split_lines};
+
+/// Relative error of every estimate: 1%.
+const RELATIVE_ACCURACY: f64 = 0.01;
+
+/// Streaming quantile sketch over non-negative values. Values fall into
+/// logarithmically sized buckets, so memory grows with the log of the largest
+/// value rather than with the input, and any estimate is within 1% of a value
+/// actually seen at that rank. Sketches of different inputs merge exactly.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct QuantileSketch {
+    zeros: u64,
+    buckets: BTreeMap<i32, u64>,
+    count: u64,
+}
+
+fn gamma() -> f64 {
+    (1.0 + RELATIVE_ACCURACY) / (1.0 - RELATIVE_ACCURACY)
+}
+
+impl QuantileSketch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sketch of the line lengths in `bytes`, measured like `-L`.
+    pub fn of_line_lengths(bytes: &[u8], terminator: u8) -> Self {
+        let mut sketch = Self::new();
+        for line in split_lines(bytes, terminator) {
+            sketch.insert(line_length(line) as u64);
+        }
+        sketch
+    }
+
+    pub fn insert(&mut self, value: u64) {
+        self.count += 1;
+        if value == 0 {
+            self.zeros += 1;
+        } else {
+            let index = (value as f64).ln() / gamma().ln();
+            *self.buckets.entry(index.ceil() as i32).or_default() += 1;
+        }
+    }
+
+    pub fn add(&mut self, other: &QuantileSketch) {
+        self.zeros += other.zeros;
+        self.count += other.count;
+        for (&index, &count) in &other.buckets {
+            *self.buckets.entry(index).or_default() += count;
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Estimated value at `percentile` (0-100), or 0 for an empty sketch.
+    pub fn percentile(&self, percentile: f64) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+
+        let rank = ((percentile / 100.0).clamp(0.0, 1.0) * (self.count - 1) as f64).round() as u64;
+        if rank < self.zeros {
+            return 0.0;
+        }
+
+        let mut seen = self.zeros;
+        for (&index, &count) in &self.buckets {
+            seen += count;
+            if seen > rank {
+                // Midpoint of the bucket (gamma^(i-1), gamma^i] in relative terms
+                return 2.0 * gamma().powi(index) / (gamma() + 1.0);
+            }
+        }
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod sketch_tests {
+    use super::*;
+
+    fn within_accuracy(estimate: f64, exact: f64) -> bool {
+        (estimate - exact).abs() <= exact * RELATIVE_ACCURACY + 1e-9
+    }
+
+    #[test]
+    fn test_sketch_percentiles() {
+        let mut sketch = QuantileSketch::new();
+        (1..=1000).for_each(|v| sketch.insert(v));
+
+        assert!(within_accuracy(sketch.percentile(50.0), 500.0));
+        assert!(within_accuracy(sketch.percentile(90.0), 900.0));
+        assert!(within_accuracy(sketch.percentile(100.0), 1000.0));
+        assert!(sketch.buckets.len() < 400);
+    }
+
+    #[test]
+    fn test_sketch_merge_and_zeros() {
+        let mut sketch = QuantileSketch::of_line_lengths(b"\n\n\nabcd\n", b'\n');
+        assert_eq!(sketch.percentile(50.0), 0.0);
+
+        sketch.add(&QuantileSketch::of_line_lengths(b"abcd\nabcd\nabcd\n", b'\n'));
+        assert_eq!(sketch.count(), 7);
+        assert!(within_accuracy(sketch.percentile(90.0), 4.0));
+        assert_eq!(QuantileSketch::new().percentile(99.0), 0.0);
+    }
+}
@@ -0,0 +1,255 @@
+use serde_json::{json, Map, Value};
+use std::{collections::BTreeMap, fmt};
+
+use crate::counter::WcCounter;
+use crate::error::{WcError, WcResult};
+use crate::threshold::Metric;
+
+const SNAPSHOT_VERSION: u64 = 1;
+
+/// Recorded counts keyed by filename, as written by `rs-wc snapshot --save`.
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    pub files: BTreeMap<String, WcCounter>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChangeKind {
+    Grew,
+    Shrank,
+    Missing,
+    Added,
+}
+
+/// A difference between a snapshot and the current counts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotChange {
+    pub filename: String,
+    pub kind: ChangeKind,
+    pub metric: Option<Metric>,
+    pub old: usize,
+    pub new: usize,
+}
+
+impl SnapshotChange {
+    /// Growth or a vanished file is a regression; shrinking and new files aren't.
+    pub fn is_regression(&self) -> bool {
+        matches!(self.kind, ChangeKind::Grew | ChangeKind::Missing)
+    }
+}
+
+impl fmt::Display for SnapshotChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.kind, self.metric) {
+            (ChangeKind::Missing, _) => write!(f, "missing {}", self.filename),
+            (ChangeKind::Added, _) => write!(f, "new {}", self.filename),
+            (kind, Some(metric)) => {
+                let verb = if kind == ChangeKind::Grew { "grew" } else { "shrank" };
+                write!(f, "{} {} {} {} -> {}", verb, self.filename, metric.name(), self.old, self.new)?;
+                if self.old > 0 {
+                    let percent = (self.new as f64 - self.old as f64) * 100.0 / self.old as f64;
+                    write!(f, " ({:+.1}%)", percent)?;
+                }
+                Ok(())
+            }
+            (_, None) => write!(f, "changed {}", self.filename),
+        }
+    }
+}
+
+fn counter_to_json(counter: &WcCounter) -> Value {
+    json!({
+        "lines": counter.lines,
+        "words": counter.words,
+        "bytes": counter.bytes,
+        "chars": counter.chars,
+        "max_line_length": counter.max_line_length,
+    })
+}
+
+fn counter_from_json(filename: &str, value: &Value) -> WcResult<WcCounter> {
+    let field = |key: &str| -> WcResult<usize> {
+        value.get(key)
+            .and_then(Value::as_u64)
+            .map(|v| v as usize)
+            .ok_or_else(|| WcError::invalid_argument(format!(
+                "snapshot entry '{}' is missing '{}'", filename, key
+            )))
+    };
+
+    Ok(WcCounter {
+        lines: field("lines")?,
+        words: field("words")?,
+        bytes: field("bytes")?,
+        chars: field("chars")?,
+        max_line_length: field("max_line_length")?,
+        filename: Some(filename.to_string()),
+        ..WcCounter::default()
+    })
+}
+
+// The key a result is recorded under: its filename with `/` separators and
+// no leading `./`, so `./src/a.rs` and `src\a.rs` match `src/a.rs`
+fn snapshot_key(result: &WcCounter) -> String {
+    let name = result.filename.as_deref().unwrap_or("-").replace('\\', "/");
+    let mut name = name.as_str();
+    while let Some(rest) = name.strip_prefix("./") {
+        name = rest;
+    }
+    name.to_string()
+}
+
+impl Snapshot {
+    pub fn from_results(results: &[WcCounter]) -> Self {
+        let files = results.iter()
+            .map(|r| (snapshot_key(r), r.clone()))
+            .collect();
+        Self { files }
+    }
+
+    pub fn to_json(&self) -> WcResult<String> {
+        let files: Map<String, Value> = self.files.iter()
+            .map(|(name, counter)| (name.clone(), counter_to_json(counter)))
+            .collect();
+
+        serde_json::to_string_pretty(&json!({
+            "version": SNAPSHOT_VERSION,
+            "files": files,
+        })).map_err(Into::into)
+    }
+
+    pub fn from_json(input: &str) -> WcResult<Self> {
+        let value: Value = serde_json::from_str(input)?;
+
+        let version = value.get("version").and_then(Value::as_u64);
+        if version != Some(SNAPSHOT_VERSION) {
+            return Err(WcError::invalid_argument(format!(
+                "unsupported snapshot version: {}",
+                version.map_or_else(|| "none".to_string(), |v| v.to_string())
+            )));
+        }
+
+        let entries = value.get("files")
+            .and_then(Value::as_object)
+            .ok_or_else(|| WcError::invalid_argument("snapshot has no 'files' object"))?;
+
+        let files = entries.iter()
+            .map(|(name, counts)| {
+                let counter = counter_from_json(name, counts)?;
+                Ok((snapshot_key(&counter), counter))
+            })
+            .collect::<WcResult<_>>()?;
+
+        Ok(Self { files })
+    }
+
+    /// Compares current counts against the snapshot. A metric is reported when
+    /// it changed by more than `tolerance` percent; files only present on one
+    /// side are reported as missing or added.
+    pub fn compare(
+        &self,
+        current: &[WcCounter],
+        metrics: &[Metric],
+        tolerance: f64,
+    ) -> Vec<SnapshotChange> {
+        let current: BTreeMap<String, &WcCounter> = current.iter()
+            .map(|r| (snapshot_key(r), r))
+            .collect();
+        let mut changes = Vec::new();
+
+        for (filename, old) in &self.files {
+            let Some(new) = current.get(filename) else {
+                changes.push(SnapshotChange {
+                    filename: filename.clone(),
+                    kind: ChangeKind::Missing,
+                    metric: None,
+                    old: 0,
+                    new: 0,
+                });
+                continue;
+            };
+
+            for &metric in metrics {
+                let (before, after) = (metric.value(old), metric.value(new));
+                let allowed = before as f64 * tolerance / 100.0;
+                let kind = if after as f64 > before as f64 + allowed {
+                    ChangeKind::Grew
+                } else if (after as f64) < before as f64 - allowed {
+                    ChangeKind::Shrank
+                } else {
+                    continue;
+                };
+
+                changes.push(SnapshotChange {
+                    filename: filename.clone(),
+                    kind,
+                    metric: Some(metric),
+                    old: before,
+                    new: after,
+                });
+            }
+        }
+
+        for filename in current.keys().filter(|name| !self.files.contains_key(*name)) {
+            changes.push(SnapshotChange {
+                filename: filename.clone(),
+                kind: ChangeKind::Added,
+                metric: None,
+                old: 0,
+                new: 0,
+            });
+        }
+
+        changes
+    }
+}
+
+#[cfg(test)]
+mod snapshot_tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let snapshot = Snapshot::from_results(&[WcCounter::named("a.rs", 10), WcCounter::named("b.rs", 20)]);
+        let restored = Snapshot::from_json(&snapshot.to_json().unwrap()).unwrap();
+
+        assert_eq!(restored.files.len(), 2);
+        assert_eq!(restored.files["b.rs"].lines, 20);
+    }
+
+    #[test]
+    fn test_snapshot_compare_with_tolerance() {
+        let snapshot = Snapshot::from_results(&[
+            WcCounter::named("a.rs", 100),
+            WcCounter::named("b.rs", 100),
+            WcCounter::named("gone.rs", 5),
+        ]);
+        let current = [WcCounter::named("a.rs", 104), WcCounter::named("b.rs", 80), WcCounter::named("new.rs", 1)];
+
+        let changes = snapshot.compare(&current, &[Metric::Lines], 5.0);
+        let kinds: Vec<(&str, ChangeKind)> = changes.iter()
+            .map(|c| (c.filename.as_str(), c.kind))
+            .collect();
+
+        assert_eq!(kinds, [
+            ("b.rs", ChangeKind::Shrank),
+            ("gone.rs", ChangeKind::Missing),
+            ("new.rs", ChangeKind::Added),
+        ]);
+        assert_eq!(changes[0].to_string(), "shrank b.rs lines 100 -> 80 (-20.0%)");
+    }
+
+    #[test]
+    fn test_snapshot_compare_normalises_filenames() {
+        let snapshot = Snapshot::from_results(&[WcCounter::named("./src/a.rs", 10), WcCounter::named("src\\b.rs", 20)]);
+        let current = [WcCounter::named("src/a.rs", 10), WcCounter::named("./src/b.rs", 20)];
+
+        assert_eq!(snapshot.compare(&current, &[Metric::Lines], 0.0), []);
+    }
+
+    #[test]
+    fn test_snapshot_rejects_unknown_version() {
+        let result = Snapshot::from_json(r#"{"version": 99, "files": {}}"#);
+        assert!(matches!(result, Err(WcError::InvalidArgument(_))));
+    }
+}
@@ -0,0 +1,185 @@
+use std::fmt;
+
+use crate::line_stats::split_lines;
+
+/// Located characters kept per input; the rest are only counted.
+pub const MAX_LOCATIONS: usize = 100;
+
+/// Why a character is reported by `--scan-unicode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InvisibleKind {
+    /// Reorders the text around it, as in Trojan Source attacks
+    Bidi,
+    /// Has no width, so can hide inside an identifier or between words
+    ZeroWidth,
+    /// Renders as nothing, or as blank, in most fonts
+    Invisible,
+}
+
+impl InvisibleKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            InvisibleKind::Bidi => "bidi control",
+            InvisibleKind::ZeroWidth => "zero-width character",
+            InvisibleKind::Invisible => "invisible character",
+        }
+    }
+}
+
+// The kind and name of each reported code point
+const INVISIBLE_CHARS: &[(char, InvisibleKind, &str)] = &[
+    ('\u{00AD}', InvisibleKind::Invisible, "SOFT HYPHEN"),
+    ('\u{061C}', InvisibleKind::Bidi, "ARABIC LETTER MARK"),
+    ('\u{115F}', InvisibleKind::Invisible, "HANGUL CHOSEONG FILLER"),
+    ('\u{1160}', InvisibleKind::Invisible, "HANGUL JUNGSEONG FILLER"),
+    ('\u{180E}', InvisibleKind::ZeroWidth, "MONGOLIAN VOWEL SEPARATOR"),
+    ('\u{200B}', InvisibleKind::ZeroWidth, "ZERO WIDTH SPACE"),
+    ('\u{200C}', InvisibleKind::ZeroWidth, "ZERO WIDTH NON-JOINER"),
+    ('\u{200D}', InvisibleKind::ZeroWidth, "ZERO WIDTH JOINER"),
+    ('\u{200E}', InvisibleKind::Bidi, "LEFT-TO-RIGHT MARK"),
+    ('\u{200F}', InvisibleKind::Bidi, "RIGHT-TO-LEFT MARK"),
+    ('\u{202A}', InvisibleKind::Bidi, "LEFT-TO-RIGHT EMBEDDING"),
+    ('\u{202B}', InvisibleKind::Bidi, "RIGHT-TO-LEFT EMBEDDING"),
+    ('\u{202C}', InvisibleKind::Bidi, "POP DIRECTIONAL FORMATTING"),
+    ('\u{202D}', InvisibleKind::Bidi, "LEFT-TO-RIGHT OVERRIDE"),
+    ('\u{202E}', InvisibleKind::Bidi, "RIGHT-TO-LEFT OVERRIDE"),
+    ('\u{2060}', InvisibleKind::ZeroWidth, "WORD JOINER"),
+    ('\u{2061}', InvisibleKind::Invisible, "FUNCTION APPLICATION"),
+    ('\u{2062}', InvisibleKind::Invisible, "INVISIBLE TIMES"),
+    ('\u{2063}', InvisibleKind::Invisible, "INVISIBLE SEPARATOR"),
+    ('\u{2064}', InvisibleKind::Invisible, "INVISIBLE PLUS"),
+    ('\u{2066}', InvisibleKind::Bidi, "LEFT-TO-RIGHT ISOLATE"),
+    ('\u{2067}', InvisibleKind::Bidi, "RIGHT-TO-LEFT ISOLATE"),
+    ('\u{2068}', InvisibleKind::Bidi, "FIRST STRONG ISOLATE"),
+    ('\u{2069}', InvisibleKind::Bidi, "POP DIRECTIONAL ISOLATE"),
+    ('\u{3164}', InvisibleKind::Invisible, "HANGUL FILLER"),
+    ('\u{FEFF}', InvisibleKind::ZeroWidth, "ZERO WIDTH NO-BREAK SPACE"),
+    ('\u{FFA0}', InvisibleKind::Invisible, "HALFWIDTH HANGUL FILLER"),
+];
+
+fn classify(c: char) -> Option<(InvisibleKind, &'static str)> {
+    if ('\u{E0000}'..='\u{E007F}').contains(&c) {
+        return Some((InvisibleKind::Invisible, "TAG CHARACTER"));
+    }
+    INVISIBLE_CHARS
+        .binary_search_by_key(&c, |&(known, _, _)| known)
+        .ok()
+        .map(|index| (INVISIBLE_CHARS[index].1, INVISIBLE_CHARS[index].2))
+}
+
+/// One reported character and where it is.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InvisibleChar {
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column, in characters
+    pub column: usize,
+    pub code_point: char,
+    pub kind: InvisibleKind,
+    pub name: &'static str,
+}
+
+impl fmt::Display for InvisibleChar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: U+{:04X} {} ({})",
+            self.line,
+            self.column,
+            self.code_point as u32,
+            self.name,
+            self.kind.name(),
+        )
+    }
+}
+
+/// Bidi controls, zero-width and other invisible characters found by
+/// `--scan-unicode`. Locations are per input only, so totals just count.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct UnicodeScan {
+    pub bidi: usize,
+    pub zero_width: usize,
+    pub invisible: usize,
+    /// The first `MAX_LOCATIONS` characters found, in order
+    pub locations: Vec<InvisibleChar>,
+}
+
+impl UnicodeScan {
+    pub fn add(&mut self, other: &UnicodeScan) {
+        self.bidi += other.bidi;
+        self.zero_width += other.zero_width;
+        self.invisible += other.invisible;
+    }
+
+    pub fn total(&self) -> usize {
+        self.bidi + self.zero_width + self.invisible
+    }
+}
+
+/// Finds the invisible characters in `bytes`. A byte order mark at the very
+/// start is expected, so it isn't reported; malformed UTF-8 is skipped.
+pub fn scan_unicode(bytes: &[u8], terminator: u8) -> UnicodeScan {
+    let mut scan = UnicodeScan::default();
+
+    for (index, line) in split_lines(bytes, terminator).enumerate() {
+        // Cheap skip for the common all-ASCII line
+        if line.is_ascii() {
+            continue;
+        }
+        let mut column = 0;
+        for chunk in line.utf8_chunks() {
+            for c in chunk.valid().chars() {
+                column += 1;
+                let Some((kind, name)) = classify(c) else { continue };
+                if index == 0 && column == 1 && c == '\u{FEFF}' {
+                    continue;
+                }
+                match kind {
+                    InvisibleKind::Bidi => scan.bidi += 1,
+                    InvisibleKind::ZeroWidth => scan.zero_width += 1,
+                    InvisibleKind::Invisible => scan.invisible += 1,
+                }
+                if scan.locations.len() < MAX_LOCATIONS {
+                    scan.locations.push(InvisibleChar { line: index + 1, column, code_point: c, kind, name });
+                }
+            }
+            column += chunk.invalid().len();
+        }
+    }
+
+    scan
+}
+
+#[cfg(test)]
+mod unicode_scan_tests {
+    use super::*;
+
+    #[test]
+    fn test_invisible_chars_sorted() {
+        assert!(INVISIBLE_CHARS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn test_scan_unicode() {
+        let text = "\u{feff}fn main() {\n    let access = \"user\u{202E} \u{2066}// admin\u{2069}\";\n  x\u{200B}y\u{E0041}\n";
+        let scan = scan_unicode(text.as_bytes(), b'\n');
+
+        assert_eq!((scan.bidi, scan.zero_width, scan.invisible), (3, 1, 1));
+        assert_eq!(scan.total(), 5);
+        assert_eq!(scan.locations[0].to_string(), "2:23: U+202E RIGHT-TO-LEFT OVERRIDE (bidi control)");
+        assert_eq!((scan.locations[3].line, scan.locations[3].column), (3, 4));
+        assert_eq!(scan.locations[4].name, "TAG CHARACTER");
+
+        assert_eq!(scan_unicode(b"plain ascii\n", b'\n'), UnicodeScan::default());
+    }
+
+    #[test]
+    fn test_scan_unicode_keeps_first_locations() {
+        let text = "\u{200B}".repeat(MAX_LOCATIONS + 5);
+        let scan = scan_unicode(text.as_bytes(), b'\n');
+        assert_eq!(scan.zero_width, MAX_LOCATIONS + 5);
+        assert_eq!(scan.locations.len(), MAX_LOCATIONS);
+        // A BOM after the start is reported
+        assert_eq!(scan_unicode("a\u{feff}".as_bytes(), b'\n').zero_width, 1);
+    }
+}
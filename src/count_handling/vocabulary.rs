@@ -0,0 +1,62 @@
+use std::collections::BTreeSet;
+
+/// The distinct words of an input, for `--ttr`. Words are compared
+/// case-insensitively, without leading or trailing punctuation, and words
+/// that are all punctuation are left out. Vocabularies merge by union, so a
+/// total knows which words recur across inputs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Vocabulary {
+    pub words: BTreeSet<String>,
+    /// Words counted, repeats included
+    pub tokens: usize,
+}
+
+impl Vocabulary {
+    pub fn from_words<'a>(words: impl IntoIterator<Item = &'a [u8]>) -> Self {
+        let mut vocabulary = Self::default();
+        for word in words {
+            let word = String::from_utf8_lossy(word);
+            let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+            if !word.is_empty() {
+                vocabulary.tokens += 1;
+                vocabulary.words.insert(word.to_lowercase());
+            }
+        }
+        vocabulary
+    }
+
+    pub fn add(&mut self, other: &Vocabulary) {
+        self.words.extend(other.words.iter().cloned());
+        self.tokens += other.tokens;
+    }
+
+    /// Type-token ratio: distinct words over all words, when there are any
+    pub fn ttr(&self) -> Option<f64> {
+        (self.tokens > 0).then(|| self.words.len() as f64 / self.tokens as f64)
+    }
+}
+
+#[cfg(test)]
+mod vocabulary_tests {
+    use super::*;
+
+    fn split(text: &str) -> Vocabulary {
+        Vocabulary::from_words(text.split_whitespace().map(str::as_bytes))
+    }
+
+    #[test]
+    fn test_vocabulary() {
+        let vocabulary = split("The cat saw the dog. THE dog -- ran!");
+        assert_eq!(vocabulary.tokens, 8);
+        assert_eq!(vocabulary.words, BTreeSet::from(["the", "cat", "saw", "dog", "ran"].map(String::from)));
+        assert_eq!(vocabulary.ttr(), Some(5.0 / 8.0));
+        assert_eq!(split("").ttr(), None);
+    }
+
+    #[test]
+    fn test_vocabulary_union() {
+        let mut total = split("a b c");
+        total.add(&split("b c d"));
+        assert_eq!((total.words.len(), total.tokens), (4, 6));
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::BTreeMap;
+
+use crate::editorconfig::IndentStyle;
+use crate::line_stats::split_lines;
+
+// A line's text without the `\r` of a CRLF ending
+fn content(line: &[u8]) -> &[u8] {
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+fn is_whitespace(text: &[u8]) -> bool {
+    match std::str::from_utf8(text) {
+        Ok(text) => text.chars().all(char::is_whitespace),
+        Err(_) => false,
+    }
+}
+
+/// Counts the lines of `bytes` that end in spaces or tabs, before any `\r`
+/// of a CRLF ending.
+pub fn trailing_whitespace_lines(bytes: &[u8], terminator: u8) -> usize {
+    split_lines(bytes, terminator)
+        .map(content)
+        .filter(|line| matches!(line.last(), Some(b' ' | b'\t')))
+        .count()
+}
+
+/// 1-based numbers of the lines of `bytes` whose indentation breaks `style`:
+/// any tab for spaces, or a leading space for tabs (spaces after the tabs,
+/// for alignment, are fine). Blank lines aren't checked.
+pub fn wrong_indent_lines(bytes: &[u8], terminator: u8, style: IndentStyle) -> Vec<usize> {
+    split_lines(bytes, terminator)
+        .map(content)
+        .enumerate()
+        .filter(|(_, line)| !is_whitespace(line))
+        .filter(|(_, line)| {
+            let indent_len = line.iter().take_while(|&&b| b == b' ' || b == b'\t').count();
+            let indent = &line[..indent_len];
+            match style {
+                IndentStyle::Space => indent.contains(&b'\t'),
+                IndentStyle::Tab => indent.first() == Some(&b' '),
+            }
+        })
+        .map(|(index, _)| index + 1)
+        .collect()
+}
+
+/// Lines with nothing on them, told apart from lines with only whitespace.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BlankLines {
+    /// Lines with no characters besides their terminator
+    pub empty: usize,
+    /// Lines with characters, all of them whitespace
+    pub whitespace_only: usize,
+}
+
+impl BlankLines {
+    /// Counts the blank lines in `bytes`. The `\r` of a CRLF ending doesn't
+    /// make a line whitespace-only.
+    pub fn from_bytes(bytes: &[u8], terminator: u8) -> Self {
+        let mut blank = Self::default();
+
+        for line in split_lines(bytes, terminator).map(content) {
+            if line.is_empty() {
+                blank.empty += 1;
+            } else if is_whitespace(line) {
+                blank.whitespace_only += 1;
+            }
+        }
+
+        blank
+    }
+
+    pub fn add(&mut self, other: &BlankLines) {
+        self.empty += other.empty;
+        self.whitespace_only += other.whitespace_only;
+    }
+}
+
+/// How lines are indented, from `--indent-stats`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct IndentStats {
+    /// Lines indented with tabs only
+    pub tabs: usize,
+    /// Lines indented with spaces only
+    pub spaces: usize,
+    /// Lines indented with both
+    pub mixed: usize,
+    /// How often each step in from one space-indented line to the next
+    /// occurs; kept whole so totals can find the mode across files
+    pub steps: BTreeMap<usize, u64>,
+}
+
+impl IndentStats {
+    /// Classifies the indentation of each line of `bytes` that isn't blank.
+    pub fn from_bytes(bytes: &[u8], terminator: u8) -> Self {
+        let mut stats = Self::default();
+        // Spaces before the previous line, if it was indented with spaces or not at all
+        let mut previous = Some(0);
+
+        for line in split_lines(bytes, terminator).map(content) {
+            let width = line.iter().take_while(|&&b| b == b' ' || b == b'\t').count();
+            if is_whitespace(line) {
+                continue;
+            }
+
+            let indent = &line[..width];
+            let (has_tabs, has_spaces) = (indent.contains(&b'\t'), indent.contains(&b' '));
+            match (has_tabs, has_spaces) {
+                (true, true) => stats.mixed += 1,
+                (true, false) => stats.tabs += 1,
+                (false, true) => stats.spaces += 1,
+                (false, false) => {}
+            }
+
+            let current = (!has_tabs).then_some(width);
+            if let (Some(before), Some(now)) = (previous, current) && now > before {
+                *stats.steps.entry(now - before).or_insert(0) += 1;
+            }
+            previous = current;
+        }
+
+        stats
+    }
+
+    pub fn add(&mut self, other: &IndentStats) {
+        self.tabs += other.tabs;
+        self.spaces += other.spaces;
+        self.mixed += other.mixed;
+        for (&step, &count) in &other.steps {
+            *self.steps.entry(step).or_insert(0) += count;
+        }
+    }
+
+    /// The most common indent step in spaces; the narrower one on a tie.
+    pub fn modal_width(&self) -> Option<usize> {
+        self.steps.iter()
+            .max_by(|(a_step, a_count), (b_step, b_count)| a_count.cmp(b_count).then(b_step.cmp(a_step)))
+            .map(|(&step, _)| step)
+    }
+}
+
+#[cfg(test)]
+mod whitespace_tests {
+    use super::*;
+
+    #[test]
+    fn test_blank_lines() {
+        let text = "code\n\n  \n\t\r\n\r\n\u{3000}\nx \n \u{a0}".as_bytes();
+        assert_eq!(BlankLines::from_bytes(text, b'\n'), BlankLines { empty: 2, whitespace_only: 4 });
+        assert_eq!(BlankLines::from_bytes(b"", b'\n'), BlankLines::default());
+        assert_eq!(BlankLines::from_bytes(b"a\0\0 \0", 0), BlankLines { empty: 1, whitespace_only: 1 });
+    }
+
+    #[test]
+    fn test_trailing_whitespace_lines() {
+        assert_eq!(trailing_whitespace_lines(b"ok\nspace \ntab\t\r\n\n  \nlast ", b'\n'), 4);
+        assert_eq!(trailing_whitespace_lines(b"clean\r\n", b'\n'), 0);
+    }
+
+    #[test]
+    fn test_wrong_indent_lines() {
+        let text = b"a\n    b\n\tc\n\t  d\n  \te\n\t\n";
+        assert_eq!(wrong_indent_lines(text, b'\n', IndentStyle::Space), [3, 4, 5]);
+        assert_eq!(wrong_indent_lines(text, b'\n', IndentStyle::Tab), [2, 5]);
+    }
+
+    #[test]
+    fn test_indent_stats() {
+        let text = b"fn a() {\n    if x {\n        y();\n\n    }\n\tz();\n \tw();\n  q\n}\n";
+        let stats = IndentStats::from_bytes(text, b'\n');
+
+        assert_eq!((stats.tabs, stats.spaces, stats.mixed), (1, 4, 1));
+        assert_eq!(stats.steps, BTreeMap::from([(4, 2)]));
+        assert_eq!(stats.modal_width(), Some(4));
+
+        let mut total = stats.clone();
+        total.add(&IndentStats::from_bytes(b"a\n  b\n    c\n  d\n    e\n", b'\n'));
+        assert_eq!(total.spaces, 8);
+        // 4 appears twice, 2 three times
+        assert_eq!(total.modal_width(), Some(2));
+        assert_eq!(IndentStats::from_bytes(b"flat\n", b'\n').modal_width(), None);
+    }
+}
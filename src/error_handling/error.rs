@@ -5,6 +5,13 @@ pub enum WcError {
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 
+    #[error("Failed to {operation} {path}: {source}")]
+    PathIo {
+        path: String,
+        operation: &'static str,
+        source: std::io::Error,
+    },
+
     #[error("Invalid UTF-8 sequence: {0}")]
     Utf8(#[from] std::str::Utf8Error),
 
@@ -17,11 +24,35 @@ pub enum WcError {
     #[error("Permission denied: {0}")]
     PermissionDenied(String),
 
+    #[error("Is a directory: {0}")]
+    IsADirectory(String),
+
     #[error("JSON serialization error: {0}")]
     Json(#[from] serde_json::Error),
 
+    #[error("Binary serialization error: {0}")]
+    Encode(String),
+
+    #[error("Database error: {0}")]
+    Database(String),
+
+    #[error("CSV parse error: {0}")]
+    Csv(#[from] csv::Error),
+
     #[error("Memory map error: {0}")]
     Mmap(String),
+
+    #[error("Directory traversal error: {0}")]
+    Walk(String),
+
+    #[error("Unexpected byte order mark: {0}")]
+    UnexpectedBom(String),
+
+    #[error("git error: {0}")]
+    Git(String),
+
+    #[error("Text extraction error: {0}")]
+    Extract(String),
 }
 
 impl WcError {
@@ -36,6 +67,19 @@ impl WcError {
     pub fn permission_denied<T: Into<String>>(file: T) -> Self {
         WcError::PermissionDenied(file.into())
     }
+
+    pub fn is_a_directory<T: Into<String>>(path: T) -> Self {
+        WcError::IsADirectory(path.into())
+    }
+
+    /// Names the file and what was being done to it when an I/O error
+    /// happened; other errors already say what went wrong and are kept.
+    pub fn with_path<T: Into<String>>(self, path: T, operation: &'static str) -> Self {
+        match self {
+            WcError::Io(source) => WcError::PathIo { path: path.into(), operation, source },
+            other => other,
+        }
+    }
 }
 
 
@@ -67,4 +111,13 @@ mod error_tests {
         let denied = WcError::permission_denied("/root/file");
         assert_eq!(denied.to_string(), "Permission denied: /root/file");
     }
+
+    #[test]
+    fn test_with_path() {
+        let error = WcError::from(io::Error::other("device gone")).with_path("data.txt", "read");
+        assert_eq!(error.to_string(), "Failed to read data.txt: device gone");
+
+        let not_found = WcError::file_not_found("a.txt").with_path("a.txt", "read");
+        assert!(matches!(not_found, WcError::FileNotFound(_)));
+    }
 }
\ No newline at end of file
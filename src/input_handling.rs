@@ -0,0 +1,8 @@
+pub mod editorconfig;
+pub mod extract;
+pub mod file_list;
+pub mod filesystem;
+pub mod git;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+pub mod uring;
+pub mod walker;
\ No newline at end of file
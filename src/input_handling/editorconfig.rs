@@ -0,0 +1,194 @@
+use globset::{GlobBuilder, GlobMatcher};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{self, Path, PathBuf},
+};
+
+use crate::counter::WcCounter;
+use crate::error::{WcError, WcResult};
+
+/// Indentation an `.editorconfig` asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    Tab,
+    Space,
+}
+
+impl IndentStyle {
+    pub fn name(&self) -> &'static str {
+        match self {
+            IndentStyle::Tab => "tabs",
+            IndentStyle::Space => "spaces",
+        }
+    }
+}
+
+/// The `.editorconfig` properties rs-wc uses, as they apply to one file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EditorConfig {
+    pub max_line_length: Option<usize>,
+    pub indent_style: Option<IndentStyle>,
+}
+
+impl EditorConfig {
+    // Values are case-insensitive; `off`, `unset` and anything unknown clear
+    // the property
+    fn set(&mut self, key: &str, value: &str) {
+        match key {
+            "max_line_length" => self.max_line_length = value.parse().ok(),
+            "indent_style" => self.indent_style = match value {
+                "tab" => Some(IndentStyle::Tab),
+                "space" => Some(IndentStyle::Space),
+                _ => None,
+            },
+            _ => {}
+        }
+    }
+}
+
+// A `[glob]` section; one whose glob can't be parsed never matches
+struct Section {
+    matcher: Option<GlobMatcher>,
+    properties: Vec<(String, String)>,
+}
+
+// One `.editorconfig` file
+struct ConfigFile {
+    root: bool,
+    sections: Vec<Section>,
+}
+
+// A glob without a slash matches a file name in any directory below the
+// `.editorconfig`; with one, it matches from the `.editorconfig`'s directory
+fn section_matcher(glob: &str) -> Option<GlobMatcher> {
+    let glob = match glob.contains('/') {
+        true => glob.trim_start_matches('/').to_string(),
+        false => format!("**/{}", glob),
+    };
+    GlobBuilder::new(&glob)
+        .literal_separator(true)
+        .build()
+        .ok()
+        .map(|glob| glob.compile_matcher())
+}
+
+fn parse(contents: &str) -> ConfigFile {
+    let mut file = ConfigFile { root: false, sections: Vec::new() };
+
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+        if let Some(glob) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            file.sections.push(Section { matcher: section_matcher(glob), properties: Vec::new() });
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else { continue };
+        let (key, value) = (key.trim().to_ascii_lowercase(), value.trim().to_ascii_lowercase());
+        match file.sections.last_mut() {
+            Some(section) => section.properties.push((key, value)),
+            None if key == "root" => file.root = value == "true",
+            None => {}
+        }
+    }
+
+    file
+}
+
+/// Finds the `.editorconfig` settings for files, reading each directory's
+/// `.editorconfig` once. Like editors, it looks in every directory from the
+/// file's up to the first `root = true`, and closer files win. Globs support
+/// `*`, `**`, `?`, `[...]` and `{a,b}`, but not numeric `{1..3}` ranges.
+#[derive(Default)]
+pub struct EditorConfigs {
+    files: HashMap<PathBuf, Option<ConfigFile>>,
+}
+
+impl EditorConfigs {
+    // Reads `dir`'s .editorconfig if it hasn't been yet, returning whether
+    // it stops the search
+    fn load(&mut self, dir: &Path) -> WcResult<bool> {
+        if !self.files.contains_key(dir) {
+            let path = dir.join(".editorconfig");
+            let file = match fs::read_to_string(&path) {
+                Ok(contents) => Some(parse(&contents)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => return Err(WcError::from(e).with_path(path.display().to_string(), "read")),
+            };
+            self.files.insert(dir.to_path_buf(), file);
+        }
+        Ok(self.files[dir].as_ref().is_some_and(|file| file.root))
+    }
+
+    /// The settings that apply to `path`.
+    pub fn lookup(&mut self, path: &Path) -> WcResult<EditorConfig> {
+        let path = path::absolute(path)?;
+        let mut dirs = Vec::new();
+        for dir in path.ancestors().skip(1) {
+            dirs.push(dir);
+            if self.load(dir)? {
+                break;
+            }
+        }
+
+        let mut config = EditorConfig::default();
+        for dir in dirs.into_iter().rev() {
+            let Some(file) = &self.files[dir] else { continue };
+            let relative = path.strip_prefix(dir).unwrap_or(&path);
+            let sections = file.sections.iter()
+                .filter(|section| section.matcher.as_ref().is_some_and(|matcher| matcher.is_match(relative)));
+            for (key, value) in sections.flat_map(|section| &section.properties) {
+                config.set(key, value);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Sets `result`'s line length limit from its file's max_line_length.
+    /// Standard input has no file to look up.
+    pub fn apply(&mut self, result: &mut WcCounter) -> WcResult<()> {
+        if let Some(filename) = result.filename.as_deref().filter(|&name| name != "-") {
+            result.line_length_limit = self.lookup(Path::new(filename))?.max_line_length;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod editorconfig_tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        let root = std::env::temp_dir().join(format!("rs-wc-editorconfig-{}", std::process::id()));
+        fs::create_dir_all(root.join("src/nested")).unwrap();
+        fs::write(root.join(".editorconfig"), "\
+root = true
+
+[*]
+indent_style = space
+max_line_length = 100
+
+[Makefile]
+indent_style = tab
+
+[*.{md,txt}]
+max_line_length = off
+").unwrap();
+        fs::write(root.join("src/.editorconfig"), "# closer files win\n[nested/*.rs]\nMax_Line_Length = 80\n").unwrap();
+
+        let mut configs = EditorConfigs::default();
+        let mut lookup = |path: &str| configs.lookup(&root.join(path)).unwrap();
+        let rust = lookup("src/main.rs");
+        let nested = lookup("src/nested/lib.rs");
+        let make = lookup("src/Makefile");
+        let readme = lookup("README.md");
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(rust, EditorConfig { max_line_length: Some(100), indent_style: Some(IndentStyle::Space) });
+        assert_eq!(nested.max_line_length, Some(80));
+        assert_eq!(make.indent_style, Some(IndentStyle::Tab));
+        assert_eq!(readme.max_line_length, None);
+    }
+}
@@ -0,0 +1,191 @@
+use std::path::Path;
+
+use crate::error::{WcError, WcResult};
+
+/// Largest `word/document.xml` read from a DOCX. The XML compresses so
+/// well that a small file can unpack to far more than fits in memory.
+const MAX_DOCX_XML: u64 = 256 * 1024 * 1024;
+
+/// A document format whose text is counted in place of its bytes, unless
+/// `--raw` is given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Document {
+    Pdf,
+    Docx,
+}
+
+impl Document {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Document::Pdf => "pdf",
+            Document::Docx => "docx",
+        }
+    }
+
+    /// The document `path` names by its extension, if rs-wc was built with
+    /// the feature that extracts it.
+    pub fn for_path(path: &Path) -> Option<Document> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "pdf" if cfg!(feature = "pdf") => Some(Document::Pdf),
+            "docx" if cfg!(feature = "docx") => Some(Document::Docx),
+            _ => None,
+        }
+    }
+
+    // How the content starts: a PDF header, or the zip container a .docx is
+    fn signature(&self) -> &'static [u8] {
+        match self {
+            Document::Pdf => b"%PDF-",
+            Document::Docx => b"PK\x03\x04",
+        }
+    }
+
+    /// The text of a document. Content that doesn't start the way this kind
+    /// of document does is just misnamed, so gives `None` to count as is.
+    pub fn extract(&self, bytes: &[u8]) -> WcResult<Option<String>> {
+        if !bytes.starts_with(self.signature()) {
+            return Ok(None);
+        }
+        match self {
+            Document::Pdf => pdf_text(bytes),
+            Document::Docx => docx_text(bytes, MAX_DOCX_XML),
+        }
+        .map(Some)
+    }
+}
+
+#[cfg(feature = "pdf")]
+fn pdf_text(bytes: &[u8]) -> WcResult<String> {
+    // The extractor panics on some malformed files; that is one bad input,
+    // not a reason to stop counting the rest
+    std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem(bytes))
+        .map_err(|_| WcError::Extract("malformed PDF".to_string()))?
+        .map_err(|e| WcError::Extract(e.to_string()))
+}
+
+#[cfg(not(feature = "pdf"))]
+fn pdf_text(_bytes: &[u8]) -> WcResult<String> {
+    Err(WcError::invalid_argument("counting PDF text needs rs-wc built with `--features pdf`"))
+}
+
+// The paragraphs of word/document.xml, one per line, with tabs and line
+// breaks kept. An error if the XML unpacks to more than `limit` bytes.
+#[cfg(feature = "docx")]
+fn docx_text(bytes: &[u8], limit: u64) -> WcResult<String> {
+    use quick_xml::events::Event;
+    use std::io::{Cursor, Read};
+
+    let extract_error = |e: &dyn std::fmt::Display| WcError::Extract(format!("malformed DOCX: {}", e));
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| extract_error(&e))?;
+    let mut xml = Vec::new();
+    archive.by_name("word/document.xml")
+        .map_err(|e| extract_error(&e))?
+        .take(limit + 1)
+        .read_to_end(&mut xml)?;
+    if xml.len() as u64 > limit {
+        return Err(WcError::Extract(format!("DOCX text is over the {} byte limit", limit)));
+    }
+    let xml = String::from_utf8(xml).map_err(|e| extract_error(&e))?;
+
+    let mut reader = quick_xml::Reader::from_str(&xml);
+    let mut text = String::new();
+    // Only w:t elements hold text; the rest is markup and properties
+    let mut in_text = false;
+    loop {
+        match reader.read_event().map_err(|e| extract_error(&e))? {
+            Event::Start(element) if element.name().as_ref() == b"w:t" => in_text = true,
+            Event::End(element) => match element.name().as_ref() {
+                b"w:t" => in_text = false,
+                b"w:p" => text.push('\n'),
+                _ => {}
+            },
+            Event::Empty(element) => match element.name().as_ref() {
+                b"w:tab" => text.push('\t'),
+                b"w:br" | b"w:cr" => text.push('\n'),
+                b"w:p" => text.push('\n'),
+                _ => {}
+            },
+            Event::Text(content) if in_text => text.push_str(&content.unescape().map_err(|e| extract_error(&e))?),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(text)
+}
+
+#[cfg(not(feature = "docx"))]
+fn docx_text(_bytes: &[u8], _limit: u64) -> WcResult<String> {
+    Err(WcError::invalid_argument("counting DOCX text needs rs-wc built with `--features docx`"))
+}
+
+#[cfg(test)]
+mod extract_tests {
+    use super::*;
+
+    #[test]
+    fn test_for_path() {
+        assert_eq!(Document::for_path(Path::new("report.PDF")), cfg!(feature = "pdf").then_some(Document::Pdf));
+        assert_eq!(Document::for_path(Path::new("thesis.docx")), cfg!(feature = "docx").then_some(Document::Docx));
+        assert_eq!(Document::for_path(Path::new("notes.txt")), None);
+        assert_eq!(Document::for_path(Path::new("pdf")), None);
+    }
+
+    #[test]
+    fn test_misnamed_documents_count_as_is() {
+        assert_eq!(Document::Pdf.extract(b"plain text").unwrap(), None);
+        assert_eq!(Document::Docx.extract(b"plain text").unwrap(), None);
+    }
+
+    #[cfg(feature = "pdf")]
+    #[test]
+    fn test_pdf_text() {
+        let content = "BT /F1 12 Tf 72 720 Td (Hello brave new world) Tj ET";
+        let objects = [
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents 4 0 R /Resources << /Font << /F1 5 0 R >> >> >>".to_string(),
+            format!("<< /Length {} >>\nstream\n{}\nendstream", content.len(), content),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_string(),
+        ];
+        let mut pdf = "%PDF-1.4\n".to_string();
+        let mut offsets = Vec::new();
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf += &format!("{} 0 obj\n{}\nendobj\n", index + 1, object);
+        }
+        let xref = pdf.len();
+        pdf += &format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            pdf += &format!("{:010} 00000 n \n", offset);
+        }
+        pdf += &format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref);
+
+        let text = Document::Pdf.extract(pdf.as_bytes()).unwrap().unwrap();
+        assert_eq!(text.split_whitespace().collect::<Vec<_>>(), ["Hello", "brave", "new", "world"]);
+        assert!(Document::Pdf.extract(b"%PDF-1.4 truncated").is_err());
+    }
+
+    #[cfg(feature = "docx")]
+    #[test]
+    fn test_docx_text() {
+        use std::io::{Cursor, Write};
+
+        let document = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body>
+<w:p><w:r><w:t>Hello</w:t></w:r><w:r><w:t xml:space="preserve"> world &amp; all</w:t></w:r></w:p>
+<w:p><w:r><w:t>a</w:t><w:tab/><w:t>b</w:t><w:br/><w:t>c</w:t></w:r></w:p>
+<w:p/>
+</w:body></w:document>"#;
+        let mut archive = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        archive.start_file("word/document.xml", zip::write::SimpleFileOptions::default()).unwrap();
+        archive.write_all(document.as_bytes()).unwrap();
+        let bytes = archive.finish().unwrap().into_inner();
+
+        assert_eq!(Document::Docx.extract(&bytes).unwrap().unwrap(), "Hello world & all\na\tb\nc\n\n");
+        assert!(Document::Docx.extract(b"PK\x03\x04 truncated").is_err());
+
+        let limit = document.len() as u64 - 1;
+        assert!(matches!(docx_text(&bytes, limit), Err(WcError::Extract(message)) if message.contains("limit")));
+    }
+}
@@ -0,0 +1,80 @@
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use crate::error::{WcError, WcResult};
+
+/// How the names in a `--files-from` or `--files0-from` list are separated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ListSeparator {
+    /// One name per line, as `git ls-files` and `find` print them
+    Newline,
+    /// NUL bytes, which can't appear in a path, as from `find -print0`
+    Nul,
+}
+
+/// Reads the paths listed in `source` (`-` for stdin). Empty entries are
+/// ignored, and newline lists also accept CRLF line endings.
+pub fn read_file_list(source: &Path, separator: ListSeparator) -> WcResult<Vec<PathBuf>> {
+    let contents = if source == Path::new("-") {
+        let mut contents = Vec::new();
+        io::stdin().lock().read_to_end(&mut contents)?;
+        contents
+    } else {
+        fs::read(source).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => WcError::file_not_found(source.display().to_string()),
+            _ => WcError::from(e).with_path(source.display().to_string(), "read"),
+        })?
+    };
+
+    Ok(parse_file_list(&contents, separator))
+}
+
+fn parse_file_list(contents: &[u8], separator: ListSeparator) -> Vec<PathBuf> {
+    let byte = match separator {
+        ListSeparator::Newline => b'\n',
+        ListSeparator::Nul => 0,
+    };
+
+    contents.split(|&b| b == byte)
+        .map(|entry| match separator {
+            ListSeparator::Newline => entry.strip_suffix(b"\r").unwrap_or(entry),
+            ListSeparator::Nul => entry,
+        })
+        .filter(|entry| !entry.is_empty())
+        .map(path_from_bytes)
+        .collect()
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+#[cfg(test)]
+mod file_list_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_file_list() {
+        let paths = parse_file_list(b"src/lib.rs\r\nREADME.md\n\nCargo.toml", ListSeparator::Newline);
+        assert_eq!(paths, [PathBuf::from("src/lib.rs"), "README.md".into(), "Cargo.toml".into()]);
+
+        let paths = parse_file_list(b"with\nnewline\0plain\0", ListSeparator::Nul);
+        assert_eq!(paths, [PathBuf::from("with\nnewline"), "plain".into()]);
+    }
+
+    #[test]
+    fn test_read_missing_list() {
+        let result = read_file_list(Path::new("missing-list.txt"), ListSeparator::Newline);
+        assert!(matches!(result, Err(WcError::FileNotFound(_))));
+    }
+}
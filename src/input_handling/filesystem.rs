@@ -0,0 +1,50 @@
+use std::fs::File;
+
+/// Whether `file` lives on a network or FUSE filesystem, where a mapping
+/// can fault (SIGBUS) if the file changes underneath it, and plain reads are
+/// the safer choice.
+#[cfg(target_os = "linux")]
+pub fn is_remote(file: &File) -> bool {
+    use std::os::fd::AsRawFd;
+
+    // Magic numbers from statfs(2)
+    const REMOTE_FILESYSTEMS: [i64; 7] = [
+        0x6969,     // NFS
+        0x65735546, // FUSE
+        0x517b,     // SMB
+        0xff534d42, // CIFS
+        0xfe534d42, // SMB2
+        0x564c,     // NCP
+        0x01021997, // 9P
+    ];
+
+    let mut stats = std::mem::MaybeUninit::<libc::statfs>::uninit();
+    // SAFETY: the descriptor stays open for the call and `stats` is only
+    // read once fstatfs reports success
+    if unsafe { libc::fstatfs(file.as_raw_fd(), stats.as_mut_ptr()) } != 0 {
+        return false;
+    }
+    // f_type's width differs between targets
+    #[allow(clippy::unnecessary_cast)]
+    let kind = unsafe { stats.assume_init() }.f_type as i64;
+    REMOTE_FILESYSTEMS.contains(&kind)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn is_remote(_file: &File) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod filesystem_tests {
+    use super::*;
+
+    // The checkout itself could be on a network mount, so check a file
+    // that is certainly local
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_procfs_is_not_remote() {
+        let file = File::open("/proc/self/stat").unwrap();
+        assert!(!is_remote(&file));
+    }
+}
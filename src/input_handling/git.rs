@@ -0,0 +1,27 @@
+use std::{io, path::PathBuf, process::Command};
+
+use crate::error::{WcError, WcResult};
+
+/// Runs `git diff` on `range` (e.g. `main..HEAD`), limited to `paths` if any
+/// are given, and returns its output. External diff drivers, textconv
+/// filters and color are turned off so the output is a plain unified diff.
+pub fn diff(range: &str, paths: &[PathBuf]) -> WcResult<Vec<u8>> {
+    // git would take it for an option, such as --output=FILE
+    if range.starts_with('-') {
+        return Err(WcError::Git(format!("invalid revision range '{}'", range)));
+    }
+    let output = Command::new("git")
+        .args(["-c", "core.quotePath=false", "diff", "--no-color", "--no-ext-diff", "--no-textconv", range, "--"])
+        .args(paths)
+        .output()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => WcError::Git("git-diff needs git installed and on PATH".to_string()),
+            _ => WcError::from(e).with_path("git", "run"),
+        })?;
+
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr);
+        return Err(WcError::Git(message.trim().to_string()));
+    }
+    Ok(output.stdout)
+}
@@ -0,0 +1,476 @@
+use ignore::{overrides::OverrideBuilder, DirEntry, WalkBuilder, WalkState};
+use std::{
+    collections::HashSet,
+    fmt, fs,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::counter::Failure;
+use crate::entropy::looks_binary;
+use crate::error::{WcError, WcResult};
+
+/// Bytes read from the start of a file to decide whether it is binary.
+const SNIFF_SIZE: u64 = 8 * 1024;
+
+/// An `--include` or `--exclude` glob, kept in command-line order so the
+/// last matching pattern decides whether a file is counted.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathFilter {
+    Include(String),
+    Exclude(String),
+}
+
+/// Settings for expanding directory arguments into the files beneath them.
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    /// Descend into directory arguments instead of passing them through
+    pub recursive: bool,
+    pub filters: Vec<PathFilter>,
+    /// Count files hidden by `.gitignore`, `.ignore` and global excludes
+    pub no_ignore: bool,
+    /// Deepest level to descend to; 1 counts only a directory's own files
+    pub max_depth: Option<usize>,
+    /// Follow symbolic links instead of skipping them
+    pub follow_symlinks: bool,
+    /// Skip files found while recursing that look binary
+    pub text_only: bool,
+    /// Count each file once, however many times it is named or found
+    pub dedup_inputs: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SkipReason {
+    Symlink,
+    SymlinkCycle,
+    Binary,
+    Duplicate,
+}
+
+/// An input that was deliberately left out of the count.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skipped {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+}
+
+/// The files to count, plus anything skipped along the way and the parts
+/// of the tree that couldn't be walked.
+#[derive(Debug, Clone, Default)]
+pub struct Expansion {
+    pub files: Vec<PathBuf>,
+    pub skipped: Vec<Skipped>,
+    pub failures: Vec<Failure>,
+}
+
+/// Something a walk turns up: a file to count, an input left out, or a
+/// path that couldn't be read, such as an unreadable directory.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Found {
+    File(PathBuf),
+    Skipped(Skipped),
+    Failed(Failure),
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::Symlink => write!(f, "symbolic link (use --follow-symlinks to count it)"),
+            SkipReason::SymlinkCycle => write!(f, "symbolic link cycle"),
+            SkipReason::Binary => write!(f, "binary file"),
+            SkipReason::Duplicate => write!(f, "duplicate of an earlier input"),
+        }
+    }
+}
+
+impl SkipReason {
+    /// Short machine-readable name, as used in JSON output.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SkipReason::Symlink => "symlink",
+            SkipReason::SymlinkCycle => "symlink_cycle",
+            SkipReason::Binary => "binary",
+            SkipReason::Duplicate => "duplicate",
+        }
+    }
+}
+
+impl fmt::Display for Skipped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: skipped {}", self.path.display(), self.reason)
+    }
+}
+
+/// Applies the symlink policy to `roots` and, when recursive, expands every
+/// directory into the files beneath it in sorted order. Other paths are
+/// passed through untouched and are never filtered, except that
+/// `dedup_inputs` drops repeats of a file.
+pub fn expand_paths(roots: &[PathBuf], options: &WalkOptions) -> WcResult<Expansion> {
+    let mut expansion = Expansion::default();
+
+    for root in roots {
+        let is_symlink = fs::symlink_metadata(root).is_ok_and(|m| m.file_type().is_symlink());
+        if is_symlink && !options.follow_symlinks {
+            expansion.skipped.push(Skipped { path: root.clone(), reason: SkipReason::Symlink });
+        } else if options.recursive && root.is_dir() {
+            walk_dir(root, options, &mut expansion)?;
+        } else {
+            expansion.files.push(root.clone());
+        }
+    }
+
+    if options.dedup_inputs {
+        dedup_files(&mut expansion);
+    }
+
+    Ok(expansion)
+}
+
+// Keeps the first of the files that resolve to the same canonical path, as
+// with `src/*.rs src/lib.rs`. Paths that can't be resolved are compared as
+// given and left for the counting pass to report.
+fn dedup_files(expansion: &mut Expansion) {
+    let mut seen = HashSet::new();
+    let mut files = Vec::with_capacity(expansion.files.len());
+
+    for path in expansion.files.drain(..) {
+        let key = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        if seen.insert(key) {
+            files.push(path);
+        } else {
+            expansion.skipped.push(Skipped { path, reason: SkipReason::Duplicate });
+        }
+    }
+
+    expansion.files = files;
+}
+
+// Identifies a directory across different paths to it, so following
+// symlinks never enters the same directory twice.
+#[cfg(unix)]
+fn directory_id(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).ok().map(|m| (m.dev(), m.ino()))
+}
+
+#[cfg(not(unix))]
+fn directory_id(_path: &Path) -> Option<(u64, u64)> {
+    None
+}
+
+// Unreadable files are left for the counting pass to report
+fn is_binary_file(path: &Path) -> bool {
+    let mut block = Vec::new();
+    fs::File::open(path)
+        .and_then(|file| file.take(SNIFF_SIZE).read_to_end(&mut block))
+        .is_ok_and(|_| looks_binary(&block))
+}
+
+// The symlink that closes a directory cycle, if that is what `error` reports.
+fn loop_path(error: &ignore::Error) -> Option<&Path> {
+    match error {
+        ignore::Error::Loop { child, .. } => Some(child),
+        ignore::Error::WithPath { err, .. }
+        | ignore::Error::WithDepth { err, .. }
+        | ignore::Error::WithLineNumber { err, .. } => loop_path(err),
+        _ => None,
+    }
+}
+
+// The path `error` is about, if it names one
+fn error_path(error: &ignore::Error) -> Option<&Path> {
+    match error {
+        ignore::Error::WithPath { path, .. } => Some(path),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => error_path(err),
+        _ => None,
+    }
+}
+
+/// Like [`expand_paths`] without `dedup_inputs`, but walks each directory
+/// on several threads and hands over every file and skipped input as soon
+/// as it is found, with the index of the root it came from. Files under a
+/// root arrive in no particular order.
+pub fn walk_paths<F>(roots: &[PathBuf], options: &WalkOptions, found: F) -> WcResult<()>
+where
+    F: Fn(usize, Found) + Sync,
+{
+    for (index, root) in roots.iter().enumerate() {
+        let is_symlink = fs::symlink_metadata(root).is_ok_and(|m| m.file_type().is_symlink());
+        if is_symlink && !options.follow_symlinks {
+            found(index, Found::Skipped(Skipped { path: root.clone(), reason: SkipReason::Symlink }));
+        } else if options.recursive && root.is_dir() {
+            walk_dir_parallel(root, options, |item| found(index, item))?;
+        } else {
+            found(index, Found::File(root.clone()));
+        }
+    }
+    Ok(())
+}
+
+fn walk_dir_parallel<F: Fn(Found) + Sync>(root: &Path, options: &WalkOptions, found: F) -> WcResult<()> {
+    walk_builder(root, options)?.build_parallel().run(|| {
+        let found = &found;
+        Box::new(move |entry| {
+            if let Some(item) = classify(entry, root, options) {
+                found(item);
+            }
+            WalkState::Continue
+        })
+    });
+    Ok(())
+}
+
+// What a walk entry amounts to; directories and other non-files are
+// nothing. Like wc, a path that can't be read is reported and the walk goes
+// on with the rest of the tree.
+fn classify(entry: Result<DirEntry, ignore::Error>, root: &Path, options: &WalkOptions) -> Option<Found> {
+    let entry = match entry {
+        Ok(entry) => entry,
+        Err(e) => {
+            if let Some(path) = loop_path(&e) {
+                let path = path.to_path_buf();
+                return Some(Found::Skipped(Skipped { path, reason: SkipReason::SymlinkCycle }));
+            }
+            let path = error_path(&e).unwrap_or(root);
+            return Some(Found::Failed(Failure::new(path, &WcError::Walk(e.to_string()))));
+        }
+    };
+
+    if !entry.file_type().is_some_and(|t| t.is_file()) {
+        return None;
+    }
+
+    if options.text_only && is_binary_file(entry.path()) {
+        Some(Found::Skipped(Skipped { path: entry.into_path(), reason: SkipReason::Binary }))
+    } else {
+        Some(Found::File(entry.into_path()))
+    }
+}
+
+fn walk_dir(root: &Path, options: &WalkOptions, expansion: &mut Expansion) -> WcResult<()> {
+    let walker = walk_builder(root, options)?
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build();
+
+    for entry in walker {
+        match classify(entry, root, options) {
+            Some(Found::File(path)) => expansion.files.push(path),
+            Some(Found::Skipped(skipped)) => expansion.skipped.push(skipped),
+            Some(Found::Failed(failure)) => expansion.failures.push(failure),
+            None => {}
+        }
+    }
+
+    Ok(())
+}
+
+// A walker for `root` with the ignore rules, filters and symlink policy of
+// `options`
+fn walk_builder(root: &Path, options: &WalkOptions) -> WcResult<WalkBuilder> {
+    let mut overrides = OverrideBuilder::new(root);
+    for filter in &options.filters {
+        let glob = match filter {
+            PathFilter::Include(glob) => glob.clone(),
+            PathFilter::Exclude(glob) => format!("!{}", glob),
+        };
+        overrides.add(&glob).map_err(|e| WcError::invalid_argument(e.to_string()))?;
+    }
+    let overrides = overrides.build().map_err(|e| WcError::invalid_argument(e.to_string()))?;
+
+    let respect_ignore = !options.no_ignore;
+    let visited = Arc::new(Mutex::new(HashSet::new()));
+    let mut walker = WalkBuilder::new(root);
+    walker
+        .standard_filters(false)
+        .git_ignore(respect_ignore)
+        .git_global(respect_ignore)
+        .git_exclude(respect_ignore)
+        .ignore(respect_ignore)
+        .parents(respect_ignore)
+        .max_depth(options.max_depth)
+        .follow_links(options.follow_symlinks)
+        .filter_entry(move |entry| {
+            if respect_ignore && entry.file_name() == ".git" {
+                return false;
+            }
+            if !entry.file_type().is_some_and(|t| t.is_dir()) {
+                return true;
+            }
+            match directory_id(entry.path()) {
+                Some(id) => visited.lock().unwrap_or_else(|e| e.into_inner()).insert(id),
+                None => true,
+            }
+        })
+        .overrides(overrides);
+
+    Ok(walker)
+}
+
+#[cfg(test)]
+mod walker_tests {
+    use super::*;
+    use std::fs;
+
+    fn recursive() -> WalkOptions {
+        WalkOptions { recursive: true, ..WalkOptions::default() }
+    }
+
+    fn walk(filters: Vec<PathFilter>) -> Vec<String> {
+        let options = WalkOptions { filters, ..recursive() };
+        expand_paths(&[PathBuf::from("src")], &options).unwrap()
+            .files
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_walk_without_filters() {
+        let files = walk(vec![]);
+        assert!(files.contains(&"src/lib.rs".to_string()));
+        assert!(files.contains(&"src/count_handling/counter.rs".to_string()));
+    }
+
+    #[test]
+    fn test_walk_include_exclude() {
+        let files = walk(vec![
+            PathFilter::Include("*.rs".into()),
+            PathFilter::Exclude("count_handling/**".into()),
+        ]);
+        assert!(files.contains(&"src/lib.rs".to_string()));
+        assert!(!files.iter().any(|f| f.contains("count_handling/")));
+    }
+
+    #[test]
+    fn test_walk_last_match_wins() {
+        let files = walk(vec![
+            PathFilter::Exclude("count_handling/**".into()),
+            PathFilter::Include("count_handling/counter.rs".into()),
+        ]);
+        assert_eq!(files, ["src/count_handling/counter.rs"]);
+    }
+
+    #[test]
+    fn test_walk_paths_finds_what_expand_paths_does() {
+        let roots = [PathBuf::from("src"), PathBuf::from("Cargo.toml")];
+        let found = Mutex::new(Vec::new());
+        walk_paths(&roots, &recursive(), |root, item| found.lock().unwrap().push((root, item))).unwrap();
+
+        let mut found: Vec<_> = found.into_inner().unwrap().into_iter()
+            .map(|(root, item)| match item {
+                Found::File(path) => (root, path),
+                Found::Skipped(skipped) => panic!("unexpected skip: {}", skipped),
+                Found::Failed(failure) => panic!("unexpected failure: {}", failure),
+            })
+            .collect();
+        found.sort();
+        let files: Vec<_> = found.into_iter().map(|(_, path)| path).collect();
+        assert_eq!(files, expand_paths(&roots, &recursive()).unwrap().files);
+    }
+
+    #[test]
+    fn test_walk_max_depth() {
+        let options = WalkOptions { max_depth: Some(1), ..recursive() };
+        let files = expand_paths(&[PathBuf::from("src")], &options).unwrap().files;
+
+        assert!(files.contains(&PathBuf::from("src/lib.rs")));
+        assert!(files.iter().all(|f| f.parent() == Some(Path::new("src"))));
+    }
+
+    #[test]
+    fn test_explicit_files_are_not_filtered() {
+        let options = WalkOptions {
+            filters: vec![PathFilter::Exclude("*.toml".into())],
+            ..recursive()
+        };
+        let paths = expand_paths(&[PathBuf::from("Cargo.toml")], &options).unwrap().files;
+        assert_eq!(paths, [PathBuf::from("Cargo.toml")]);
+    }
+
+    #[test]
+    fn test_walk_respects_ignore_files() {
+        let root = std::env::temp_dir().join(format!("rs-wc-walker-{}", std::process::id()));
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join(".ignore"), "*.log\n").unwrap();
+        fs::write(root.join("keep.txt"), "kept\n").unwrap();
+        fs::write(root.join("skip.log"), "skipped\n").unwrap();
+        fs::write(root.join(".git").join("HEAD"), "ref\n").unwrap();
+
+        let names = |options: &WalkOptions| -> Vec<String> {
+            expand_paths(std::slice::from_ref(&root), options).unwrap()
+                .files
+                .iter()
+                .map(|p| p.strip_prefix(&root).unwrap().display().to_string())
+                .collect()
+        };
+
+        let respected = names(&recursive());
+        let everything = names(&WalkOptions { no_ignore: true, ..recursive() });
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(respected, [".ignore", "keep.txt"]);
+        assert_eq!(everything, [".git/HEAD", ".ignore", "keep.txt", "skip.log"]);
+    }
+
+    #[test]
+    fn test_walk_text_only() {
+        let root = std::env::temp_dir().join(format!("rs-wc-text-only-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("notes.txt"), "plain text\n").unwrap();
+        fs::write(root.join("image.png"), b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+
+        let options = WalkOptions { text_only: true, ..recursive() };
+        let expansion = expand_paths(std::slice::from_ref(&root), &options).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(expansion.files, [root.join("notes.txt")]);
+        assert_eq!(expansion.skipped, [Skipped { path: root.join("image.png"), reason: SkipReason::Binary }]);
+    }
+
+    #[test]
+    fn test_dedup_inputs() {
+        let roots = [PathBuf::from("src/lib.rs"), PathBuf::from("src"), PathBuf::from("./src/lib.rs")];
+        let options = WalkOptions { dedup_inputs: true, ..recursive() };
+        let expansion = expand_paths(&roots, &options).unwrap();
+
+        assert_eq!(expansion.files.iter().filter(|f| f.ends_with("lib.rs")).count(), 1);
+        assert_eq!(expansion.files[0], PathBuf::from("src/lib.rs"));
+        assert_eq!(expansion.skipped.len(), 2);
+        assert!(expansion.skipped.iter().all(|s| s.reason == SkipReason::Duplicate));
+
+        let kept = expand_paths(&roots, &recursive()).unwrap();
+        assert_eq!(kept.files.iter().filter(|f| f.ends_with("lib.rs")).count(), 3);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_policy_and_cycles() {
+        use std::os::unix::fs::symlink;
+
+        let root = std::env::temp_dir().join(format!("rs-wc-symlinks-{}", std::process::id()));
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("a.txt"), "a\n").unwrap();
+        symlink(root.join("a.txt"), root.join("link.txt")).unwrap();
+        symlink(&root, root.join("sub").join("loop")).unwrap();
+
+        let names = |expansion: &Expansion| -> Vec<String> {
+            expansion.files.iter()
+                .map(|p| p.strip_prefix(&root).unwrap().display().to_string())
+                .collect()
+        };
+
+        let skipping = expand_paths(&[root.clone(), root.join("link.txt")], &recursive()).unwrap();
+        let following = expand_paths(
+            std::slice::from_ref(&root),
+            &WalkOptions { follow_symlinks: true, ..recursive() },
+        ).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(names(&skipping), ["a.txt"]);
+        assert_eq!(skipping.skipped, [Skipped { path: root.join("link.txt"), reason: SkipReason::Symlink }]);
+        assert_eq!(names(&following), ["a.txt", "link.txt"]);
+        assert_eq!(following.skipped.len(), 1);
+        assert_eq!(following.skipped[0].reason, SkipReason::SymlinkCycle);
+    }
+}
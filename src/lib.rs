@@ -1,13 +1,29 @@
 mod argument_parser;
+mod check_handling;
 mod count_handling;
 mod error_handling;
+mod input_handling;
 mod output_handling;
+mod service_handling;
 
 
-pub use argument_parser::parser;
-pub use count_handling::counter;
+pub use argument_parser::{escape, parser, size};
+pub use check_handling::{endings, lint, threshold, unicode};
+pub use count_handling::{aggregate, cache, comments, control, counter, csv_stats, diff, emoji, encoding, entropy, group, hash, html, json_stats, line_endings, line_stats, longest, matches, patch, readability, records, sketch, snapshot, unicode_scan, vocabulary, whitespace};
 pub use error_handling::error;
-pub use output_handling::printer;
+pub use input_handling::{editorconfig, extract, file_list, filesystem, git, walker};
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+pub use input_handling::uring;
+pub use output_handling::{destination, logging, printer};
+#[cfg(feature = "parquet")]
+pub use output_handling::columnar;
+#[cfg(feature = "sqlite")]
+pub use output_handling::sqlite;
+#[cfg(feature = "tui")]
+pub use output_handling::tui;
+#[cfg(unix)]
+pub use service_handling::daemon;
+pub use service_handling::server;
 
 
 #[cfg(test)]
@@ -15,7 +31,7 @@ mod proptests {
     use proptest::prelude::*;
     use std::io::Cursor;
     use crate::parser::CountMode;
-    use crate::count_handling::counter::{WcCounter, count_bytes, count_reader};
+    use crate::count_handling::counter::{WcCounter, CountOptions, count_bytes, count_bytes_with, count_reader};
 
     proptest! {
         #[test]
@@ -36,6 +52,23 @@ mod proptests {
             }
         }
 
+        #[test]
+        fn test_lines_only_matches_full_scan(bytes in any::<Vec<u8>>(), terminator in prop_oneof![Just(b'\n'), Just(0u8)]) {
+            let full = CountOptions { line_terminator: terminator, ..CountOptions::default() };
+            let lines_only = CountOptions { max_line_length: false, ..full.clone() };
+            let expected = count_bytes_with(&bytes, None, &[CountMode::Lines], &full).unwrap();
+            let result = count_bytes_with(&bytes, None, &[CountMode::Lines], &lines_only).unwrap();
+            assert_eq!(result.lines, expected.lines);
+        }
+
+        #[test]
+        fn test_count_reader_matches_count_bytes(bytes in any::<Vec<u8>>()) {
+            let modes = [CountMode::Lines, CountMode::Words, CountMode::Bytes, CountMode::Chars];
+            let expected = count_bytes(&bytes, None, &modes).unwrap();
+            let result = count_reader(Cursor::new(&bytes), None, &modes).unwrap();
+            assert!(result.counts_eq(&expected));
+        }
+
         #[test]
         fn test_counter_add_associative(
             a in any::<WcCounter>(),
@@ -1,29 +1,561 @@
-use clap::Parser;
 use std::{
-    io::{self, BufReader},
-    path::PathBuf,
+    fs,
+    io::{self, BufReader, IsTerminal, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{mpsc, Mutex},
+    thread,
+    time::Instant,
 };
 
 use rs_wc::{
-    parser::Cli,
-    error::WcResult,
+    parser::{Cli, Command, CountMode, OutputFormat},
+    error::{WcError, WcResult},
+    logging,
     printer,
-    counter::{self, count_files},
+    threshold,
+    endings,
+    unicode,
+    lint,
+    git,
+    patch::PatchStats,
+    editorconfig::{EditorConfig, EditorConfigs},
+    file_list,
+    diff::CountDiff,
+    group,
+    hash::{self, DuplicateTracker},
+    snapshot::Snapshot,
+    walker,
+    server,
+    counter::{self, count_file_with, count_files_each, count_files_with, Failure},
 };
 
-fn main() -> WcResult<()> {
-    let cli = Cli::parse();
-    
-    let results = if cli.files.is_empty() || (cli.files.len() == 1 && cli.files[0] == PathBuf::from("-")) {
+fn main() -> ExitCode {
+    let cli = Cli::parse_args();
+    logging::init(cli.verbose);
+
+    run(&cli).unwrap_or_else(|e| {
+        eprintln!("rs-wc: {}", e);
+        ExitCode::FAILURE
+    })
+}
+
+fn run(cli: &Cli) -> WcResult<ExitCode> {
+    match &cli.command {
+        Some(Command::Diff { old, new }) => run_diff(cli, old, new),
+        Some(Command::Snapshot { save: Some(path), files, .. }) => run_snapshot_save(cli, path, files),
+        Some(Command::Snapshot { check: Some(path), tolerance, files, .. }) => {
+            run_snapshot_check(cli, path, *tolerance, files)
+        }
+        Some(Command::Snapshot { .. }) => unreachable!("clap requires --save or --check"),
+        Some(Command::Readability { files }) => run_readability(cli, files),
+        Some(Command::Lint { limit, files }) => run_lint(cli, *limit, files),
+        Some(Command::GitDiff { range, paths }) => run_git_diff(cli, range, paths),
+        Some(Command::Serve { listen, paths }) => {
+            server::serve(listen, paths.clone(), cli)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        #[cfg(unix)]
+        Some(Command::Daemon { socket }) => {
+            rs_wc::daemon::run(socket, cli)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        None if cli.tui => run_tui(cli),
+        None => run_count(cli),
+    }
+}
+
+#[cfg(feature = "tui")]
+fn run_tui(cli: &Cli) -> WcResult<ExitCode> {
+    rs_wc::tui::run(cli.tui_roots(), cli)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(not(feature = "tui"))]
+fn run_tui(_cli: &Cli) -> WcResult<ExitCode> {
+    Err(WcError::invalid_argument("--tui needs rs-wc built with `--features tui`"))
+}
+
+fn run_count(cli: &Cli) -> WcResult<ExitCode> {
+    if cfg!(not(feature = "sqlite")) && cli.output_sqlite.is_some() {
+        return Err(WcError::invalid_argument("--output-sqlite needs rs-wc built with `--features sqlite`"));
+    }
+    let modes = cli.get_required_modes();
+    let listed = match cli.file_list() {
+        Some((source, separator)) => Some(file_list::read_file_list(source, separator)?),
+        None => None,
+    };
+    let reads_stdin = listed.is_none() && cli.names_no_files();
+
+    let mut skipped = Vec::new();
+    let mut failures = Vec::new();
+    let results = if reads_stdin && !cli.recursive {
         let stdin = io::stdin();
+        if stdin.is_terminal() && !cli.stdin {
+            eprintln!("rs-wc: reading from terminal; press Ctrl-D to end or pass --stdin to silence");
+        }
         let reader = BufReader::new(stdin.lock());
-        vec![counter::count_reader(reader, None, &cli.get_count_modes())?]
+        let started = Instant::now();
+        let mut result = counter::count_reader_with(reader, None, &modes, &cli.count_options())
+            .map_err(|e| e.with_path("standard input", "read"))?;
+        if cli.time {
+            result.duration = Some(started.elapsed());
+        }
+        vec![result]
     } else {
-        count_files(&cli.files, &cli.get_count_modes())?
+        let roots = match listed {
+            Some(listed) => listed,
+            None if reads_stdin => vec![PathBuf::from(".")],
+            None => cli.files.clone(),
+        };
+        // Directories are walked in parallel with counting, unless repeats
+        // are to be dropped, which needs every path in walk order first
+        let walked = cli.recursive && !cli.dedup_inputs;
+        if cli.stream {
+            let (results, failed) = stream_files(cli, |on_result| match walked {
+                true => count_walked(cli, &roots, |_, path, result| on_result(&path, result)),
+                false => {
+                    let expansion = expand_paths(cli, &roots)?;
+                    counter::count_files_streaming(&expansion.files, &modes, &cli.count_options(), |index, result| {
+                        on_result(&expansion.files[index], result)
+                    });
+                    Ok((expansion.skipped, expansion.failures))
+                }
+            })?;
+            record_results(cli, &results)?;
+            return Ok(exit_code(check_results(cli, &results), failed));
+        }
+
+        let counted = match walked {
+            true => {
+                let mut counted = Vec::new();
+                (skipped, failures) = count_walked(cli, &roots, |root, path, result| counted.push((root, path, result)))?;
+                counted.sort_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)));
+                counted.into_iter().map(|(_, path, result)| (path, result)).collect()
+            }
+            false => {
+                let expansion = expand_paths(cli, &roots)?;
+                (skipped, failures) = (expansion.skipped, expansion.failures);
+                let counts = count_files_each(&expansion.files, &modes, &cli.count_options());
+                expansion.files.into_iter().zip(counts).collect::<Vec<_>>()
+            }
+        };
+
+        // Like wc, a file that can't be counted is reported and the rest
+        // are still counted and totalled
+        let mut results = Vec::with_capacity(counted.len());
+        for (path, result) in counted {
+            match result {
+                Ok(result) => results.push(result),
+                Err(e) => {
+                    eprintln!("rs-wc: {}", e);
+                    failures.push(Failure::new(&path, &e));
+                }
+            }
+        }
+        if cli.dedup_content {
+            hash::mark_duplicates(&mut results);
+        }
+        if cli.editorconfig && cli.max_line_length {
+            let mut configs = EditorConfigs::default();
+            for result in &mut results {
+                apply_editorconfig(&mut configs, result);
+            }
+        }
+        results
+    };
+
+    let rows = match &cli.group_by {
+        Some(group_by) => group::group_results(&results, group_by),
+        None => results.clone(),
     };
 
-    let output = printer::format_results(&results, &cli)?;
-    print!("{}", output);
+    let format_options = cli.format_options();
+    let output = tracing::debug_span!("format").in_scope(|| -> WcResult<Vec<u8>> {
+        if cli.per_line {
+            return printer::format_per_line(&rows, &format_options).map(String::into_bytes);
+        }
+        let mut output = printer::render_results(&rows, &skipped, &failures, &format_options)?;
+        if let Some(buckets) = format_options.histogram {
+            output.extend(printer::format_histograms(&rows, buckets, &format_options)?.into_bytes());
+        }
+        Ok(output)
+    })?;
+    let mut destination = cli.destination()?;
+    destination.write_all(&output)?;
+    destination.finish()?;
+    record_results(cli, &results)?;
+
+    Ok(exit_code(check_results(cli, &results), !failures.is_empty()))
+}
 
+// Adds the results to the --output-sqlite database, if one was given
+#[cfg(feature = "sqlite")]
+fn record_results(cli: &Cli, results: &[counter::WcCounter]) -> WcResult<()> {
+    if let Some(path) = &cli.output_sqlite {
+        rs_wc::sqlite::record_results(path, results, &cli.format_options())?;
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+// Rejected by run_count before anything is counted
+#[cfg(not(feature = "sqlite"))]
+fn record_results(_cli: &Cli, _results: &[counter::WcCounter]) -> WcResult<()> {
+    Ok(())
+}
+
+fn exit_code(checks: ExitCode, failed: bool) -> ExitCode {
+    if failed { ExitCode::FAILURE } else { checks }
+}
+
+// Expands directory arguments up front, reporting what was skipped and
+// what couldn't be walked
+fn expand_paths(cli: &Cli, roots: &[PathBuf]) -> WcResult<walker::Expansion> {
+    let expansion = walker::expand_paths(roots, &cli.walk_options())?;
+    for entry in &expansion.skipped {
+        report_skipped(cli, entry);
+    }
+    for failure in &expansion.failures {
+        eprintln!("rs-wc: {}", failure);
+    }
+    Ok(expansion)
+}
+
+// Notes a skipped input on stderr, unless -q asks for bare counts or the
+// output format lists skipped inputs itself
+fn report_skipped(cli: &Cli, entry: &walker::Skipped) {
+    let listed = matches!(cli.format, OutputFormat::Json | OutputFormat::Ndjson | OutputFormat::Msgpack | OutputFormat::Cbor);
+    if !cli.quiet && !listed {
+        eprintln!("rs-wc: {}", entry);
+    }
+}
+
+// Walks `roots` on several threads, counting each file as soon as it is
+// found. `on_result` gets each file's result as it finishes, with the index
+// of its root. Returns what was skipped and what couldn't be walked, each in
+// path order.
+fn count_walked<F>(cli: &Cli, roots: &[PathBuf], on_result: F) -> WcResult<(Vec<walker::Skipped>, Vec<Failure>)>
+where
+    F: FnMut(usize, PathBuf, WcResult<counter::WcCounter>),
+{
+    let skipped = Mutex::new(Vec::new());
+    let failures = Mutex::new(Vec::new());
+    let (sender, receiver) = mpsc::channel();
+    let walk_options = cli.walk_options();
+    let (found_skipped, found_failures) = (&skipped, &failures);
+
+    thread::scope(|scope| {
+        let walk = scope.spawn(|| {
+            walker::walk_paths(roots, &walk_options, move |root, found| match found {
+                walker::Found::File(path) => {
+                    let _ = sender.send((root, path));
+                }
+                walker::Found::Skipped(entry) => {
+                    report_skipped(cli, &entry);
+                    found_skipped.lock().unwrap_or_else(|e| e.into_inner()).push(entry);
+                }
+                walker::Found::Failed(failure) => {
+                    eprintln!("rs-wc: {}", failure);
+                    found_failures.lock().unwrap_or_else(|e| e.into_inner()).push(failure);
+                }
+            })
+        });
+        counter::count_files_pipelined(receiver, &cli.get_required_modes(), &cli.count_options(), on_result);
+        walk.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })?;
+
+    let mut skipped = skipped.into_inner().unwrap_or_else(|e| e.into_inner());
+    skipped.sort_by(|a, b| a.path.cmp(&b.path));
+    let mut failures = failures.into_inner().unwrap_or_else(|e| e.into_inner());
+    failures.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok((skipped, failures))
+}
+
+// Prints each file's row as soon as it is counted, then the summary rows.
+// `count` counts the files, passing each result on, and returns what was
+// skipped and what couldn't be walked. Also returns whether any file
+// couldn't be counted or walked.
+fn stream_files<C>(cli: &Cli, count: C) -> WcResult<(Vec<counter::WcCounter>, bool)>
+where
+    C: FnOnce(&mut dyn FnMut(&Path, WcResult<counter::WcCounter>)) -> WcResult<(Vec<walker::Skipped>, Vec<Failure>)>,
+{
+    let format_options = cli.format_options();
+    let mut printer = printer::StreamPrinter::new(&format_options)?;
+    let mut results = Vec::new();
+    let mut failed = false;
+    let mut duplicates = cli.dedup_content.then(DuplicateTracker::default);
+    let mut configs = (cli.editorconfig && cli.max_line_length).then(EditorConfigs::default);
+    let mut destination = cli.destination()?;
+
+    let (skipped, walk_failures) = count(&mut |path, result| {
+        match result {
+            Ok(mut result) => {
+                if let Some(duplicates) = &mut duplicates {
+                    duplicates.mark(&mut result);
+                }
+                if let Some(configs) = &mut configs {
+                    apply_editorconfig(configs, &mut result);
+                }
+                let _ = write!(destination, "{}", printer.row(result.clone()));
+                let _ = destination.flush();
+                results.push(result);
+            }
+            Err(e) => {
+                eprintln!("rs-wc: {}", e);
+                let _ = write!(destination, "{}", printer.failure(&Failure::new(path, &e)));
+                let _ = destination.flush();
+                failed = true;
+            }
+        }
+    })?;
+
+    for failure in &walk_failures {
+        write!(destination, "{}", printer.failure(failure))?;
+        failed = true;
+    }
+    write!(destination, "{}", printer.finish(&skipped))?;
+    destination.finish()?;
+    Ok((results, failed))
+}
+
+// Reports threshold, line ending, final newline, trailing whitespace and
+// invisible character problems on stderr, failing if any of them should
+fn check_results(cli: &Cli, results: &[counter::WcCounter]) -> ExitCode {
+    let violations = threshold::check(&cli.fail_if, results);
+    for violation in &violations {
+        eprintln!("rs-wc: {}", violation);
+    }
+
+    let mismatches = match cli.check_line_endings {
+        Some(style) => endings::check_endings(style, results),
+        None => Vec::new(),
+    };
+    for mismatch in &mismatches {
+        eprintln!("rs-wc: {}", mismatch);
+    }
+
+    let missing_newlines = endings::check_final_newline(results);
+    for missing in &missing_newlines {
+        eprintln!("rs-wc: {}", missing);
+    }
+    let newlines_ok = missing_newlines.is_empty() || !cli.fail_on_missing_newline;
+
+    // Only listed when they fail the run; the count is in the output
+    let trailing = match cli.fail_on_trailing_whitespace {
+        true => endings::check_trailing_whitespace(results),
+        false => Vec::new(),
+    };
+    for found in &trailing {
+        eprintln!("rs-wc: {}", found);
+    }
+
+    let unicode_findings = unicode::check_unicode(results);
+    for finding in &unicode_findings {
+        eprintln!("rs-wc: {}", finding);
+    }
+
+    let clean = violations.is_empty() && mismatches.is_empty() && trailing.is_empty() && unicode_findings.is_empty();
+    if clean && newlines_ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn run_diff(cli: &Cli, old: &Path, new: &Path) -> WcResult<ExitCode> {
+    let modes = cli.get_required_modes();
+    let options = cli.count_options();
+    let diff = CountDiff::new(
+        count_file_with(old, &modes, &options)?,
+        count_file_with(new, &modes, &options)?,
+    );
+
+    let output = printer::format_diff(&diff, &cli.format_options())?;
+    let mut destination = cli.destination()?;
+    write!(destination, "{}", output)?;
+    destination.finish()?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_readability(cli: &Cli, files: &[PathBuf]) -> WcResult<ExitCode> {
+    let options = counter::CountOptions { readability: true, ..cli.count_options() };
+    let counted = count_files_each(files, &[CountMode::Words], &options);
+
+    let mut results = Vec::with_capacity(files.len());
+    let mut failures = Vec::new();
+    for (path, result) in files.iter().zip(counted) {
+        match result {
+            // The scores leave numbers and symbols out of the word count, so
+            // the words column does too
+            Ok(mut result) => {
+                if let Some(readability) = &result.readability {
+                    result.words = readability.words;
+                }
+                results.push(result);
+            }
+            Err(e) => {
+                eprintln!("rs-wc: {}", e);
+                failures.push(Failure::new(path, &e));
+            }
+        }
+    }
+
+    // Words, then the readability metrics
+    let format_options = printer::FormatOptions {
+        modes: vec![CountMode::Words],
+        max_line_length: false,
+        listed_modes: None,
+        ..cli.format_options()
+    };
+    let output = printer::render_results(&results, &[], &failures, &format_options)?;
+    let mut destination = cli.destination()?;
+    destination.write_all(&output)?;
+    destination.finish()?;
+
+    Ok(exit_code(ExitCode::SUCCESS, !failures.is_empty()))
+}
+
+// Sets `result`'s line length limit from its .editorconfig. A file whose
+// settings can't be read is warned about and left without a limit.
+fn apply_editorconfig(configs: &mut EditorConfigs, result: &mut counter::WcCounter) {
+    if let Err(e) = configs.apply(result) {
+        eprintln!("rs-wc: {}", e);
+    }
+}
+
+fn run_lint(cli: &Cli, limit: Option<usize>, files: &[PathBuf]) -> WcResult<ExitCode> {
+    let rules = EditorConfig { max_line_length: limit, indent_style: None };
+    let file_rules = match cli.editorconfig {
+        true => {
+            let mut configs = EditorConfigs::default();
+            files.iter().map(|path| {
+                // A file whose .editorconfig can't be read is still checked
+                // against the command line's limit
+                let mut file_rules = configs.lookup(path).unwrap_or_else(|e| {
+                    eprintln!("rs-wc: {}", e);
+                    EditorConfig::default()
+                });
+                file_rules.max_line_length = file_rules.max_line_length.or(limit);
+                file_rules
+            }).collect()
+        }
+        false => vec![rules; files.len()],
+    };
+
+    // Files that share rules are counted together, in parallel
+    let mut groups: Vec<(EditorConfig, Vec<usize>)> = Vec::new();
+    for (index, rules) in file_rules.into_iter().enumerate() {
+        match groups.iter_mut().find(|(group, _)| *group == rules) {
+            Some((_, indices)) => indices.push(index),
+            None => groups.push((rules, vec![index])),
+        }
+    }
+    let mut counted: Vec<Option<(WcResult<counter::WcCounter>, EditorConfig)>> = files.iter().map(|_| None).collect();
+    for (rules, indices) in groups {
+        let options = counter::CountOptions {
+            long_lines: rules.max_line_length,
+            indent_style: rules.indent_style,
+            ..cli.count_options()
+        };
+        let paths: Vec<&PathBuf> = indices.iter().map(|&index| &files[index]).collect();
+        for (index, result) in indices.into_iter().zip(count_files_each(&paths, &[CountMode::Lines], &options)) {
+            counted[index] = Some((result, rules.clone()));
+        }
+    }
+
+    let mut findings = Vec::new();
+    let mut failed = false;
+    for (result, rules) in counted.into_iter().flatten() {
+        match result {
+            Ok(result) => findings.extend(lint::check_file(&result, &rules)),
+            Err(e) => {
+                eprintln!("rs-wc: {}", e);
+                failed = true;
+            }
+        }
+    }
+
+    let mut destination = cli.destination()?;
+    for finding in &findings {
+        writeln!(destination, "{}", finding)?;
+    }
+    destination.finish()?;
+
+    let clean = if findings.is_empty() { ExitCode::SUCCESS } else { ExitCode::FAILURE };
+    Ok(exit_code(clean, failed))
+}
+
+fn run_git_diff(cli: &Cli, range: &str, paths: &[PathBuf]) -> WcResult<ExitCode> {
+    let diff = git::diff(range, paths)?;
+    let results: Vec<counter::WcCounter> = PatchStats::per_file(&diff, b'\n')
+        .into_iter()
+        .map(|(filename, patch)| counter::WcCounter {
+            filename: Some(filename),
+            patch: Some(patch),
+            ..counter::WcCounter::default()
+        })
+        .collect();
+
+    // Only the diff metrics
+    let format_options = printer::FormatOptions {
+        modes: Vec::new(),
+        max_line_length: false,
+        listed_modes: None,
+        ..cli.format_options()
+    };
+    let output = printer::render_results(&results, &[], &[], &format_options)?;
+    let mut destination = cli.destination()?;
+    destination.write_all(&output)?;
+    destination.finish()?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+const SNAPSHOT_MODES: [CountMode; 4] = [CountMode::Lines, CountMode::Words, CountMode::Bytes, CountMode::Chars];
+
+fn run_snapshot_save(cli: &Cli, path: &Path, files: &[PathBuf]) -> WcResult<ExitCode> {
+    let results = count_files_with(files, &SNAPSHOT_MODES, &cli.count_options())?;
+    let json = Snapshot::from_results(&results).to_json()?;
+    fs::write(path, json + "\n").map_err(|e| WcError::from(e).with_path(path.display().to_string(), "write"))?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_snapshot_check(cli: &Cli, path: &Path, tolerance: f64, files: &[PathBuf]) -> WcResult<ExitCode> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| WcError::from(e).with_path(path.display().to_string(), "read"))?;
+    let mut snapshot = Snapshot::from_json(&contents)?;
+
+    let paths: Vec<PathBuf> = if files.is_empty() {
+        snapshot.files.keys().map(PathBuf::from).collect()
+    } else {
+        let requested: Vec<String> = files.iter().map(|f| f.display().to_string()).collect();
+        snapshot.files.retain(|name, _| requested.contains(name));
+        files.to_vec()
+    };
+
+    // Files that vanished are reported by the comparison, not as errors
+    let options = cli.count_options();
+    let mut results = Vec::with_capacity(paths.len());
+    for path in &paths {
+        match count_file_with(path, &SNAPSHOT_MODES, &options) {
+            Ok(result) => results.push(result),
+            Err(WcError::FileNotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    let changes = snapshot.compare(&results, &cli.get_metrics(), tolerance);
+    let mut destination = cli.destination()?;
+    for change in &changes {
+        writeln!(destination, "{}", change)?;
+    }
+    destination.finish()?;
+
+    if changes.iter().any(|change| change.is_regression()) {
+        Ok(ExitCode::FAILURE)
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
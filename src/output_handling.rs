@@ -1 +1,9 @@
-pub mod printer;
\ No newline at end of file
+#[cfg(feature = "parquet")]
+pub mod columnar;
+pub mod destination;
+pub mod logging;
+pub mod printer;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+#[cfg(feature = "tui")]
+pub mod tui;
\ No newline at end of file
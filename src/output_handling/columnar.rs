@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray, UInt64Array};
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use serde_json::{Map, Value};
+
+use crate::error::{WcError, WcResult};
+
+// The narrowest Arrow type that holds every value seen in a column
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColumnType {
+    UInt64,
+    Int64,
+    Float64,
+    Boolean,
+    Utf8,
+}
+
+impl ColumnType {
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::Number(n) if n.is_u64() => ColumnType::UInt64,
+            Value::Number(n) if n.is_i64() => ColumnType::Int64,
+            Value::Number(_) => ColumnType::Float64,
+            Value::Bool(_) => ColumnType::Boolean,
+            _ => ColumnType::Utf8,
+        }
+    }
+
+    fn widen(self, other: Self) -> Self {
+        use ColumnType::*;
+        match (self, other) {
+            (a, b) if a == b => a,
+            (UInt64 | Int64, UInt64 | Int64) => Int64,
+            (UInt64 | Int64 | Float64, UInt64 | Int64 | Float64) => Float64,
+            _ => Utf8,
+        }
+    }
+
+    fn data_type(self) -> DataType {
+        match self {
+            ColumnType::UInt64 => DataType::UInt64,
+            ColumnType::Int64 => DataType::Int64,
+            ColumnType::Float64 => DataType::Float64,
+            ColumnType::Boolean => DataType::Boolean,
+            ColumnType::Utf8 => DataType::Utf8,
+        }
+    }
+
+    // `values` has None for a null or missing value
+    fn array(self, values: &[Option<&Value>]) -> ArrayRef {
+        match self {
+            ColumnType::UInt64 => Arc::new(values.iter().map(|v| v.and_then(Value::as_u64)).collect::<UInt64Array>()),
+            ColumnType::Int64 => Arc::new(values.iter().map(|v| v.and_then(Value::as_i64)).collect::<Int64Array>()),
+            ColumnType::Float64 => Arc::new(values.iter().map(|v| v.and_then(Value::as_f64)).collect::<Float64Array>()),
+            ColumnType::Boolean => Arc::new(values.iter().map(|v| v.and_then(Value::as_bool)).collect::<BooleanArray>()),
+            // Anything that isn't a scalar is kept as its JSON text
+            ColumnType::Utf8 => Arc::new(values.iter().map(|v| v.map(|v| match v {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })).collect::<StringArray>()),
+        }
+    }
+}
+
+/// Encodes `rows` (one JSON object per file) as a Parquet file with a column
+/// per key. `filename` comes first; a column's type is the narrowest that
+/// holds all of its values, and a row without the key has a null there.
+pub fn to_parquet(rows: &[Map<String, Value>]) -> WcResult<Vec<u8>> {
+    let mut names = vec!["filename".to_string()];
+    for row in rows {
+        for key in row.keys() {
+            if !names.contains(key) {
+                names.push(key.clone());
+            }
+        }
+    }
+
+    let mut fields = Vec::with_capacity(names.len());
+    let mut columns = Vec::with_capacity(names.len());
+    for name in &names {
+        let values: Vec<Option<&Value>> = rows.iter()
+            .map(|row| row.get(name).filter(|value| !value.is_null()))
+            .collect();
+        let column_type = values.iter().flatten()
+            .map(|value| ColumnType::of(value))
+            .reduce(ColumnType::widen)
+            .unwrap_or(ColumnType::Utf8);
+        fields.push(Field::new(name, column_type.data_type(), true));
+        columns.push(column_type.array(&values));
+    }
+
+    let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).map_err(encode_error)?;
+    let mut writer = ArrowWriter::try_new(Vec::new(), batch.schema(), None).map_err(encode_error)?;
+    writer.write(&batch).map_err(encode_error)?;
+    writer.into_inner().map_err(encode_error)
+}
+
+fn encode_error(e: impl std::fmt::Display) -> WcError {
+    WcError::Encode(e.to_string())
+}
+
+#[cfg(test)]
+mod columnar_tests {
+    use super::*;
+    use arrow_array::Array;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use serde_json::json;
+
+    fn row(value: Value) -> Map<String, Value> {
+        value.as_object().cloned().unwrap()
+    }
+
+    #[test]
+    fn test_column_types_widen() {
+        assert_eq!(ColumnType::UInt64.widen(ColumnType::Int64), ColumnType::Int64);
+        assert_eq!(ColumnType::UInt64.widen(ColumnType::Float64), ColumnType::Float64);
+        assert_eq!(ColumnType::Float64.widen(ColumnType::Boolean), ColumnType::Utf8);
+    }
+
+    #[test]
+    fn test_to_parquet_round_trip() {
+        let rows = [
+            row(json!({"filename": "a.txt", "lines": 3, "entropy": 4.5, "hash": null})),
+            row(json!({"filename": "b.txt", "lines": 7, "entropy": 2, "hash": "ab12"})),
+        ];
+        let path = std::env::temp_dir().join(format!("rs-wc-parquet-{}.parquet", std::process::id()));
+        std::fs::write(&path, to_parquet(&rows).unwrap()).unwrap();
+
+        let file = std::fs::File::open(&path).unwrap();
+        let batch = ParquetRecordBatchReaderBuilder::try_new(file).unwrap()
+            .build().unwrap()
+            .next().unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let schema = batch.schema();
+        let names: Vec<&str> = schema.fields().iter().map(|field| field.name().as_str()).collect();
+        assert_eq!(names, ["filename", "entropy", "hash", "lines"]);
+        assert_eq!(schema.field(1).data_type(), &DataType::Float64);
+        assert_eq!(schema.field(3).data_type(), &DataType::UInt64);
+        assert_eq!(batch.num_rows(), 2);
+
+        let lines = batch.column(3).as_any().downcast_ref::<UInt64Array>().unwrap();
+        assert_eq!(lines.values(), &[3, 7]);
+        let hashes = batch.column(2).as_any().downcast_ref::<StringArray>().unwrap();
+        assert!(hashes.is_null(0));
+        assert_eq!(hashes.value(1), "ab12");
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::error::{WcError, WcResult};
+
+/// Where output goes: stdout, or the file given with `--output`.
+///
+/// A replaced file is written beside its target and renamed over it by
+/// [`Destination::finish`], so readers never see a partial report. If
+/// `finish` is never reached the temporary file is removed and the target
+/// is left as it was. A symlink is followed, so the file it points to is
+/// the one replaced, keeping its permissions; a target that isn't a
+/// regular file, such as a FIFO or `/dev/null`, is written in place.
+pub struct Destination {
+    writer: Box<dyn Write>,
+    // Temporary file and the target it is renamed to
+    pending: Option<(PathBuf, PathBuf)>,
+}
+
+impl Destination {
+    pub fn stdout() -> Self {
+        Self { writer: Box::new(io::stdout()), pending: None }
+    }
+
+    /// Opens `path` for writing, after anything already in it with `append`.
+    pub fn open(path: &Path, append: bool) -> WcResult<Self> {
+        let name = path.display().to_string();
+        if append {
+            let file = OpenOptions::new().create(true).append(true).open(path)
+                .map_err(|e| WcError::from(e).with_path(&name, "open"))?;
+            return Ok(Self { writer: Box::new(BufWriter::new(file)), pending: None });
+        }
+
+        let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let existing = fs::metadata(&target).ok();
+        if existing.as_ref().is_some_and(|metadata| !metadata.is_file()) {
+            let file = File::create(&target)
+                .map_err(|e| WcError::from(e).with_path(&name, "open"))?;
+            return Ok(Self { writer: Box::new(BufWriter::new(file)), pending: None });
+        }
+
+        let temp = temp_path(&target);
+        let file = File::create(&temp)
+            .map_err(|e| WcError::from(e).with_path(&name, "create"))?;
+        // Register the temporary file before anything else can fail, so
+        // it's removed if that happens
+        let destination = Self { writer: Box::new(BufWriter::new(file)), pending: Some((temp.clone(), target)) };
+        if let Some(metadata) = existing {
+            fs::set_permissions(&temp, metadata.permissions())
+                .map_err(|e| WcError::from(e).with_path(&name, "create"))?;
+        }
+        Ok(destination)
+    }
+
+    /// Flushes everything written and, when replacing a file, moves the new
+    /// contents into place.
+    pub fn finish(mut self) -> WcResult<()> {
+        self.writer.flush()?;
+        if let Some((temp, target)) = self.pending.take() {
+            fs::rename(&temp, &target).map_err(|e| {
+                let _ = fs::remove_file(&temp);
+                WcError::from(e).with_path(target.display().to_string(), "replace")
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl Write for Destination {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl Drop for Destination {
+    fn drop(&mut self) {
+        if let Some((temp, _)) = &self.pending {
+            let _ = fs::remove_file(temp);
+        }
+    }
+}
+
+// A hidden file in the target's directory, so renaming it never crosses
+// filesystems
+fn temp_path(target: &Path) -> PathBuf {
+    let name = target.file_name().map_or_else(|| "output".into(), |name| name.to_string_lossy());
+    target.with_file_name(format!(".{}.{}.tmp", name, std::process::id()))
+}
+
+#[cfg(test)]
+mod destination_tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_and_append() {
+        let dir = std::env::temp_dir().join(format!("rs-wc-destination-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("report.txt");
+        fs::write(&path, "old\n").unwrap();
+
+        let mut output = Destination::open(&path, false).unwrap();
+        writeln!(output, "new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old\n");
+        output.finish().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+
+        let mut output = Destination::open(&path, true).unwrap();
+        writeln!(output, "more").unwrap();
+        output.finish().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new\nmore\n");
+
+        let mut abandoned = Destination::open(&path, false).unwrap();
+        write!(abandoned, "partial").unwrap();
+        drop(abandoned);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new\nmore\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_replace_keeps_symlink_and_permissions() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir = std::env::temp_dir().join(format!("rs-wc-destination-link-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let real = dir.join("real.txt");
+        let link = dir.join("link.txt");
+        fs::write(&real, "old\n").unwrap();
+        fs::set_permissions(&real, fs::Permissions::from_mode(0o600)).unwrap();
+        symlink(&real, &link).unwrap();
+
+        let mut output = Destination::open(&link, false).unwrap();
+        writeln!(output, "new").unwrap();
+        output.finish().unwrap();
+
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&real).unwrap(), "new\n");
+        assert_eq!(fs::metadata(&real).unwrap().permissions().mode() & 0o777, 0o600);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_in_place_to_special_file() {
+        let mut output = Destination::open(Path::new("/dev/null"), false).unwrap();
+        writeln!(output, "discarded").unwrap();
+        output.finish().unwrap();
+        assert!(!fs::metadata("/dev/null").unwrap().is_file());
+    }
+}
@@ -0,0 +1,25 @@
+use std::io::{self, IsTerminal};
+
+use tracing::Level;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+/// Sends diagnostics to stderr for `-v` (each file and any fallback it
+/// took, with timings), `-vv` (the open, map, count and format steps too)
+/// and `-vvv` (everything). Without `-v` nothing is logged.
+pub fn init(verbosity: u8) {
+    let level = match verbosity {
+        0 => return,
+        1 => Level::INFO,
+        2 => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+
+    // A closing span reports how long it was busy and idle
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_target(false)
+        .with_ansi(io::stderr().is_terminal())
+        .with_writer(io::stderr)
+        .init();
+}
@@ -1,33 +1,447 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{self, Path},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
 use crate::{
-    parser::{Cli, CountMode, OutputFormat},
-    counter::WcCounter,
-    error::WcResult,
+    aggregate::{aggregate, aggregate_values},
+    threshold::{Metric, Threshold},
+    parser::{Aggregate, Cli, Compat, CountMode, FilenameStyle, OutputFormat},
+    counter::{self, Failure, WcCounter},
+    diff::{CountDiff, MetricDelta},
+    error::{WcError, WcResult},
+    hash::HashAlgorithm,
+    line_stats::LineStats,
+    walker::Skipped,
 };
-use serde_json::{json, to_string_pretty};
+use serde_json::{json, to_string_pretty, Value};
+
+/// When a total row follows the per-file rows.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Totals {
+    /// Only for more than one result
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// How results are laid out, independent of how they were counted.
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    pub format: OutputFormat,
+    /// Count columns, in order; may be empty, e.g. to show only the max line
+    /// length
+    pub modes: Vec<CountMode>,
+    /// Show the max line length column
+    pub max_line_length: bool,
+    /// Count modes the JSON envelope and diffs list in place of `modes`,
+    /// as `-L` on its own hides the columns of the default counts but still
+    /// lists them
+    pub listed_modes: Option<Vec<CountMode>>,
+    pub totals: Totals,
+    /// Summary rows after the total
+    pub aggregates: Vec<Aggregate>,
+    /// Leave out the per-file rows, leaving the total and summary rows
+    pub summary_only: bool,
+    /// Leave out filenames and summary rows
+    pub quiet: bool,
+    pub filename_style: FilenameStyle,
+    /// Separator between groups of three digits in text output
+    pub group_digits: Option<String>,
+    /// A wc whose column layout text output reproduces
+    pub compat: Option<Compat>,
+    /// Show each result's share of the first count column
+    pub percent: bool,
+    /// Line length percentiles to show, when they were counted
+    pub percentiles: Vec<f64>,
+    /// Show the shortest line's length, even for results without one
+    pub min_line_length: bool,
+    /// Show LF, CRLF and CR counts, when they were counted
+    pub line_endings: bool,
+    /// Show how many malformed UTF-8 sequences were found
+    pub invalid_utf8: bool,
+    /// The algorithm content hashes were computed with
+    pub hash: Option<HashAlgorithm>,
+    /// Show which earlier input each result duplicates, for every row
+    pub dedup_content: bool,
+    /// Give the total row the time since `started`
+    pub time: bool,
+    /// When the run started, for the JSON envelope and the total's time
+    pub started: Instant,
+    /// JSON results as a bare array, without the envelope
+    pub json_array: bool,
+    /// JSON on one line instead of indented
+    pub compact: bool,
+    /// Output goes after what's already in the destination
+    pub append: bool,
+    /// Number the lines of `--per-line` output
+    pub line_numbers: bool,
+    /// Show line length statistics, when they were counted
+    pub line_stats: bool,
+    /// Chart line lengths in this many ranges after the counts
+    pub histogram: Option<usize>,
+    /// Counts to highlight in text output
+    pub warn: Vec<Threshold>,
+    /// Highlight with escape codes rather than a trailing `!`
+    pub color: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            format: OutputFormat::Plain,
+            modes: vec![CountMode::Lines, CountMode::Words, CountMode::Bytes],
+            max_line_length: false,
+            listed_modes: None,
+            totals: Totals::Auto,
+            aggregates: Vec::new(),
+            summary_only: false,
+            quiet: false,
+            filename_style: FilenameStyle::default(),
+            group_digits: None,
+            compat: None,
+            percent: false,
+            percentiles: Vec::new(),
+            min_line_length: false,
+            line_endings: false,
+            invalid_utf8: false,
+            hash: None,
+            dedup_content: false,
+            time: false,
+            started: Instant::now(),
+            json_array: false,
+            compact: false,
+            append: false,
+            line_numbers: false,
+            line_stats: true,
+            histogram: None,
+            warn: Vec::new(),
+            color: false,
+        }
+    }
+}
+
+impl FormatOptions {
+    /// The metrics shown as columns: the count modes, then the max line
+    /// length.
+    pub fn metrics(&self) -> Vec<Metric> {
+        let mut metrics: Vec<Metric> = self.modes.iter().map(Metric::from).collect();
+        if self.max_line_length {
+            metrics.push(Metric::MaxLineLength);
+        }
+        metrics
+    }
+
+    /// The metrics the JSON envelope and diffs list: `metrics`, but with
+    /// `listed_modes` when set.
+    pub fn listed_metrics(&self) -> Vec<Metric> {
+        let modes = self.listed_modes.as_ref().unwrap_or(&self.modes);
+        let mut metrics: Vec<Metric> = modes.iter().map(Metric::from).collect();
+        if self.max_line_length {
+            metrics.push(Metric::MaxLineLength);
+        }
+        metrics
+    }
+}
 
 // Common trait for formatting counts
 trait CountFormatter {
     fn format_count(&self, mode: &CountMode, counter: &WcCounter) -> String;
     fn format_max_line_length(&self, counter: &WcCounter) -> String;
+    fn format_extra(&self, name: &str, value: &Value) -> String;
+    fn format_percent(&self, percent: f64) -> String;
     fn format_filename(&self, filename: &Option<String>) -> String;
     fn format_total_label(&self) -> String;
+
+    // Text at the start of every row
+    fn line_prefix(&self) -> &str {
+        ""
+    }
+}
+
+// Optional metrics that were counted for this result, in display order
+fn extra_metrics(counter: &WcCounter, options: &FormatOptions) -> Vec<(String, Value)> {
+    let mut metrics = Vec::new();
+    if let Some(records) = counter.records {
+        metrics.push(("records".into(), json!(records)));
+    }
+    if let Some(csv) = &counter.csv {
+        metrics.push(("csv_records".into(), json!(csv.records)));
+        metrics.push(("csv_fields".into(), json!(csv.fields)));
+        metrics.push(("csv_max_fields".into(), json!(csv.max_fields)));
+        metrics.push(("csv_empty_cells".into(), json!(csv.empty_cells)));
+    }
+    if let Some(matches) = &counter.matches {
+        metrics.push(("matches".into(), json!(matches.matches)));
+        metrics.push(("matching_lines".into(), json!(matches.lines)));
+    }
+    if let Some(stats) = &counter.json {
+        metrics.push(("json_documents".into(), json!(stats.documents)));
+        metrics.push(("json_keys".into(), json!(stats.keys)));
+        metrics.push(("json_array_elements".into(), json!(stats.array_elements)));
+        metrics.push(("json_max_depth".into(), json!(stats.max_depth)));
+        metrics.push(("json_string_chars".into(), json!(stats.string_chars)));
+    }
+    if let Some(limit) = counter.line_length_limit {
+        metrics.push(("max_line_length_limit".into(), json!(limit)));
+    }
+    if let Some(longest) = &counter.longest {
+        metrics.push(("longest_line".into(), json!(longest.number)));
+        metrics.push(("longest_line_length".into(), json!(longest.length)));
+        if let Some(content) = &longest.content {
+            metrics.push(("longest_line_content".into(), json!(content)));
+        }
+    }
+    // Also counted for --histogram, but only shown when asked for
+    if let Some(stats) = counter.line_stats.as_ref().filter(|_| options.line_stats) {
+        metrics.push(("line_mean".into(), rounded(stats.mean())));
+        metrics.push(("line_median".into(), rounded(stats.median())));
+        metrics.push(("line_stddev".into(), rounded(stats.stddev())));
+    }
+    if let Some(blank) = &counter.blank_lines {
+        metrics.push(("empty_lines".into(), json!(blank.empty)));
+        metrics.push(("whitespace_lines".into(), json!(blank.whitespace_only)));
+    }
+    if let Some(trailing) = counter.trailing_whitespace {
+        metrics.push(("trailing_whitespace_lines".into(), json!(trailing)));
+    }
+    if let Some(indent) = &counter.indent_stats {
+        metrics.push(("tab_indented".into(), json!(indent.tabs)));
+        metrics.push(("space_indented".into(), json!(indent.spaces)));
+        metrics.push(("mixed_indented".into(), json!(indent.mixed)));
+        metrics.push(("indent_width".into(), json!(indent.modal_width())));
+    }
+    if let Some(sketch) = &counter.line_percentiles {
+        for &percentile in &options.percentiles {
+            metrics.push((format!("p{}", percentile), rounded(sketch.percentile(percentile))));
+        }
+    }
+    if let Some(longest) = counter.max_word_length {
+        metrics.push(("max_word_length".into(), json!(longest)));
+    }
+    if let Some(vocabulary) = &counter.vocabulary {
+        metrics.push(("unique_words".into(), json!(vocabulary.words.len())));
+        metrics.push(("ttr".into(), json!(vocabulary.ttr().map(|ttr| (ttr * 10_000.0).round() / 10_000.0))));
+    }
+    if let Some(longest) = &counter.longest_word {
+        metrics.push(("longest_word".into(), json!(longest.word)));
+        metrics.push(("longest_word_length".into(), json!(longest.length)));
+    }
+    if let Some(readability) = &counter.readability {
+        metrics.push(("sentences".into(), json!(readability.sentences)));
+        metrics.push(("syllables".into(), json!(readability.syllables)));
+        metrics.push(("complex_words".into(), json!(readability.complex_words)));
+        metrics.push(("flesch_reading_ease".into(), json!(readability.flesch_reading_ease().map(rounded))));
+        metrics.push(("flesch_kincaid_grade".into(), json!(readability.flesch_kincaid_grade().map(rounded))));
+        metrics.push(("gunning_fog".into(), json!(readability.gunning_fog().map(rounded))));
+    }
+    if let Some(patch) = &counter.patch {
+        metrics.push(("files_touched".into(), json!(patch.files)));
+        metrics.push(("added_lines".into(), json!(patch.added_lines)));
+        metrics.push(("removed_lines".into(), json!(patch.removed_lines)));
+        metrics.push(("added_words".into(), json!(patch.added_words)));
+        metrics.push(("removed_words".into(), json!(patch.removed_words)));
+    }
+    if let Some(emoji) = counter.emoji {
+        metrics.push(("emoji".into(), json!(emoji)));
+    }
+    // Shown even with no line to measure, so columns stay aligned
+    if options.min_line_length {
+        metrics.push(("min_line_length".into(), json!(counter.min_line_length)));
+    }
+    if let Some(histogram) = &counter.byte_histogram {
+        metrics.push(("entropy".into(), rounded(histogram.entropy())));
+        metrics.push(("binary".into(), json!(histogram.is_binary())));
+    }
+    // Also counted for --check-line-endings, but only shown when asked for
+    if let Some(endings) = counter.line_endings.as_ref().filter(|_| options.line_endings) {
+        metrics.push(("lf".into(), json!(endings.lf)));
+        metrics.push(("crlf".into(), json!(endings.crlf)));
+        metrics.push(("cr".into(), json!(endings.cr)));
+    }
+    if let Some(control) = counter.control_chars {
+        metrics.push(("control_chars".into(), json!(control)));
+    }
+    if let Some(scan) = &counter.unicode_scan {
+        metrics.push(("bidi_chars".into(), json!(scan.bidi)));
+        metrics.push(("zero_width_chars".into(), json!(scan.zero_width)));
+        metrics.push(("invisible_chars".into(), json!(scan.invisible)));
+    }
+    if let Some(missing) = counter.missing_final_newline {
+        metrics.push(("missing_final_newline".into(), json!(missing)));
+    }
+    // --compat may pick a policy without asking for the count
+    if let Some(invalid) = counter.invalid_utf8.filter(|_| options.invalid_utf8) {
+        metrics.push(("invalid_utf8".into(), json!(invalid)));
+    }
+    if let (Some(hash), Some(algorithm)) = (&counter.hash, options.hash) {
+        metrics.push((algorithm.name().into(), json!(hash)));
+    }
+    // Shown for every row once asked for, so columns stay aligned
+    if options.dedup_content {
+        metrics.push(("duplicate_of".into(), json!(counter.duplicate_of)));
+    }
+    if let Some(encoding) = &counter.encoding {
+        metrics.push(("encoding".into(), json!(encoding)));
+    }
+    if let Some(document) = counter.extracted_from {
+        metrics.push(("extracted_from".into(), json!(document)));
+    }
+    if let Some(language) = counter.comment_language {
+        metrics.push(("comment_language".into(), json!(language)));
+    }
+    if let Some(duration) = counter.duration {
+        metrics.push(("duration_ms".into(), rounded(duration.as_secs_f64() * 1000.0)));
+    }
+    metrics
+}
+
+// The extra metrics of `results` summarised as `kind` summarises counts:
+// whole when every value is, to two places otherwise. Metrics that aren't
+// numbers, such as names and flags, are left blank.
+fn aggregate_metrics(results: &[WcCounter], kind: Aggregate, options: &FormatOptions) -> Vec<(String, Value)> {
+    let mut columns: Vec<(String, Vec<Value>)> = Vec::new();
+    for result in results {
+        for (name, value) in extra_metrics(result, options) {
+            match columns.iter_mut().find(|(column, _)| *column == name) {
+                Some((_, values)) => values.push(value),
+                None => columns.push((name, vec![value])),
+            }
+        }
+    }
+
+    columns.into_iter()
+        .map(|(name, values)| {
+            let whole = values.iter().all(Value::is_u64);
+            let numbers = values.iter().filter_map(Value::as_f64).collect();
+            let value = match aggregate_values(numbers, kind) {
+                Some(value) if whole => json!(value.round() as u64),
+                Some(value) => rounded(value),
+                None => Value::Null,
+            };
+            (name, value)
+        })
+        .collect()
+}
+
+fn rounded(value: f64) -> Value {
+    json!((value * 100.0).round() / 100.0)
+}
+
+// Strings with whitespace are quoted so columns stay separable
+fn plain_value(value: &Value) -> String {
+    match value {
+        Value::String(s) if s.contains(char::is_whitespace) => format!("{:?}", s),
+        Value::String(s) => s.clone(),
+        Value::Null => "-".into(),
+        other => other.to_string(),
+    }
+}
+
+// Inserts `separator` between each group of three digits
+fn group_digits(value: u64, separator: &str) -> String {
+    let digits = value.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3 * separator.len());
+
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+
+    grouped
+}
+
+// Renders a count, grouping its digits if --group-digits was given
+fn format_number(value: usize, separator: Option<&str>) -> String {
+    match separator {
+        Some(separator) => group_digits(value as u64, separator),
+        None => value.to_string(),
+    }
+}
+
+// Like `plain_value`, but whole numbers get their digits grouped too
+fn format_value(value: &Value, separator: Option<&str>) -> String {
+    match (value.as_u64(), separator) {
+        (Some(number), Some(separator)) => group_digits(number, separator),
+        _ => plain_value(value),
+    }
+}
+
+#[derive(Default)]
+struct PlainFormatter {
+    group_separator: Option<String>,
+    // Counts are right-aligned to this many characters
+    width: usize,
+    prefix: &'static str,
+}
+
+#[derive(Default)]
+struct HumanFormatter {
+    group_separator: Option<String>,
+}
+
+impl PlainFormatter {
+    fn new(options: &FormatOptions) -> Self {
+        Self { group_separator: options.group_digits.clone(), ..Self::default() }
+    }
+
+    // Lays columns out the way --compat's wc would for `results`
+    fn with_compat(mut self, results: &[WcCounter], options: &FormatOptions) -> Self {
+        match options.compat {
+            Some(Compat::Gnu) => self.width = gnu_width(results, options),
+            Some(Compat::Bsd) => {
+                self.width = 7;
+                self.prefix = " ";
+            }
+            Some(Compat::Posix) | None => {}
+        }
+        self
+    }
+}
+
+// GNU wc pads every column to the digits in the inputs' total size, to at
+// least 7 when reading stdin (whose size it can't know), and not at all for
+// a single count of a single input
+fn gnu_width(results: &[WcCounter], options: &FormatOptions) -> usize {
+    let columns = options.modes.len() + usize::from(options.max_line_length);
+    if results.len() <= 1 && columns == 1 {
+        return 1;
+    }
+
+    let is_stdin = |result: &WcCounter| result.filename.as_deref().is_none_or(|name| name == "-");
+    let total: usize = results.iter().filter(|r| !is_stdin(r)).map(|r| r.bytes).sum();
+    let width = total.to_string().len();
+    if results.iter().any(is_stdin) { width.max(7) } else { width }
 }
 
-struct PlainFormatter;
-struct HumanFormatter;
+impl HumanFormatter {
+    fn new(options: &FormatOptions) -> Self {
+        Self { group_separator: options.group_digits.clone() }
+    }
+}
 
 impl CountFormatter for PlainFormatter {
     fn format_count(&self, mode: &CountMode, counter: &WcCounter) -> String {
-        match mode {
-            CountMode::Lines => counter.lines.to_string(),
-            CountMode::Words => counter.words.to_string(),
-            CountMode::Bytes => counter.bytes.to_string(),
-            CountMode::Chars => counter.chars.to_string(),
-        }
+        let count = format_number(counter.count(mode), self.group_separator.as_deref());
+        format!("{:>width$}", count, width = self.width)
     }
 
     fn format_max_line_length(&self, counter: &WcCounter) -> String {
-        counter.max_line_length.to_string()
+        let length = format_number(counter.max_line_length, self.group_separator.as_deref());
+        format!("{:>width$}", length, width = self.width)
+    }
+
+    fn format_extra(&self, _name: &str, value: &Value) -> String {
+        format_value(value, self.group_separator.as_deref())
+    }
+
+    fn format_percent(&self, percent: f64) -> String {
+        format!("{:.1}%", percent)
     }
 
     fn format_filename(&self, filename: &Option<String>) -> String {
@@ -37,20 +451,34 @@ impl CountFormatter for PlainFormatter {
     fn format_total_label(&self) -> String {
         "total".to_string()
     }
+
+    fn line_prefix(&self) -> &str {
+        self.prefix
+    }
 }
 
 impl CountFormatter for HumanFormatter {
     fn format_count(&self, mode: &CountMode, counter: &WcCounter) -> String {
+        let count = format_number(counter.count(mode), self.group_separator.as_deref());
         match mode {
-            CountMode::Lines => format!("lines: {}", counter.lines),
-            CountMode::Words => format!("words: {}", counter.words),
-            CountMode::Bytes => format!("bytes: {}", counter.bytes),
-            CountMode::Chars => format!("chars: {}", counter.chars),
+            CountMode::Lines => format!("lines: {}", count),
+            CountMode::Words => format!("words: {}", count),
+            CountMode::Bytes => format!("bytes: {}", count),
+            CountMode::Chars => format!("chars: {}", count),
         }
     }
 
     fn format_max_line_length(&self, counter: &WcCounter) -> String {
-        format!("{} max line length", counter.max_line_length)
+        let length = format_number(counter.max_line_length, self.group_separator.as_deref());
+        format!("{} max line length", length)
+    }
+
+    fn format_extra(&self, name: &str, value: &Value) -> String {
+        format!("{}: {}", name, format_value(value, self.group_separator.as_deref()))
+    }
+
+    fn format_percent(&self, percent: f64) -> String {
+        format!("{:.1}% of total", percent)
     }
 
     fn format_filename(&self, filename: &Option<String>) -> String {
@@ -64,118 +492,801 @@ impl CountFormatter for HumanFormatter {
     }
 }
 
+// Applies --filename-style (and --quiet) to a result's filename. Standard
+// input keeps its "-" name; paths that can't be resolved are shown as given.
+fn display_name(filename: &str, options: &FormatOptions) -> Option<String> {
+    if options.quiet {
+        return None;
+    }
+
+    let path = Path::new(filename);
+    match options.filename_style {
+        _ if filename == "-" => Some(filename.to_string()),
+        FilenameStyle::Path => Some(filename.to_string()),
+        FilenameStyle::Absolute => {
+            let absolute = fs::canonicalize(path).or_else(|_| path::absolute(path));
+            Some(absolute.map_or_else(|_| filename.to_string(), |p| p.display().to_string()))
+        }
+        FilenameStyle::Basename => path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .or_else(|| Some(filename.to_string())),
+        FilenameStyle::None => None,
+    }
+}
+
+// With --percent, returns each result's share of the total for the first
+// count column; `None` when there's no column or nothing to share
+fn percent_shares(results: &[WcCounter], modes: &[CountMode], options: &FormatOptions) -> Option<Vec<f64>> {
+    let mode = modes.first().filter(|_| options.percent)?;
+    let total: usize = results.iter().map(|result| result.count(mode)).sum();
+
+    Some(results.iter().map(|result| match total {
+        0 => 0.0,
+        _ => result.count(mode) as f64 * 100.0 / total as f64,
+    }).collect())
+}
+
+fn shows_total(results: &[WcCounter], options: &FormatOptions) -> bool {
+    let wanted = match options.totals {
+        // The total row carries the whole run's time
+        Totals::Auto => results.len() > 1 || options.time,
+        Totals::Always => true,
+        Totals::Never => false,
+    };
+    wanted && !options.quiet
+}
+
+// A row printed after the per-file results: the total (`None`) or an
+// aggregate, with its extra metrics
+type SummaryRow = (Option<Aggregate>, WcCounter, Vec<(String, Value)>);
+
+// Rows printed after the per-file results: the total, then any requested
+// aggregates
+fn summary_rows(results: &[WcCounter], options: &FormatOptions) -> Vec<SummaryRow> {
+    let mut rows = Vec::new();
+    let shows_total = shows_total(results, options);
+
+    // Content seen before (--dedup-content) is only counted once
+    let unique: Vec<WcCounter>;
+    let results = match results.iter().any(|result| result.duplicate_of.is_some()) {
+        true => {
+            unique = results.iter().filter(|result| result.duplicate_of.is_none()).cloned().collect();
+            &unique[..]
+        }
+        false => results,
+    };
+
+    if shows_total {
+        let mut total = counter::total(results);
+        // The total row is printed once counting is done, so this is the
+        // whole run
+        if options.time {
+            total.duration = Some(options.started.elapsed());
+        }
+        let metrics = extra_metrics(&total, options);
+        rows.push((None, total, metrics));
+    }
+
+    if !results.is_empty() && !options.quiet {
+        for &kind in &options.aggregates {
+            rows.push((Some(kind), aggregate(results, kind), aggregate_metrics(results, kind, options)));
+        }
+    }
+
+    rows
+}
+
+// Highlights a count that matches a --warn rule, or a max line length over
+// the .editorconfig limit: yellow with color, else followed by `!`
+fn mark_warning(part: String, metric: Metric, result: &WcCounter, options: &FormatOptions) -> String {
+    let over_limit = metric == Metric::MaxLineLength
+        && result.line_length_limit.is_some_and(|limit| result.max_line_length > limit);
+    if !over_limit && !options.warn.iter().any(|rule| rule.metric == metric && rule.is_violated_by(result)) {
+        return part;
+    }
+    match options.color {
+        true => format!("\x1b[33m{}\x1b[0m", part),
+        false => format!("{}!", part),
+    }
+}
+
+// The count, percent and extra columns shared by result and summary rows
+fn row_parts<F: CountFormatter>(
+    result: &WcCounter,
+    metrics: Vec<(String, Value)>,
+    options: &FormatOptions,
+    formatter: &F,
+    share: Option<f64>,
+) -> Vec<String> {
+    let mut parts: Vec<String> = options.modes.iter()
+        .map(|mode| mark_warning(formatter.format_count(mode, result), Metric::from(mode), result, options))
+        .collect();
+
+    if options.max_line_length {
+        let part = formatter.format_max_line_length(result);
+        parts.push(mark_warning(part, Metric::MaxLineLength, result, options));
+    }
+
+    if let Some(share) = share {
+        parts.push(formatter.format_percent(share));
+    }
+
+    for (name, value) in metrics {
+        parts.push(formatter.format_extra(&name, &value));
+    }
+
+    parts
+}
+
+// One result's line, without the newline
+fn format_row<F: CountFormatter>(result: &WcCounter, options: &FormatOptions, formatter: &F, share: Option<f64>) -> String {
+    let mut parts = row_parts(result, extra_metrics(result, options), options, formatter, share);
+    if let Some(filename) = result.filename.as_deref().and_then(|f| display_name(f, options)) {
+        parts.push(formatter.format_filename(&Some(filename)));
+    }
+    format!("{}{}", formatter.line_prefix(), parts.join(" "))
+}
+
+// The total (`None`) or an aggregate's line, without the newline
+fn format_summary_row<F: CountFormatter>(row: &SummaryRow, options: &FormatOptions, formatter: &F) -> String {
+    let (kind, total, metrics) = row;
+    let mut parts = row_parts(total, metrics.clone(), options, formatter, None);
+    match kind {
+        Some(kind) => parts.push(kind.name().to_string()),
+        None => parts.push(formatter.format_total_label()),
+    }
+    format!("{}{}", formatter.line_prefix(), parts.join(" "))
+}
+
 fn build_output<F: CountFormatter>(
     results: &[WcCounter],
-    cli: &Cli,
+    options: &FormatOptions,
     formatter: F,
 ) -> String {
-    let modes = cli.get_count_modes();
+    let shares = percent_shares(results, &options.modes, options);
     let mut output = String::new();
 
-    for result in results {
-        let mut parts: Vec<String> = modes.iter()
-            .map(|mode| formatter.format_count(mode, result))
-            .collect();
+    for (index, result) in results.iter().enumerate().filter(|_| !options.summary_only) {
+        let share = shares.as_ref().map(|shares| shares[index]);
+        output.push_str(&format_row(result, options, &formatter, share));
+        output.push('\n');
+    }
 
-        if cli.max_line_length {
-            parts.push(formatter.format_max_line_length(result));
-        }
+    for row in summary_rows(results, options) {
+        output.push_str(&format_summary_row(&row, options, &formatter));
+        output.push('\n');
+    }
+
+    output
+}
+
+/// One result as the JSON object `--format json` would print for it.
+pub fn result_json(result: &WcCounter, options: &FormatOptions) -> serde_json::Map<String, Value> {
+    let mut json_obj = serde_json::Map::new();
+
+    for mode in &options.modes {
+        match mode {
+            CountMode::Lines => json_obj.insert("lines".into(), json!(result.lines)),
+            CountMode::Words => json_obj.insert("words".into(), json!(result.words)),
+            CountMode::Bytes => json_obj.insert("bytes".into(), json!(result.bytes)),
+            CountMode::Chars => json_obj.insert("chars".into(), json!(result.chars)),
+        };
+    }
+
+    if options.max_line_length {
+        json_obj.insert("max_line_length".into(), json!(result.max_line_length));
+    }
+
+    for (name, value) in extra_metrics(result, options) {
+        json_obj.insert(name, value);
+    }
+
+    if let Some(filename) = result.filename.as_deref().and_then(|f| display_name(f, options)) {
+        json_obj.insert("filename".into(), json!(filename));
+    }
 
-        if let Some(filename) = &result.filename {
-            parts.push(formatter.format_filename(&Some(filename.clone())));
+    json_obj
+}
+
+fn skipped_json(entry: &Skipped, options: &FormatOptions) -> Value {
+    let mut json_obj = serde_json::Map::new();
+    if let Some(filename) = display_name(&entry.path.display().to_string(), options) {
+        json_obj.insert("filename".into(), json!(filename));
+    }
+    json_obj.insert("skipped".into(), json!(entry.reason.name()));
+    json!(json_obj)
+}
+
+fn failure_json(failure: &Failure, options: &FormatOptions) -> Value {
+    let mut json_obj = serde_json::Map::new();
+    if let Some(filename) = display_name(&failure.path.display().to_string(), options) {
+        json_obj.insert("filename".into(), json!(filename));
+    }
+    json_obj.insert("error".into(), json!(failure.message));
+    json!(json_obj)
+}
+
+fn summary_json((kind, total, metrics): &SummaryRow, options: &FormatOptions) -> Value {
+    let mut json_obj = result_json(total, options);
+    json_obj.extend(metrics.iter().cloned());
+    json_obj.insert("type".into(), json!(kind.map_or("total", |kind| kind.name())));
+    json!(json_obj)
+}
+
+// Every JSON entry in output order: results, inputs that failed, skipped
+// inputs, summary rows
+fn json_entries(results: &[WcCounter], skipped: &[Skipped], failures: &[Failure], options: &FormatOptions) -> Vec<Value> {
+    let shares = percent_shares(results, &options.modes, options);
+    let mut entries = Vec::with_capacity(results.len() + failures.len() + skipped.len() + 1);
+
+    for (index, result) in results.iter().enumerate().filter(|_| !options.summary_only) {
+        let mut json_obj = result_json(result, options);
+
+        if let Some(shares) = &shares {
+            json_obj.insert("percent".into(), rounded(shares[index]));
         }
 
-        output.push_str(&parts.join(" "));
-        output.push('\n');
+        entries.push(json!(json_obj));
+    }
+
+    entries.extend(failures.iter().map(|failure| failure_json(failure, options)));
+    entries.extend(skipped.iter().map(|entry| skipped_json(entry, options)));
+    entries.extend(summary_rows(results, options).iter().map(|row| summary_json(row, options)));
+    entries
+}
+
+/// Version of the `--format json` envelope, raised whenever its shape or the
+/// shape of its entries changes incompatibly.
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+// UTC time as RFC 3339, e.g. 2024-05-01T12:34:56Z
+fn rfc3339(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, rem) = ((secs / 86_400) as i64, secs % 86_400);
+
+    // Civil date from days since 1970-01-01, per Howard Hinnant's
+    // days_from_civil inverse
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, rem / 3600, rem % 3600 / 60, rem % 60)
+}
+
+/// When the run started, as RFC 3339 UTC.
+pub fn run_timestamp(options: &FormatOptions) -> String {
+    rfc3339(SystemTime::now() - options.started.elapsed())
+}
+
+// What the JSON envelope records about the run, apart from its results and
+// how long it took
+fn run_metadata(options: &FormatOptions) -> serde_json::Map<String, Value> {
+    let modes: Vec<&str> = options.listed_metrics().iter().map(|metric| metric.name()).collect();
+
+    let mut metadata = serde_json::Map::new();
+    metadata.insert("schema_version".into(), json!(JSON_SCHEMA_VERSION));
+    metadata.insert("version".into(), json!(env!("CARGO_PKG_VERSION")));
+    metadata.insert("timestamp".into(), json!(run_timestamp(options)));
+    metadata.insert("modes".into(), json!(modes));
+    metadata
+}
+
+fn elapsed_ms(options: &FormatOptions) -> Value {
+    rounded(options.started.elapsed().as_secs_f64() * 1000.0)
+}
+
+// Wraps JSON entries in the envelope, unless --json-array asks for them bare
+fn json_document(entries: Vec<Value>, options: &FormatOptions) -> Value {
+    if options.json_array {
+        return Value::Array(entries);
+    }
+    let mut document = run_metadata(options);
+    document.insert("elapsed_ms".into(), elapsed_ms(options));
+    document.insert("results".into(), Value::Array(entries));
+    Value::Object(document)
+}
+
+// A JSON document as text: indented, or on one line with --compact
+fn json_text(value: &Value, options: &FormatOptions) -> WcResult<String> {
+    match options.compact {
+        true => Ok(value.to_string()),
+        false => to_string_pretty(value).map_err(Into::into),
+    }
+}
+
+/// Renders results (and skipped or failed inputs) as a JSON array, whatever
+/// `--format` says.
+pub fn format_json(results: &[WcCounter], skipped: &[Skipped], failures: &[Failure], options: &FormatOptions) -> WcResult<String> {
+    to_string_pretty(&json_entries(results, skipped, failures, options)).map_err(Into::into)
+}
+
+// One compact JSON object per line
+fn format_ndjson(results: &[WcCounter], skipped: &[Skipped], failures: &[Failure], options: &FormatOptions) -> String {
+    json_entries(results, skipped, failures, options).iter()
+        .map(|entry| format!("{}\n", entry))
+        .collect()
+}
+
+// Escapes a Prometheus label value
+fn prometheus_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+// Appends one metric family: its HELP and TYPE lines, then a sample per
+// (labels, value) pair
+fn push_prometheus_family(output: &mut String, name: &str, kind: &str, help: &str, samples: &[(String, Value)]) {
+    output.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind));
+    for (labels, value) in samples {
+        output.push_str(&format!("{}{} {}\n", name, labels, value));
     }
+}
 
-    if results.len() > 1 {
-        let mut total = WcCounter::new();
-        for result in results {
-            total += result;
+// Each result's label set. Standard input is `file="-"`, and a name seen
+// before also gets an `occurrence` label, since the exposition format rejects
+// two samples with the same labels
+fn prometheus_labels(results: &[WcCounter], options: &FormatOptions) -> Vec<String> {
+    let mut seen: HashMap<Option<String>, usize> = HashMap::new();
+    results.iter()
+        .map(|result| {
+            let name = display_name(result.filename.as_deref().unwrap_or("-"), options);
+            let occurrence = seen.entry(name.clone()).or_default();
+            *occurrence += 1;
+
+            let mut labels = Vec::new();
+            if let Some(name) = name {
+                labels.push(format!("file=\"{}\"", prometheus_label(&name)));
+            }
+            if *occurrence > 1 {
+                labels.push(format!("occurrence=\"{}\"", occurrence));
+            }
+            match labels.is_empty() {
+                true => String::new(),
+                false => format!("{{{}}}", labels.join(",")),
+            }
+        })
+        .collect()
+}
+
+// Per-file samples only; totals are left to the query side to sum
+fn format_prometheus(results: &[WcCounter], options: &FormatOptions) -> String {
+    let labels = prometheus_labels(results, options);
+
+    let mut output = String::new();
+    for metric in options.metrics() {
+        let samples: Vec<(String, Value)> = results.iter()
+            .zip(&labels)
+            .map(|(result, labels)| (labels.clone(), json!(metric.value(result))))
+            .collect();
+        match metric {
+            Metric::MaxLineLength => push_prometheus_family(
+                &mut output, "rs_wc_max_line_length", "gauge", "Length of the longest line.", &samples,
+            ),
+            _ => push_prometheus_family(
+                &mut output,
+                &format!("rs_wc_{}_total", metric.name()),
+                "counter",
+                &format!("Number of {} counted.", metric.name()),
+                &samples,
+            ),
         }
+    }
 
-        let mut parts: Vec<String> = modes.iter()
-            .map(|mode| formatter.format_count(mode, &total))
+    // Optional metrics become gauges named after their JSON key; booleans
+    // are exported as 0 or 1 and text values are left out
+    let mut extras: Vec<(String, Vec<(String, Value)>)> = Vec::new();
+    for (result, labels) in results.iter().zip(&labels) {
+        for (name, value) in extra_metrics(result, options) {
+            let value = match value {
+                Value::Bool(flag) => json!(u8::from(flag)),
+                Value::Number(_) => value,
+                _ => continue,
+            };
+            match extras.iter_mut().find(|(existing, _)| *existing == name) {
+                Some((_, samples)) => samples.push((labels.clone(), value)),
+                None => extras.push((name, vec![(labels.clone(), value)])),
+            }
+        }
+    }
+    for (name, samples) in extras {
+        let help = format!("The {} metric.", name.replace('_', " "));
+        let name: String = name.chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
             .collect();
+        push_prometheus_family(&mut output, &format!("rs_wc_{}", name), "gauge", &help, &samples);
+    }
+
+    output
+}
 
-        if cli.max_line_length {
-            parts.push(formatter.format_max_line_length(&total));
+/// Renders `results` as `options.format` says, followed by the total and
+/// aggregate rows.
+pub fn format(results: &[WcCounter], options: &FormatOptions) -> WcResult<String> {
+    format_results_with_issues(results, &[], &[], options)
+}
+
+/// `format`, laid out as the command line asks.
+pub fn format_results(results: &[WcCounter], cli: &Cli) -> WcResult<String> {
+    format(results, &cli.format_options())
+}
+
+/// Like `format`, but JSON output also lists inputs that were
+/// skipped or couldn't be counted. Other formats leave those to the caller's
+/// diagnostics.
+pub fn format_results_with_issues(
+    results: &[WcCounter],
+    skipped: &[Skipped],
+    failures: &[Failure],
+    options: &FormatOptions,
+) -> WcResult<String> {
+    match options.format {
+        OutputFormat::Plain => Ok(build_output(results, options, PlainFormatter::new(options).with_compat(results, options))),
+        OutputFormat::Human => Ok(build_output(results, options, HumanFormatter::new(options))),
+        OutputFormat::Json => {
+            let document = json_document(json_entries(results, skipped, failures, options), options);
+            Ok(json_text(&document, options)? + "\n")
         }
+        OutputFormat::Ndjson => Ok(format_ndjson(results, skipped, failures, options)),
+        OutputFormat::Prometheus => Ok(format_prometheus(results, options)),
+        OutputFormat::Msgpack | OutputFormat::Cbor | OutputFormat::Parquet => Err(binary_format_error(&options.format)),
+    }
+}
 
-        parts.push(formatter.format_total_label());
-        output.push_str(&parts.join(" "));
-        output.push('\n');
+/// Like `format_results_with_issues`, as the bytes to write. MessagePack
+/// and CBOR encode the same document as `--format json`; Parquet has just
+/// the per-file rows.
+pub fn render_results(
+    results: &[WcCounter],
+    skipped: &[Skipped],
+    failures: &[Failure],
+    options: &FormatOptions,
+) -> WcResult<Vec<u8>> {
+    match options.format {
+        OutputFormat::Msgpack | OutputFormat::Cbor => {
+            encode_binary(&json_document(json_entries(results, skipped, failures, options), options), &options.format)
+        }
+        OutputFormat::Parquet => format_parquet(results, options),
+        _ => format_results_with_issues(results, skipped, failures, options).map(String::into_bytes),
     }
+}
 
-    output
+fn binary_format_error(format: &OutputFormat) -> WcError {
+    let name = match format {
+        OutputFormat::Msgpack => "msgpack",
+        OutputFormat::Cbor => "cbor",
+        _ => "parquet",
+    };
+    WcError::invalid_argument(format!("--format {} is binary and only available for counting results", name))
+}
+
+#[cfg(feature = "parquet")]
+fn format_parquet(results: &[WcCounter], options: &FormatOptions) -> WcResult<Vec<u8>> {
+    if options.append {
+        return Err(WcError::invalid_argument("--append can't be used with --format parquet"));
+    }
+    let rows: Vec<_> = results.iter().map(|result| result_json(result, options)).collect();
+    crate::columnar::to_parquet(&rows)
 }
 
-fn format_json(results: &[WcCounter], cli: &Cli) -> WcResult<String> {
-    let modes = cli.get_count_modes();
-    let mut json_results = Vec::with_capacity(results.len() + 1);
+#[cfg(not(feature = "parquet"))]
+fn format_parquet(_results: &[WcCounter], _options: &FormatOptions) -> WcResult<Vec<u8>> {
+    Err(WcError::invalid_argument("--format parquet needs rs-wc built with `--features parquet`"))
+}
 
-    for result in results {
-        let mut json_obj = serde_json::Map::new();
-
-        for mode in &modes {
-            match mode {
-                CountMode::Lines => json_obj.insert("lines".into(), json!(result.lines)),
-                CountMode::Words => json_obj.insert("words".into(), json!(result.words)),
-                CountMode::Bytes => json_obj.insert("bytes".into(), json!(result.bytes)),
-                CountMode::Chars => json_obj.insert("chars".into(), json!(result.chars)),
-            };
+// Without both features, one of the arms never looks at `value`
+#[cfg_attr(not(all(feature = "msgpack", feature = "cbor")), allow(unused_variables))]
+fn encode_binary(value: &Value, format: &OutputFormat) -> WcResult<Vec<u8>> {
+    match format {
+        #[cfg(feature = "msgpack")]
+        OutputFormat::Msgpack => rmp_serde::to_vec_named(value).map_err(|e| WcError::Encode(e.to_string())),
+        #[cfg(not(feature = "msgpack"))]
+        OutputFormat::Msgpack => Err(WcError::invalid_argument("--format msgpack needs rs-wc built with `--features msgpack`")),
+        #[cfg(feature = "cbor")]
+        OutputFormat::Cbor => {
+            let mut bytes = Vec::new();
+            ciborium::into_writer(value, &mut bytes).map_err(|e| WcError::Encode(e.to_string()))?;
+            Ok(bytes)
         }
+        #[cfg(not(feature = "cbor"))]
+        OutputFormat::Cbor => Err(WcError::invalid_argument("--format cbor needs rs-wc built with `--features cbor`")),
+        _ => unreachable!("only called for binary formats"),
+    }
+}
 
-        if cli.max_line_length {
-            json_obj.insert("max_line_length".into(), json!(result.max_line_length));
+/// Formats results one at a time as they are counted, for `--stream`. JSON
+/// is written with one entry per line, in the envelope unless --json-array
+/// is given; summary rows come last, from `finish`.
+pub struct StreamPrinter<'a> {
+    options: &'a FormatOptions,
+    results: Vec<WcCounter>,
+    // JSON entries written so far
+    written: usize,
+}
+
+impl<'a> StreamPrinter<'a> {
+    pub fn new(options: &'a FormatOptions) -> WcResult<Self> {
+        match options.format {
+            OutputFormat::Prometheus => {
+                return Err(WcError::invalid_argument("--stream can't be used with --format prometheus"));
+            }
+            OutputFormat::Msgpack | OutputFormat::Cbor | OutputFormat::Parquet => {
+                return Err(WcError::invalid_argument("--stream can't be used with a binary --format"));
+            }
+            _ => {}
         }
+        Ok(Self { options, results: Vec::new(), written: 0 })
+    }
 
-        if let Some(filename) = &result.filename {
-            json_obj.insert("filename".into(), json!(filename));
+    // What separates JSON tokens, as json_text lays them out: a line break,
+    // the space after a colon, and one level of indentation, or nothing at
+    // all with --compact
+    fn json_layout(&self) -> (&'static str, &'static str, &'static str) {
+        match self.options.compact {
+            true => ("", ":", ""),
+            false => ("\n", ": ", "  "),
         }
+    }
+
+    // `value` as json_text writes it, nested `depth` levels deep
+    fn nested_json(&self, value: &Value, depth: usize) -> String {
+        let indent = self.json_layout().2.repeat(depth);
+        json_text(value, self.options).unwrap_or_default().replace('\n', &format!("\n{}", indent))
+    }
+
+    // A JSON entry, separated from the previous one; the first opens the
+    // array, and the envelope around it. The elapsed time isn't known yet, so
+    // the envelope gets it last.
+    fn json_entry(&mut self, entry: Value) -> String {
+        let options = self.options;
+        let (newline, colon, indent) = self.json_layout();
+        let depth = if options.json_array { 1 } else { 2 };
+        let prefix = match (self.written, options.json_array) {
+            (0, true) => format!("[{}", newline),
+            (0, false) => {
+                let metadata: String = run_metadata(options).iter()
+                    .map(|(key, value)| format!("{}{}{}{},{}", indent, json!(key), colon, self.nested_json(value, 1), newline))
+                    .collect();
+                format!("{{{}{}{}\"results\"{}[{}", newline, metadata, indent, colon, newline)
+            }
+            _ => format!(",{}", newline),
+        };
+        self.written += 1;
+        format!("{}{}{}", prefix, indent.repeat(depth), self.nested_json(&entry, depth))
+    }
 
-        json_results.push(json!(json_obj));
+    /// The text to print for a newly counted result.
+    pub fn row(&mut self, result: WcCounter) -> String {
+        let options = self.options;
+        let text = match options.format {
+            // Without every result up front, GNU columns can't be sized
+            OutputFormat::Plain => format_row(&result, options, &PlainFormatter::new(options).with_compat(&[], options), None) + "\n",
+            OutputFormat::Human => format_row(&result, options, &HumanFormatter::new(options), None) + "\n",
+            OutputFormat::Json => self.json_entry(json!(result_json(&result, options))),
+            OutputFormat::Ndjson => format!("{}\n", json!(result_json(&result, options))),
+            OutputFormat::Prometheus | OutputFormat::Msgpack | OutputFormat::Cbor | OutputFormat::Parquet => {
+                unreachable!("rejected by StreamPrinter::new")
+            }
+        };
+        self.results.push(result);
+        text
     }
 
-    if results.len() > 1 {
-        let mut total = WcCounter::new();
-        for result in results {
-            total += result;
+    /// The text to print for an input that couldn't be counted: an error
+    /// entry in JSON output, nothing otherwise.
+    pub fn failure(&mut self, failure: &Failure) -> String {
+        match self.options.format {
+            OutputFormat::Json => self.json_entry(failure_json(failure, self.options)),
+            OutputFormat::Ndjson => format!("{}\n", failure_json(failure, self.options)),
+            _ => String::new(),
         }
+    }
 
-        let mut json_obj = serde_json::Map::new();
-        for mode in &modes {
-            match mode {
-                CountMode::Lines => json_obj.insert("lines".into(), json!(total.lines)),
-                CountMode::Words => json_obj.insert("words".into(), json!(total.words)),
-                CountMode::Bytes => json_obj.insert("bytes".into(), json!(total.bytes)),
-                CountMode::Chars => json_obj.insert("chars".into(), json!(total.chars)),
-            };
+    /// The text that ends the output: skipped inputs (JSON only) and the
+    /// total and aggregate rows.
+    pub fn finish(mut self, skipped: &[Skipped]) -> String {
+        let options = self.options;
+        let summaries = summary_rows(&self.results, options);
+
+        match options.format {
+            OutputFormat::Plain => summaries.iter()
+                .map(|row| format_summary_row(row, options, &PlainFormatter::new(options).with_compat(&[], options)) + "\n")
+                .collect(),
+            OutputFormat::Human => summaries.iter()
+                .map(|row| format_summary_row(row, options, &HumanFormatter::new(options)) + "\n")
+                .collect(),
+            OutputFormat::Json | OutputFormat::Ndjson => {
+                let entries = skipped.iter()
+                    .map(|entry| skipped_json(entry, options))
+                    .chain(summaries.iter().map(|row| summary_json(row, options)));
+
+                if matches!(options.format, OutputFormat::Ndjson) {
+                    return entries.map(|entry| format!("{}\n", entry)).collect();
+                }
+
+                let entries: Vec<Value> = entries.collect();
+                let mut output = String::new();
+                for entry in entries {
+                    output.push_str(&self.json_entry(entry));
+                }
+                let (newline, colon, indent) = self.json_layout();
+                match (self.written, options.json_array) {
+                    (0, _) => json_text(&json_document(Vec::new(), options), options).unwrap_or_default() + "\n",
+                    (_, true) => format!("{}{}]\n", output, newline),
+                    (_, false) => format!(
+                        "{}{nl}{indent}],{nl}{indent}\"elapsed_ms\"{}{}{nl}}}\n",
+                        output, colon, elapsed_ms(options), nl = newline, indent = indent,
+                    ),
+                }
+            }
+            OutputFormat::Prometheus | OutputFormat::Msgpack | OutputFormat::Cbor | OutputFormat::Parquet => {
+                unreachable!("rejected by StreamPrinter::new")
+            }
+        }
+    }
+}
+
+fn format_delta(delta: &MetricDelta) -> (String, String) {
+    let change = format!("{:+}", delta.delta());
+    let percent = delta.percent_change()
+        .map(|p| format!("{:+.1}%", p))
+        .unwrap_or_else(|| "n/a".to_string());
+    (change, percent)
+}
+
+pub fn format_diff(diff: &CountDiff, options: &FormatOptions) -> WcResult<String> {
+    let deltas = diff.deltas(&options.listed_metrics());
+    let old_name = diff.old.filename.as_deref().unwrap_or("-");
+    let new_name = diff.new.filename.as_deref().unwrap_or("-");
+
+    match options.format {
+        OutputFormat::Plain => Ok(deltas.iter().map(|delta| {
+            let (change, percent) = format_delta(delta);
+            format!("{} {} {} {} {}\n", delta.metric.name(), delta.old, delta.new, change, percent)
+        }).collect()),
+        OutputFormat::Human => {
+            let mut output = format!("{} -> {}\n", old_name, new_name);
+            for delta in &deltas {
+                let (change, percent) = format_delta(delta);
+                output.push_str(&format!(
+                    "{}: {} -> {} ({}, {})\n",
+                    delta.metric.name(), delta.old, delta.new, change, percent
+                ));
+            }
+            Ok(output)
         }
+        OutputFormat::Json | OutputFormat::Ndjson => {
+            let mut metrics = serde_json::Map::new();
+            for delta in &deltas {
+                metrics.insert(delta.metric.name().into(), json!({
+                    "old": delta.old,
+                    "new": delta.new,
+                    "delta": delta.delta(),
+                    "percent_change": delta.percent_change(),
+                }));
+            }
 
-        if cli.max_line_length {
-            json_obj.insert("max_line_length".into(), json!(total.max_line_length));
+            let diff = json!({
+                "old": old_name,
+                "new": new_name,
+                "metrics": metrics,
+            });
+            match options.format {
+                OutputFormat::Ndjson => Ok(format!("{}\n", diff)),
+                _ => json_text(&diff, options),
+            }
+        }
+        OutputFormat::Prometheus => {
+            let labels = format!(
+                "{{old=\"{}\",new=\"{}\"}}",
+                prometheus_label(old_name), prometheus_label(new_name)
+            );
+            let mut output = String::new();
+            for delta in &deltas {
+                let name = format!("rs_wc_{}_delta", delta.metric.name().replace('-', "_"));
+                let help = format!("Change in {} between the two inputs.", delta.metric.name());
+                push_prometheus_family(&mut output, &name, "gauge", &help, &[(labels.clone(), json!(delta.delta()))]);
+            }
+            Ok(output)
         }
+        OutputFormat::Msgpack | OutputFormat::Cbor | OutputFormat::Parquet => Err(binary_format_error(&options.format)),
+    }
+}
 
-        json_obj.insert("type".into(), json!("total"));
-        json_results.push(json!(json_obj));
+// Longest bar in a --histogram chart
+const HISTOGRAM_WIDTH: usize = 40;
+
+/// Charts each result's line lengths in `buckets` ranges, one `#` bar per
+/// range scaled to the range with the most lines. With several results the
+/// total gets a chart too, unless totals are turned off.
+pub fn format_histograms(results: &[WcCounter], buckets: usize, options: &FormatOptions) -> WcResult<String> {
+    if !matches!(options.format, OutputFormat::Plain | OutputFormat::Human) {
+        return Err(WcError::invalid_argument("--histogram draws a text chart, so needs --format plain or human"));
     }
 
-    to_string_pretty(&json_results).map_err(Into::into)
-}
+    let mut charts: Vec<(String, LineStats)> = results.iter()
+        .filter_map(|result| Some((
+            display_name(result.filename.as_deref().unwrap_or("-"), options).unwrap_or_default(),
+            result.line_stats.clone()?,
+        )))
+        .collect();
+    if charts.len() > 1 && !matches!(options.totals, Totals::Never) {
+        let mut total = LineStats::default();
+        for (_, stats) in &charts {
+            total.add(stats);
+        }
+        charts.push(("total".to_string(), total));
+    }
 
-pub fn format_results(results: &[WcCounter], cli: &Cli) -> WcResult<String> {
-    if cli.max_line_length && !cli.lines && !cli.words && !cli.bytes && !cli.chars {
-        return Ok(results.iter().map(|r| {
-            format!("{} {}\n", r.max_line_length, r.filename.as_deref().unwrap_or(""))
-        }).collect::<Vec<_>>().join("\n"));
+    let mut output = String::new();
+    for (name, stats) in charts {
+        let ranges = stats.buckets(buckets);
+        let most = ranges.iter().map(|&(_, _, lines)| lines).max().unwrap_or(0).max(1);
+        let label_width = ranges.last().map_or(1, |&(_, last, _)| last.to_string().len());
+
+        output.push_str(&format!("\nline lengths in {}:\n", if name.is_empty() { "-" } else { &name }));
+        for (first, last, lines) in ranges {
+            let bar = "#".repeat((lines as usize * HISTOGRAM_WIDTH).div_ceil(most as usize));
+            output.push_str(&format!(
+                "{:>width$}-{:<width$} | {:<bar_width$} {}\n",
+                first, last, bar, format_number(lines as usize, options.group_digits.as_deref()),
+                width = label_width, bar_width = HISTOGRAM_WIDTH,
+            ));
+        }
     }
+    Ok(output)
+}
+
+/// Renders the line widths collected by `--per-line`, one line per row:
+/// the filename when there are several inputs, the line number with
+/// `--line-numbers`, then the width. JSON gives each input's widths as an
+/// array, and NDJSON a record per line.
+pub fn format_per_line(results: &[WcCounter], options: &FormatOptions) -> WcResult<String> {
+    let inputs: Vec<(Option<String>, &[usize])> = results.iter()
+        .map(|result| {
+            let name = display_name(result.filename.as_deref().unwrap_or("-"), options);
+            (name, result.line_widths.as_deref().unwrap_or_default())
+        })
+        .collect();
 
-    match cli.format {
-        OutputFormat::Plain => Ok(build_output(results, cli, PlainFormatter)),
-        OutputFormat::Human => Ok(build_output(results, cli, HumanFormatter)),
-        OutputFormat::Json => format_json(results, cli),
+    match options.format {
+        OutputFormat::Plain | OutputFormat::Human => {
+            let named = inputs.len() > 1;
+            let separator = if matches!(options.format, OutputFormat::Human) { ":" } else { " " };
+            let mut output = String::new();
+            for (name, widths) in &inputs {
+                for (index, width) in widths.iter().enumerate() {
+                    let mut columns = Vec::with_capacity(3);
+                    if let Some(name) = name.as_ref().filter(|_| named) {
+                        columns.push(name.clone());
+                    }
+                    if options.line_numbers {
+                        columns.push((index + 1).to_string());
+                    }
+                    columns.push(format_number(*width, options.group_digits.as_deref()));
+                    output.push_str(&columns.join(separator));
+                    output.push('\n');
+                }
+            }
+            Ok(output)
+        }
+        OutputFormat::Json => {
+            let files: Vec<Value> = inputs.iter()
+                .map(|(name, widths)| json!({ "filename": name, "line_widths": widths }))
+                .collect();
+            json_text(&Value::Array(files), options)
+        }
+        OutputFormat::Ndjson => Ok(inputs.iter()
+            .flat_map(|(name, widths)| widths.iter().enumerate().map(move |(index, width)| {
+                format!("{}\n", json!({ "filename": name, "line": index + 1, "width": width }))
+            }))
+            .collect()),
+        OutputFormat::Prometheus => Err(WcError::invalid_argument("--per-line can't be used with --format prometheus")),
+        OutputFormat::Msgpack | OutputFormat::Cbor | OutputFormat::Parquet => Err(binary_format_error(&options.format)),
     }
 }
 
@@ -183,7 +1294,8 @@ pub fn format_results(results: &[WcCounter], cli: &Cli) -> WcResult<String> {
 mod printer_tests {
     use super::*;
     use crate::counter::WcCounter;
-    use crate::parser::{Cli, OutputFormat};
+    use crate::parser::{Cli, ColorChoice, OutputFormat};
+    use std::time::Duration;
 
     fn create_test_counter() -> WcCounter {
         WcCounter {
@@ -193,6 +1305,7 @@ mod printer_tests {
             chars: 40,
             max_line_length: 50,
             filename: Some("test.txt".to_string()),
+            ..WcCounter::default()
         }
     }
 
@@ -206,7 +1319,7 @@ mod printer_tests {
             ..Cli::default()
         };
         
-        let output = build_output(&[counter], &cli, PlainFormatter);
+        let output = build_output(&[counter], &cli.format_options(), PlainFormatter::default());
         assert_eq!(output.trim(), "10 20 30 test.txt");
     }
 
@@ -220,6 +1333,7 @@ mod printer_tests {
             chars: 20,
             max_line_length: 25,
             filename: Some("test2.txt".to_string()),
+            ..WcCounter::default()
         };
         
         let cli = Cli {
@@ -228,7 +1342,7 @@ mod printer_tests {
             ..Cli::default()
         };
         
-        let output = build_output(&[counter1, counter2], &cli, PlainFormatter);
+        let output = build_output(&[counter1, counter2], &cli.format_options(), PlainFormatter::default());
         let lines: Vec<&str> = output.trim().lines().collect();
         
         assert_eq!(lines.len(), 3);
@@ -247,7 +1361,7 @@ mod printer_tests {
             ..Cli::default()
         };
         
-        let output = build_output(&[counter], &cli, HumanFormatter);
+        let output = build_output(&[counter], &cli.format_options(), HumanFormatter::default());
         assert!(output.contains("lines: 10"));
         assert!(output.contains("words: 20"));
         assert!(output.contains("in test.txt"));
@@ -262,8 +1376,483 @@ mod printer_tests {
             ..Cli::default()
         };
         
-        let output = format_json(&[counter], &cli).unwrap();
+        let output = format_json(&[counter], &[], &[], &cli.format_options()).unwrap();
         assert!(output.contains("\"lines\": 10"));
         assert!(output.contains("\"filename\": \"test.txt\""));
     }
+
+    #[test]
+    fn test_format_extra_metrics() {
+        let counter = WcCounter { records: Some(7), ..create_test_counter() };
+        let cli = Cli {
+            lines: true,
+            ..Cli::default()
+        };
+
+        let output = build_output(std::slice::from_ref(&counter), &cli.format_options(), PlainFormatter::default());
+        assert_eq!(output.trim(), "10 7 test.txt");
+
+        let output = format_json(&[counter], &[], &[], &cli.format_options()).unwrap();
+        assert!(output.contains("\"records\": 7"));
+    }
+
+    #[test]
+    fn test_format_quiet() {
+        let cli = Cli {
+            lines: true,
+            quiet: true,
+            ..Cli::default()
+        };
+
+        let output = build_output(&[create_test_counter(), create_test_counter()], &cli.format_options(), PlainFormatter::default());
+        assert_eq!(output, "10\n10\n");
+    }
+
+    #[test]
+    fn test_format_no_total() {
+        let cli = Cli {
+            lines: true,
+            no_total: true,
+            ..Cli::default()
+        };
+        let results = [create_test_counter(), create_test_counter()];
+
+        let output = build_output(&results, &cli.format_options(), PlainFormatter::default());
+        assert_eq!(output, "10 test.txt\n10 test.txt\n");
+
+        let json = format_json(&results, &[], &[], &Cli { format: OutputFormat::Json, ..cli }.format_options()).unwrap();
+        assert!(!json.contains("total"));
+    }
+
+    #[test]
+    fn test_format_filename_style() {
+        let counter = WcCounter {
+            filename: Some("src/lib.rs".to_string()),
+            ..create_test_counter()
+        };
+        let output = |filename_style| {
+            let cli = Cli { lines: true, filename_style, ..Cli::default() };
+            build_output(std::slice::from_ref(&counter), &cli.format_options(), PlainFormatter::default())
+        };
+
+        assert_eq!(output(FilenameStyle::Path), "10 src/lib.rs\n");
+        assert_eq!(output(FilenameStyle::Basename), "10 lib.rs\n");
+        assert_eq!(output(FilenameStyle::None), "10\n");
+
+        let absolute = output(FilenameStyle::Absolute);
+        assert!(absolute.starts_with("10 /") && absolute.ends_with("/src/lib.rs\n"), "{absolute}");
+    }
+
+    #[test]
+    fn test_format_aggregates() {
+        let cli = Cli {
+            lines: true,
+            aggregate: vec![Aggregate::Max, Aggregate::Mean],
+            ..Cli::default()
+        };
+        let results = [create_test_counter(), WcCounter { lines: 5, ..create_test_counter() }];
+
+        let output = build_output(&results, &cli.format_options(), PlainFormatter::default());
+        assert_eq!(output, "10 test.txt\n5 test.txt\n15 total\n10 max\n8 mean\n");
+
+        let json = format_json(&results, &[], &[], &cli.format_options()).unwrap();
+        assert!(json.contains("\"type\": \"mean\""));
+
+        let summary = Cli { lines: true, aggregate: vec![Aggregate::Max], summary_only: true, ..Cli::default() };
+        let output = build_output(&results, &summary.format_options(), PlainFormatter::default());
+        assert_eq!(output, "15 total\n10 max\n5 min\n8 mean\n8 median\n");
+
+        // Extra metrics are summarised alongside the counts
+        let records = [
+            WcCounter { records: Some(3), ..create_test_counter() },
+            WcCounter { lines: 5, records: Some(6), ..create_test_counter() },
+        ];
+        let output = build_output(&records, &summary.format_options(), PlainFormatter::default());
+        assert_eq!(output, "15 9 total\n10 6 max\n5 3 min\n8 5 mean\n8 5 median\n");
+    }
+
+    #[test]
+    fn test_format_warn() {
+        let cli = Cli {
+            lines: true,
+            max_line_length: true,
+            warn: vec!["lines>5".parse().unwrap(), "max-line-length>60".parse().unwrap()],
+            color: ColorChoice::Never,
+            ..Cli::default()
+        };
+        let results = [create_test_counter(), WcCounter { lines: 5, ..create_test_counter() }];
+
+        let output = build_output(&results, &cli.format_options(), PlainFormatter::default());
+        assert_eq!(output, "10! 50 test.txt\n5 50 test.txt\n15! 50 total\n");
+
+        let colored = FormatOptions { color: true, ..cli.format_options() };
+        let output = build_output(&results[..1], &colored, HumanFormatter::default());
+        assert_eq!(output, "\x1b[33mlines: 10\x1b[0m 50 max line length in test.txt\n");
+    }
+
+    #[test]
+    fn test_format_percent() {
+        let cli = Cli {
+            lines: true,
+            percent: true,
+            ..Cli::default()
+        };
+        let results = [
+            create_test_counter(),
+            WcCounter { lines: 30, filename: Some("big.txt".to_string()), ..WcCounter::default() },
+        ];
+
+        let output = build_output(&results, &cli.format_options(), PlainFormatter::default());
+        assert_eq!(output, "10 25.0% test.txt\n30 75.0% big.txt\n40 total\n");
+
+        let output = build_output(&results, &cli.format_options(), HumanFormatter::default());
+        assert!(output.starts_with("lines: 10 25.0% of total in test.txt\n"));
+
+        let json: Value = serde_json::from_str(&format_json(&results, &[], &[], &cli.format_options()).unwrap()).unwrap();
+        assert_eq!(json[1]["percent"], json!(75.0));
+        assert!(json[2].get("percent").is_none());
+    }
+
+    #[test]
+    fn test_group_digits() {
+        assert_eq!(group_digits(0, ","), "0");
+        assert_eq!(group_digits(999, ","), "999");
+        assert_eq!(group_digits(1234, ","), "1,234");
+        assert_eq!(group_digits(1234567, "."), "1.234.567");
+        assert_eq!(group_digits(123456, "\u{202f}"), "123\u{202f}456");
+    }
+
+    #[test]
+    fn test_format_group_digits() {
+        let cli = Cli {
+            lines: true,
+            bytes: true,
+            group_digits: Some(",".to_string()),
+            ..Cli::default()
+        };
+        let counter = WcCounter { bytes: 1234567, ..create_test_counter() };
+
+        let output = build_output(std::slice::from_ref(&counter), &cli.format_options(), PlainFormatter::new(&cli.format_options()));
+        assert_eq!(output, "10 1,234,567 test.txt\n");
+
+        let output = build_output(std::slice::from_ref(&counter), &cli.format_options(), HumanFormatter::new(&cli.format_options()));
+        assert!(output.contains("bytes: 1,234,567"));
+
+        let json = format_json(&[counter], &[], &[], &cli.format_options()).unwrap();
+        assert!(json.contains("\"bytes\": 1234567"));
+    }
+
+    #[test]
+    fn test_format_prometheus() {
+        let cli = Cli {
+            lines: true,
+            max_line_length: true,
+            format: OutputFormat::Prometheus,
+            ..Cli::default()
+        };
+        let results = [
+            WcCounter { records: Some(2), ..create_test_counter() },
+            WcCounter {
+                lines: 3,
+                records: Some(0),
+                filename: Some("odd \"name\".txt".to_string()),
+                ..WcCounter::default()
+            },
+        ];
+
+        let output = format(&results, &cli.format_options()).unwrap();
+        assert_eq!(output, concat!(
+            "# HELP rs_wc_lines_total Number of lines counted.\n",
+            "# TYPE rs_wc_lines_total counter\n",
+            "rs_wc_lines_total{file=\"test.txt\"} 10\n",
+            "rs_wc_lines_total{file=\"odd \\\"name\\\".txt\"} 3\n",
+            "# HELP rs_wc_max_line_length Length of the longest line.\n",
+            "# TYPE rs_wc_max_line_length gauge\n",
+            "rs_wc_max_line_length{file=\"test.txt\"} 50\n",
+            "rs_wc_max_line_length{file=\"odd \\\"name\\\".txt\"} 0\n",
+            "# HELP rs_wc_records The records metric.\n",
+            "# TYPE rs_wc_records gauge\n",
+            "rs_wc_records{file=\"test.txt\"} 2\n",
+            "rs_wc_records{file=\"odd \\\"name\\\".txt\"} 0\n",
+        ));
+    }
+
+    #[test]
+    fn test_prometheus_labels_are_unique() {
+        let cli = Cli { lines: true, format: OutputFormat::Prometheus, ..Cli::default() };
+        let results = [create_test_counter(), create_test_counter(), WcCounter::default(), WcCounter::default()];
+        assert_eq!(prometheus_labels(&results, &cli.format_options()), [
+            "{file=\"test.txt\"}",
+            "{file=\"test.txt\",occurrence=\"2\"}",
+            "{file=\"-\"}",
+            "{file=\"-\",occurrence=\"2\"}",
+        ]);
+
+        let cli = Cli { filename_style: FilenameStyle::None, ..cli };
+        assert_eq!(prometheus_labels(&results[..2], &cli.format_options()), ["", "{occurrence=\"2\"}"]);
+    }
+
+    #[test]
+    fn test_format_compat() {
+        let results = [
+            WcCounter { lines: 3, words: 12, bytes: 80, ..create_test_counter() },
+            WcCounter { lines: 40, words: 300, bytes: 2000, filename: Some("b.txt".into()), ..WcCounter::default() },
+        ];
+
+        let cli = Cli { compat: Some(Compat::Gnu), ..Cli::default() };
+        assert_eq!(format(&results, &cli.format_options()).unwrap(), concat!(
+            "   3   12   80 test.txt\n",
+            "  40  300 2000 b.txt\n",
+            "  43  312 2080 total\n",
+        ));
+
+        let stdin = [WcCounter { lines: 1, words: 2, bytes: 4, ..WcCounter::default() }];
+        assert_eq!(format(&stdin, &cli.format_options()).unwrap(), "      1       2       4\n");
+
+        let cli = Cli { compat: Some(Compat::Bsd), ..Cli::default() };
+        assert_eq!(format(&results[..1], &cli.format_options()).unwrap(), "       3      12      80 test.txt\n");
+
+        let cli = Cli { compat: Some(Compat::Posix), ..Cli::default() };
+        assert_eq!(format(&results[..1], &cli.format_options()).unwrap(), "3 12 80 test.txt\n");
+    }
+
+    #[test]
+    fn test_format_time() {
+        let cli = Cli { lines: true, time: true, format: OutputFormat::Json, ..Cli::default() };
+        let counter = WcCounter { duration: Some(Duration::from_micros(1234)), ..create_test_counter() };
+        let json: Value = serde_json::from_str(&format(&[counter.clone(), counter], &cli.format_options()).unwrap()).unwrap();
+        assert_eq!(json["results"][0]["duration_ms"], 1.23);
+        assert!(json["results"][2]["duration_ms"].is_f64());
+
+        let cli = Cli { lines: true, time: true, ..Cli::default() };
+        let counter = WcCounter { duration: Some(Duration::from_millis(5)), ..create_test_counter() };
+        let output = format(&[counter], &cli.format_options()).unwrap();
+        let rows: Vec<&str> = output.lines().collect();
+        assert_eq!(rows[0], "10 5.0 test.txt");
+        assert!(rows[1].starts_with("10 ") && rows[1].ends_with(" total"), "{}", output);
+    }
+
+    #[test]
+    fn test_format_json_envelope() {
+        let cli = Cli::parse_args_from(["rs-wc", "-lw", "-f", "json"]);
+        let json: Value = serde_json::from_str(&format(&[create_test_counter()], &cli.format_options()).unwrap()).unwrap();
+        assert_eq!(json["schema_version"], JSON_SCHEMA_VERSION);
+        assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(json["modes"], json!(["lines", "words"]));
+        assert!(json["timestamp"].as_str().unwrap().ends_with('Z'));
+        assert_eq!(json["results"][0]["filename"], "test.txt");
+
+        let cli = Cli { json_array: true, ..cli };
+        let json: Value = serde_json::from_str(&format(&[create_test_counter()], &cli.format_options()).unwrap()).unwrap();
+        assert_eq!(json[0]["lines"], 10);
+    }
+
+    #[test]
+    fn test_format_json_compact() {
+        let cli = Cli::parse_args_from(["rs-wc", "-l", "-f", "json", "--json-array", "--compact"]);
+        let output = format(&[create_test_counter()], &cli.format_options()).unwrap();
+        assert_eq!(output, "[{\"filename\":\"test.txt\",\"lines\":10}]\n");
+    }
+
+    #[test]
+    fn test_render_binary_formats() {
+        let json = Cli::parse_args_from(["rs-wc", "-l", "-f", "json", "--json-array"]).format_options();
+        let msgpack = FormatOptions { format: OutputFormat::Msgpack, ..json.clone() };
+        let cbor = FormatOptions { format: OutputFormat::Cbor, ..json.clone() };
+        let expected = json_document(json_entries(&[create_test_counter()], &[], &[], &json), &json);
+
+        let rendered = render_results(&[create_test_counter()], &[], &[], &msgpack);
+        match cfg!(feature = "msgpack") {
+            true => assert_eq!(rendered.unwrap(), encode_binary(&expected, &OutputFormat::Msgpack).unwrap()),
+            false => assert!(rendered.unwrap_err().to_string().contains("--features msgpack")),
+        }
+        let rendered = render_results(&[create_test_counter()], &[], &[], &cbor);
+        match cfg!(feature = "cbor") {
+            true => assert_eq!(rendered.unwrap(), encode_binary(&expected, &OutputFormat::Cbor).unwrap()),
+            false => assert!(rendered.unwrap_err().to_string().contains("--features cbor")),
+        }
+        assert!(format(&[create_test_counter()], &cbor).is_err());
+    }
+
+    #[cfg(all(feature = "msgpack", feature = "cbor"))]
+    #[test]
+    fn test_binary_formats_decode_to_json() {
+        let value = json!({"filename": "test.txt", "lines": 10, "ratio": 0.5, "hash": null});
+        let msgpack = encode_binary(&value, &OutputFormat::Msgpack).unwrap();
+        assert_eq!(rmp_serde::from_slice::<Value>(&msgpack).unwrap(), value);
+        let cbor = encode_binary(&value, &OutputFormat::Cbor).unwrap();
+        assert_eq!(ciborium::from_reader::<Value, _>(cbor.as_slice()).unwrap(), value);
+    }
+
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!(rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(UNIX_EPOCH + Duration::from_secs(951_782_400)), "2000-02-29T00:00:00Z");
+        assert_eq!(rfc3339(UNIX_EPOCH + Duration::from_secs(1_735_689_599)), "2024-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn test_format_ndjson() {
+        let cli = Cli { lines: true, format: OutputFormat::Ndjson, ..Cli::default() };
+        let output = format(&[create_test_counter(), create_test_counter()], &cli.format_options()).unwrap();
+        assert_eq!(output, concat!(
+            "{\"filename\":\"test.txt\",\"lines\":10}\n",
+            "{\"filename\":\"test.txt\",\"lines\":10}\n",
+            "{\"lines\":20,\"type\":\"total\"}\n",
+        ));
+
+        let failure = Failure { path: "gone.txt".into(), message: "File not found: gone.txt".into() };
+        let output = format_results_with_issues(&[create_test_counter()], &[], &[failure], &cli.format_options()).unwrap();
+        assert_eq!(output, concat!(
+            "{\"filename\":\"test.txt\",\"lines\":10}\n",
+            "{\"error\":\"File not found: gone.txt\",\"filename\":\"gone.txt\"}\n",
+        ));
+    }
+
+    #[test]
+    fn test_format_without_cli() {
+        let options = FormatOptions { modes: vec![CountMode::Words], ..FormatOptions::default() };
+        assert_eq!(format(&[create_test_counter()], &options).unwrap(), "20 test.txt\n");
+
+        let always = FormatOptions { totals: Totals::Always, ..options.clone() };
+        assert_eq!(format(&[create_test_counter()], &always).unwrap(), "20 test.txt\n20 total\n");
+
+        let never = FormatOptions { totals: Totals::Never, max_line_length: true, ..options };
+        let output = format(&[create_test_counter(), create_test_counter()], &never).unwrap();
+        assert_eq!(output, "20 50 test.txt\n20 50 test.txt\n");
+    }
+
+    #[test]
+    fn test_format_per_line() {
+        let result = |name: &str, widths: Vec<usize>| WcCounter {
+            filename: Some(name.into()),
+            line_widths: Some(widths),
+            ..WcCounter::default()
+        };
+        let single = [result("a.txt", vec![3, 0, 12])];
+        let options = FormatOptions::default();
+        assert_eq!(format_per_line(&single, &options).unwrap(), "3\n0\n12\n");
+
+        let both = [result("a.txt", vec![3]), result("b.txt", vec![7, 1])];
+        let numbered = FormatOptions { line_numbers: true, ..options.clone() };
+        assert_eq!(format_per_line(&both, &numbered).unwrap(), "a.txt 1 3\nb.txt 1 7\nb.txt 2 1\n");
+
+        let ndjson = FormatOptions { format: OutputFormat::Ndjson, ..options.clone() };
+        let lines: Vec<String> = format_per_line(&single, &ndjson).unwrap().lines().map(String::from).collect();
+        assert_eq!(lines[2], r#"{"filename":"a.txt","line":3,"width":12}"#);
+
+        let json = FormatOptions { format: OutputFormat::Json, compact: true, ..options.clone() };
+        assert_eq!(format_per_line(&both, &json).unwrap(),
+            r#"[{"filename":"a.txt","line_widths":[3]},{"filename":"b.txt","line_widths":[7,1]}]"#);
+
+        let prometheus = FormatOptions { format: OutputFormat::Prometheus, ..options };
+        assert!(format_per_line(&single, &prometheus).is_err());
+    }
+
+    #[test]
+    fn test_format_histograms() {
+        let result = |name: &str, text: &[u8]| WcCounter {
+            filename: Some(name.into()),
+            line_stats: Some(LineStats::from_bytes(text, b'\n')),
+            ..WcCounter::default()
+        };
+        let single = [result("a.txt", b"a\nbb\n\nxxxxxxx\n")];
+        let options = FormatOptions::default();
+        let chart = format_histograms(&single, 2, &options).unwrap();
+        assert_eq!(chart.lines().collect::<Vec<_>>(), [
+            "",
+            "line lengths in a.txt:",
+            &format!("0-3 | {:<40} 3", "#".repeat(40)),
+            &format!("4-7 | {:<40} 1", "#".repeat(14)),
+        ]);
+
+        let both = [single[0].clone(), result("b.txt", b"abc\n")];
+        assert!(format_histograms(&both, 2, &options).unwrap().contains("line lengths in total:"));
+        let no_total = FormatOptions { totals: Totals::Never, ..options.clone() };
+        assert!(!format_histograms(&both, 2, &no_total).unwrap().contains("total"));
+
+        let json = FormatOptions { format: OutputFormat::Json, ..options };
+        assert!(format_histograms(&single, 2, &json).is_err());
+    }
+
+    #[test]
+    fn test_stream_printer() {
+        let options = FormatOptions { modes: vec![CountMode::Lines], ..FormatOptions::default() };
+        let mut printer = StreamPrinter::new(&options).unwrap();
+        let mut output = printer.row(create_test_counter());
+        output += &printer.row(WcCounter { lines: 5, ..create_test_counter() });
+        output += &printer.finish(&[]);
+        assert_eq!(output, "10 test.txt\n5 test.txt\n15 total\n");
+
+        let options = FormatOptions { format: OutputFormat::Json, ..options };
+        let mut printer = StreamPrinter::new(&options).unwrap();
+        let mut output = printer.row(create_test_counter());
+        output += &printer.row(create_test_counter());
+        output += &printer.finish(&[]);
+        let json: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(json["schema_version"], JSON_SCHEMA_VERSION);
+        assert!(json["elapsed_ms"].is_number());
+        assert_eq!(json["results"].as_array().unwrap().len(), 3);
+        assert_eq!(json["results"][2]["type"], "total");
+
+        let empty: Value = serde_json::from_str(&StreamPrinter::new(&options).unwrap().finish(&[])).unwrap();
+        assert_eq!(empty["results"], json!([]));
+
+        // --compact puts the whole document on one line, as without --stream
+        let compact = FormatOptions { compact: true, ..options.clone() };
+        let mut printer = StreamPrinter::new(&compact).unwrap();
+        let output = printer.row(create_test_counter()) + &printer.finish(&[]);
+        assert_eq!(output.lines().count(), 1);
+        let json: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(json["results"][0]["lines"], 10);
+
+        let options = FormatOptions { json_array: true, ..options };
+
+        let mut printer = StreamPrinter::new(&options).unwrap();
+        let failure = Failure { path: "gone.txt".into(), message: "File not found: gone.txt".into() };
+        let output = printer.failure(&failure) + &printer.finish(&[]);
+        let json: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(json, json!([{"filename": "gone.txt", "error": "File not found: gone.txt"}]));
+
+        let empty: Value = serde_json::from_str(&StreamPrinter::new(&options).unwrap().finish(&[])).unwrap();
+        assert_eq!(empty, json!([]));
+
+        // Laid out as the same document would be without --stream
+        let mut printer = StreamPrinter::new(&options).unwrap();
+        let output = printer.row(create_test_counter()) + &printer.row(create_test_counter()) + &printer.finish(&[]);
+        let json: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(output, to_string_pretty(&json).unwrap() + "\n");
+
+        let options = FormatOptions { format: OutputFormat::Prometheus, ..FormatOptions::default() };
+        assert!(StreamPrinter::new(&options).is_err());
+    }
+
+    #[test]
+    fn test_format_diff() {
+        let old = create_test_counter();
+        let new = WcCounter { lines: 15, ..create_test_counter() };
+        let cli = Cli {
+            lines: true,
+            words: true,
+            ..Cli::default()
+        };
+
+        let output = format_diff(&CountDiff::new(old, new), &cli.format_options()).unwrap();
+        assert_eq!(output, "lines 10 15 +5 +50.0%\nwords 20 20 +0 +0.0%\n");
+    }
+
+    #[test]
+    fn test_max_line_length_alone_lists_default_counts() {
+        let options = Cli::parse_args_from(["rs-wc", "-L", "-f", "json"]).format_options();
+        let json: Value = serde_json::from_str(&format(&[create_test_counter()], &options).unwrap()).unwrap();
+        assert_eq!(json["modes"], json!(["lines", "words", "bytes", "max-line-length"]));
+        assert_eq!(json["results"][0].get("lines"), None);
+
+        let old = create_test_counter();
+        let new = WcCounter { lines: 15, ..create_test_counter() };
+        let options = FormatOptions { format: OutputFormat::Plain, ..options };
+        let output = format_diff(&CountDiff::new(old, new), &options).unwrap();
+        assert_eq!(output.lines().map(|line| line.split(' ').next().unwrap()).collect::<Vec<_>>(),
+                   ["lines", "words", "bytes", "max-line-length"]);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,127 @@
+use std::{path::Path, time::Duration};
+
+use rusqlite::{params, Connection, TransactionBehavior};
+use serde_json::Value;
+
+use crate::{
+    counter::WcCounter,
+    error::{WcError, WcResult},
+    printer::{result_json, run_timestamp, FormatOptions},
+};
+
+// Counts that have a column of their own; other metrics go in `metrics`
+const COLUMNS: [&str; 5] = ["lines", "words", "bytes", "chars", "max_line_length"];
+
+// How long to wait for another run writing to the same database
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS counts (
+        run_id INTEGER NOT NULL,
+        timestamp TEXT NOT NULL,
+        filename TEXT,
+        lines INTEGER,
+        words INTEGER,
+        bytes INTEGER,
+        chars INTEGER,
+        max_line_length INTEGER,
+        metrics TEXT
+    );
+    CREATE INDEX IF NOT EXISTS counts_filename ON counts (filename, run_id);
+";
+
+/// Adds a row per result to the `counts` table of the database at `path`,
+/// creating both if needed. Every row of one run shares a `run_id`, one more
+/// than the last run's, and the time the run started. Counts that aren't
+/// among `options.modes` are NULL; other metrics are a JSON object in
+/// `metrics`.
+///
+/// Returns the run id.
+pub fn record_results(path: &Path, results: &[WcCounter], options: &FormatOptions) -> WcResult<i64> {
+    let name = path.display().to_string();
+    let database_error = |e: rusqlite::Error| WcError::Database(format!("{}: {}", name, e));
+
+    let mut connection = Connection::open(path).map_err(database_error)?;
+    connection.busy_timeout(BUSY_TIMEOUT).map_err(database_error)?;
+    connection.execute_batch(SCHEMA).map_err(database_error)?;
+
+    // Taking the write lock up front keeps two runs from reading the same
+    // last run_id
+    let transaction = connection.transaction_with_behavior(TransactionBehavior::Immediate).map_err(database_error)?;
+    let run_id: i64 = transaction
+        .query_row("SELECT COALESCE(MAX(run_id), 0) + 1 FROM counts", [], |row| row.get(0))
+        .map_err(database_error)?;
+    let timestamp = run_timestamp(options);
+
+    {
+        let mut insert = transaction.prepare(
+            "INSERT INTO counts (run_id, timestamp, filename, lines, words, bytes, chars, max_line_length, metrics)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        ).map_err(database_error)?;
+
+        for result in results {
+            let mut metrics = result_json(result, options);
+            metrics.remove("filename");
+            let [lines, words, bytes, chars, max_line_length] =
+                COLUMNS.map(|column| metrics.remove(column).as_ref().and_then(Value::as_i64));
+            let metrics = (!metrics.is_empty()).then(|| Value::Object(metrics).to_string());
+
+            insert.execute(params![
+                run_id, timestamp, result.filename, lines, words, bytes, chars, max_line_length, metrics
+            ]).map_err(database_error)?;
+        }
+    }
+
+    transaction.commit().map_err(database_error)?;
+    Ok(run_id)
+}
+
+#[cfg(test)]
+mod sqlite_tests {
+    use super::*;
+    use crate::parser::Cli;
+
+    #[test]
+    fn test_record_results() {
+        let path = std::env::temp_dir().join(format!("rs-wc-sqlite-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let options = Cli::parse_args_from(["rs-wc", "-l", "--hash", "sha256"]).format_options();
+        let result = WcCounter {
+            lines: 3,
+            words: 5,
+            filename: Some("a.txt".into()),
+            hash: Some("ab12".into()),
+            ..WcCounter::new()
+        };
+
+        assert_eq!(record_results(&path, std::slice::from_ref(&result), &options).unwrap(), 1);
+        assert_eq!(record_results(&path, &[result], &options).unwrap(), 2);
+
+        let connection = Connection::open(&path).unwrap();
+        let row: (i64, String, Option<i64>, Option<i64>, String) = connection.query_row(
+            "SELECT run_id, filename, lines, words, metrics FROM counts ORDER BY run_id DESC LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+        ).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(row, (2, "a.txt".to_string(), Some(3), None, r#"{"sha256":"ab12"}"#.to_string()));
+    }
+
+    #[test]
+    fn test_concurrent_runs_get_distinct_ids() {
+        let path = std::env::temp_dir().join(format!("rs-wc-sqlite-concurrent-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut ids: Vec<i64> = std::thread::scope(|scope| {
+            let runs: Vec<_> = (0..8)
+                .map(|_| scope.spawn(|| record_results(&path, &[WcCounter::new()], &FormatOptions::default()).unwrap()))
+                .collect();
+            runs.into_iter().map(|run| run.join().unwrap()).collect()
+        });
+        std::fs::remove_file(&path).unwrap();
+
+        ids.sort_unstable();
+        assert_eq!(ids, (1..=8).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,218 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    widgets::{Block, Paragraph, Row, Table, TableState},
+    DefaultTerminal, Frame,
+};
+
+use crate::cache::CountCache;
+use crate::counter::WcCounter;
+use crate::error::WcResult;
+use crate::parser::{Cli, CountMode};
+use crate::walker::{self, WalkOptions};
+
+// How often files are checked for changes
+const REFRESH: Duration = Duration::from_secs(1);
+
+/// What the interface shows: the latest results, how they're sorted and
+/// filtered, and the selected row.
+pub struct TuiState {
+    modes: Vec<CountMode>,
+    results: Vec<WcCounter>,
+    // `None` sorts by filename
+    sort: Option<CountMode>,
+    descending: bool,
+    filter: String,
+    editing_filter: bool,
+    table: TableState,
+}
+
+impl TuiState {
+    pub fn new(modes: Vec<CountMode>) -> Self {
+        Self {
+            modes,
+            results: Vec::new(),
+            sort: None,
+            descending: false,
+            filter: String::new(),
+            editing_filter: false,
+            table: TableState::default().with_selected(Some(0)),
+        }
+    }
+
+    pub fn set_results(&mut self, results: Vec<WcCounter>) {
+        self.results = results;
+    }
+
+    /// Results whose filename contains the filter, in display order.
+    pub fn visible(&self) -> Vec<&WcCounter> {
+        let mut rows: Vec<&WcCounter> = self.results.iter()
+            .filter(|result| result.filename.as_deref().unwrap_or("-").contains(&self.filter))
+            .collect();
+
+        match &self.sort {
+            Some(mode) => rows.sort_by_key(|result| result.count(mode)),
+            None => rows.sort_by(|a, b| a.filename.cmp(&b.filename)),
+        }
+        if self.descending {
+            rows.reverse();
+        }
+        rows
+    }
+
+    /// Applies a key press; returns `false` once the user asks to quit.
+    pub fn handle_key(&mut self, key: KeyCode) -> bool {
+        if self.editing_filter {
+            match key {
+                KeyCode::Enter | KeyCode::Esc => self.editing_filter = false,
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Char(c) => self.filter.push(c),
+                _ => {}
+            }
+            self.table.select(Some(0));
+            return true;
+        }
+
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('/') => self.editing_filter = true,
+            KeyCode::Char('s') | KeyCode::Tab => self.sort = self.next_sort(),
+            KeyCode::Char('r') => self.descending = !self.descending,
+            KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+            _ => {}
+        }
+        true
+    }
+
+    // Cycles filename -> each count column -> filename
+    fn next_sort(&self) -> Option<CountMode> {
+        match &self.sort {
+            None => self.modes.first().cloned(),
+            Some(current) => self.modes.iter()
+                .skip_while(|mode| *mode != current)
+                .nth(1)
+                .cloned(),
+        }
+    }
+
+    fn header_label(&self, name: &str, column: Option<&CountMode>) -> String {
+        match (column == self.sort.as_ref(), self.descending) {
+            (true, true) => format!("{} ▼", name),
+            (true, false) => format!("{} ▲", name),
+            (false, _) => name.to_string(),
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [table_area, filter_area] = Layout::vertical([Constraint::Min(0), Constraint::Length(3)])
+            .areas(frame.area());
+
+        let mut header: Vec<String> = self.modes.iter()
+            .map(|mode| self.header_label(&format!("{:?}", mode).to_lowercase(), Some(mode)))
+            .collect();
+        header.push(self.header_label("file", None));
+
+        let rows: Vec<Row> = self.visible().into_iter()
+            .map(|result| {
+                let mut cells: Vec<String> = self.modes.iter()
+                    .map(|mode| result.count(mode).to_string())
+                    .collect();
+                cells.push(result.filename.clone().unwrap_or_else(|| "-".to_string()));
+                Row::new(cells)
+            })
+            .collect();
+
+        let mut widths = vec![Constraint::Length(12); self.modes.len()];
+        widths.push(Constraint::Fill(1));
+
+        let table = Table::new(rows, widths)
+            .header(Row::new(header).style(Style::new().add_modifier(Modifier::BOLD)))
+            .block(Block::bordered().title(" rs-wc  s: sort  r: reverse  /: filter  q: quit "))
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, table_area, &mut self.table);
+
+        let title = if self.editing_filter { " filter (Enter to finish) " } else { " filter " };
+        frame.render_widget(Paragraph::new(self.filter.as_str()).block(Block::bordered().title(title)), filter_area);
+    }
+}
+
+/// Shows live counts for the files under `roots` until the user quits.
+pub fn run(roots: Vec<PathBuf>, cli: &Cli) -> WcResult<()> {
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, roots, cli);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, roots: Vec<PathBuf>, cli: &Cli) -> WcResult<()> {
+    let walk_options = WalkOptions { recursive: true, ..cli.walk_options() };
+    let modes = cli.get_count_modes();
+    let count_options = cli.count_options();
+    let mut cache = CountCache::new();
+    let mut state = TuiState::new(modes.clone());
+
+    loop {
+        // Files that vanish or can't be read between refreshes drop out
+        let files = walker::expand_paths(&roots, &walk_options)?.files;
+        state.set_results(files.iter()
+            .filter_map(|file| cache.count(file, &modes, &count_options).ok())
+            .collect());
+
+        let refreshed = Instant::now();
+        loop {
+            terminal.draw(|frame| state.draw(frame))?;
+
+            let timeout = REFRESH.saturating_sub(refreshed.elapsed());
+            if timeout.is_zero() {
+                break;
+            }
+            if event::poll(timeout)?
+                && let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && !state.handle_key(key.code)
+            {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tui_tests {
+    use super::*;
+
+    fn names(state: &TuiState) -> Vec<&str> {
+        state.visible().iter().map(|r| r.filename.as_deref().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_sort_and_filter() {
+        let mut state = TuiState::new(vec![CountMode::Lines, CountMode::Words]);
+        state.set_results(vec![WcCounter::named("b.rs", 1), WcCounter::named("a.md", 30), WcCounter::named("c.rs", 20)]);
+        assert_eq!(names(&state), ["a.md", "b.rs", "c.rs"]);
+
+        state.handle_key(KeyCode::Char('s'));
+        state.handle_key(KeyCode::Char('r'));
+        assert_eq!(names(&state), ["a.md", "c.rs", "b.rs"]);
+
+        for key in [KeyCode::Char('/'), KeyCode::Char('.'), KeyCode::Char('r'), KeyCode::Enter] {
+            assert!(state.handle_key(key));
+        }
+        assert_eq!(names(&state), ["c.rs", "b.rs"]);
+
+        // Words, then back to filenames
+        state.handle_key(KeyCode::Tab);
+        state.handle_key(KeyCode::Tab);
+        assert_eq!(state.sort, None);
+        assert!(!state.handle_key(KeyCode::Char('q')));
+    }
+}
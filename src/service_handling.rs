@@ -0,0 +1,3 @@
+#[cfg(unix)]
+pub mod daemon;
+pub mod server;
\ No newline at end of file
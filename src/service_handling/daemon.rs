@@ -0,0 +1,174 @@
+use std::{
+    ffi::CString,
+    fs,
+    io::{BufRead, BufReader, Write},
+    os::unix::{
+        ffi::OsStrExt,
+        fs::FileTypeExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::Path,
+    sync::OnceLock,
+    thread,
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+
+use crate::counter::{count_bytes_with, count_file_with, WcCounter};
+use crate::error::{WcError, WcResult};
+use crate::parser::Cli;
+use crate::printer;
+
+/// Counts one request line. Requests are JSON objects naming a file,
+/// `{"path": "notes.md"}`, or carrying the text itself, `{"text": "...",
+/// "filename": "notes.md"}`, so unsaved editor buffers can be counted too.
+pub fn handle_request(line: &str, cli: &Cli) -> Value {
+    match count_request(line, cli) {
+        Ok(result) => Value::Object(printer::result_json(&result, &cli.format_options())),
+        Err(e) => json!({ "error": e.to_string() }),
+    }
+}
+
+fn count_request(line: &str, cli: &Cli) -> WcResult<WcCounter> {
+    let request: Value = serde_json::from_str(line)?;
+    let modes = cli.get_required_modes();
+    let options = cli.count_options();
+
+    match (request.get("path"), request.get("text")) {
+        (Some(Value::String(path)), None) => count_file_with(path, &modes, &options),
+        (None, Some(Value::String(text))) => {
+            let filename = request.get("filename").and_then(Value::as_str).map(String::from);
+            count_bytes_with(text.as_bytes(), filename, &modes, &options)
+        }
+        _ => Err(WcError::invalid_argument("expected a string \"path\" or \"text\", not both")),
+    }
+}
+
+/// Answers newline-delimited requests on one connection until the client
+/// hangs up, writing one JSON line per request.
+pub fn handle_connection(stream: UnixStream, cli: &Cli) -> WcResult<()> {
+    let mut writer = &stream;
+    for line in BufReader::new(&stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        writeln!(writer, "{}", handle_request(&line, cli))?;
+    }
+    Ok(())
+}
+
+// A socket file left behind by a daemon that is no longer running is
+// replaced; one that still accepts connections is not.
+fn remove_stale_socket(path: &Path) -> WcResult<()> {
+    let is_socket = fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_socket());
+    if is_socket && UnixStream::connect(path).is_err() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+// How long to wait after a failed accept, such as when out of file
+// descriptors, so the loop doesn't spin
+const ACCEPT_RETRY: Duration = Duration::from_millis(100);
+
+// The socket for the signal handler to remove; set once by `run`
+static SOCKET_PATH: OnceLock<CString> = OnceLock::new();
+
+extern "C" fn remove_socket_and_exit(signal: libc::c_int) {
+    // Only async-signal-safe calls are allowed here
+    if let Some(path) = SOCKET_PATH.get() {
+        unsafe { libc::unlink(path.as_ptr()) };
+    }
+    unsafe { libc::_exit(128 + signal) };
+}
+
+// Removes the socket file when the daemon is interrupted or terminated, so
+// the next start can bind it again
+fn remove_socket_on_exit(path: &Path) {
+    let Ok(path) = CString::new(path.as_os_str().as_bytes()) else { return };
+    if SOCKET_PATH.set(path).is_ok() {
+        let handler = remove_socket_and_exit as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+            unsafe { libc::signal(signal, handler) };
+        }
+    }
+}
+
+// Removes the socket file when `run` returns
+struct SocketFile<'a>(&'a Path);
+
+impl Drop for SocketFile<'_> {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(self.0);
+    }
+}
+
+/// Listens on the Unix socket at `path`, serving each client on its own
+/// thread until interrupted, then removes the socket file. A client that
+/// can't be accepted is reported and the daemon carries on.
+pub fn run(path: &Path, cli: &Cli) -> WcResult<()> {
+    remove_stale_socket(path)?;
+    let listener = UnixListener::bind(path).map_err(|e| {
+        WcError::invalid_argument(format!("cannot listen on {}: {}", path.display(), e))
+    })?;
+    let _socket = SocketFile(path);
+    remove_socket_on_exit(path);
+    eprintln!("rs-wc: listening on {}", path.display());
+
+    thread::scope(|scope| {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("rs-wc: cannot accept a connection: {}", e);
+                    thread::sleep(ACCEPT_RETRY);
+                    continue;
+                }
+            };
+            scope.spawn(move || {
+                if let Err(e) = handle_connection(stream, cli) {
+                    eprintln!("rs-wc: {}", e);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod daemon_tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_handle_request() {
+        let cli = Cli { words: true, ..Cli::default() };
+
+        let response = handle_request(r#"{"text": "one two three", "filename": "draft.md"}"#, &cli);
+        assert_eq!(response, json!({ "words": 3, "filename": "draft.md" }));
+
+        let response = handle_request(r#"{"path": "Cargo.toml"}"#, &cli);
+        assert_eq!(response["filename"], "Cargo.toml");
+
+        assert!(handle_request(r#"{"text": "a", "path": "b"}"#, &cli).get("error").is_some());
+        assert!(handle_request("not json", &cli).get("error").is_some());
+    }
+
+    #[test]
+    fn test_handle_connection() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let cli = Cli { lines: true, ..Cli::default() };
+
+        let mut writer = &client;
+        writer.write_all(b"{\"text\": \"a\\nb\\n\"}\n\n{\"text\": \"\"}\n").unwrap();
+        client.shutdown(std::net::Shutdown::Write).unwrap();
+
+        handle_connection(server, &cli).unwrap();
+
+        let mut response = String::new();
+        (&client).read_to_string(&mut response).unwrap();
+        assert_eq!(response, "{\"lines\":2}\n{\"lines\":0}\n");
+    }
+}
@@ -0,0 +1,279 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    sync::Mutex,
+    thread,
+    time::Duration,
+};
+
+use serde_json::json;
+
+use crate::cache::CountCache;
+use crate::counter::Failure;
+use crate::error::{WcError, WcResult};
+use crate::parser::Cli;
+use crate::printer;
+use crate::walker::{self, WalkOptions};
+
+// A client that stalls mid-request or stops reading the response gives up
+// its connection after this long
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+// How long to wait after a failed accept, such as when out of file
+// descriptors, so the loop doesn't spin
+const ACCEPT_RETRY: Duration = Duration::from_millis(100);
+
+/// A status code and JSON body to send back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Response { status, body: json!({ "error": message.into() }).to_string() }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+/// Answers `GET /counts` and `GET /counts/<file>` for the files under
+/// `roots`, recounting a file only when it has changed since it was last
+/// served.
+pub struct CountServer<'a> {
+    roots: Vec<PathBuf>,
+    cli: &'a Cli,
+    cache: Mutex<CountCache>,
+}
+
+impl<'a> CountServer<'a> {
+    pub fn new(roots: Vec<PathBuf>, cli: &'a Cli) -> Self {
+        Self { roots, cli, cache: Mutex::new(CountCache::new()) }
+    }
+
+    /// Serves each connection on its own thread, so a slow client never
+    /// holds up the others. A connection that can't be accepted is reported
+    /// and the server carries on.
+    pub fn run(&self, listener: TcpListener) -> WcResult<()> {
+        thread::scope(|scope| {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        eprintln!("rs-wc: cannot accept a connection: {}", e);
+                        thread::sleep(ACCEPT_RETRY);
+                        continue;
+                    }
+                };
+                scope.spawn(move || {
+                    if let Err(e) = self.handle_connection(stream) {
+                        eprintln!("rs-wc: {}", e);
+                    }
+                });
+            }
+        });
+        Ok(())
+    }
+
+    pub fn handle_connection(&self, stream: TcpStream) -> WcResult<()> {
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+        let mut reader = BufReader::new(&stream);
+
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+
+        // Headers are read and ignored; requests carry no body
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 0 && !header.trim_end().is_empty() {
+            header.clear();
+        }
+
+        let response = match request_line.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["GET", target, _] => self.handle(target),
+            [_, _, _] => Response::error(405, "only GET is supported"),
+            _ => Response::error(400, "malformed request line"),
+        };
+
+        let mut stream = &stream;
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            response.status, response.reason(), response.body.len(), response.body
+        )?;
+        stream.flush()?;
+        Ok(())
+    }
+
+    /// Routes a request target such as `/counts/src%2Fmain.rs?x=1`.
+    pub fn handle(&self, target: &str) -> Response {
+        let path = target.split('?').next().unwrap_or_default();
+
+        let requested = match path.trim_end_matches('/') {
+            "/counts" => None,
+            other => match other.strip_prefix("/counts/").map(percent_decode) {
+                Some(Some(file)) => Some(file),
+                Some(None) => return Response::error(400, "invalid percent-encoding"),
+                None => return Response::error(404, format!("no route for {}", path)),
+            },
+        };
+
+        match self.counts(requested.as_deref()) {
+            Ok(Some(body)) => Response { status: 200, body },
+            Ok(None) => Response::error(404, format!("{} is not being served", requested.unwrap_or_default())),
+            Err(e) => Response::error(500, e.to_string()),
+        }
+    }
+
+    // JSON for every served file, or just `requested`; `None` if it isn't served
+    fn counts(&self, requested: Option<&str>) -> WcResult<Option<String>> {
+        // Directories are always served as the files beneath them
+        let options = WalkOptions { recursive: true, ..self.cli.walk_options() };
+        let mut files = walker::expand_paths(&self.roots, &options)?.files;
+        if let Some(requested) = requested {
+            files.retain(|file| file.to_str() == Some(requested));
+            if files.is_empty() {
+                return Ok(None);
+            }
+        }
+
+        // A file that can't be counted is listed as an error entry, as the
+        // CLI's JSON output does, rather than failing the whole response
+        let modes = self.cli.get_required_modes();
+        let options = self.cli.count_options();
+        let mut results = Vec::with_capacity(files.len());
+        let mut failures = Vec::new();
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        for file in &files {
+            match cache.count(file, &modes, &options) {
+                Ok(result) => results.push(result),
+                Err(e) => failures.push(Failure::new(file, &e)),
+            }
+        }
+        printer::format_json(&results, &[], &failures, &self.cli.format_options()).map(Some)
+    }
+}
+
+// Decodes `%XX` escapes; `None` if an escape is malformed or the result
+// isn't UTF-8
+fn percent_decode(input: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(input.len());
+    let mut rest = input.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+/// Binds `address` and serves counts for `roots` until interrupted.
+pub fn serve(address: &str, roots: Vec<PathBuf>, cli: &Cli) -> WcResult<()> {
+    let listener = TcpListener::bind(address)
+        .map_err(|e| WcError::invalid_argument(format!("cannot listen on {}: {}", address, e)))?;
+    eprintln!("rs-wc: serving counts on http://{}", listener.local_addr()?);
+    CountServer::new(roots, cli).run(listener)
+}
+
+#[cfg(test)]
+mod server_tests {
+    use super::*;
+    use crate::parser::{CharUnit, InvalidUtf8};
+    use std::{fs, io::Read, thread};
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rs-wc-server-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("src%2Fmain.rs").as_deref(), Some("src/main.rs"));
+        assert_eq!(percent_decode("a%20b").as_deref(), Some("a b"));
+        assert_eq!(percent_decode("%2"), None);
+        assert_eq!(percent_decode("%zz"), None);
+    }
+
+    #[test]
+    fn test_handle_routes() {
+        let dir = test_dir("routes");
+        let file = dir.join("a.txt");
+        fs::write(&file, "one two\n").unwrap();
+
+        let cli = Cli { lines: true, words: true, ..Cli::default() };
+        let server = CountServer::new(vec![dir.clone()], &cli);
+
+        let all = server.handle("/counts");
+        assert_eq!(all.status, 200);
+        assert!(all.body.contains("\"words\": 2"));
+
+        let one = server.handle(&format!("/counts/{}", file.display()));
+        assert_eq!(one.status, 200);
+
+        // Changes are picked up on the next request
+        fs::write(&file, "one two three four\nfive\n").unwrap();
+        let recounted = server.handle("/counts/");
+        assert!(recounted.body.contains("\"words\": 5"));
+
+        assert_eq!(server.handle("/counts/elsewhere.txt").status, 404);
+        assert_eq!(server.handle("/metrics").status, 404);
+
+        // One file that can't be counted doesn't fail the others
+        fs::write(dir.join("bad.txt"), [0xff, 0xfe]).unwrap();
+        let cli = Cli { chars: Some(CharUnit::Codepoints), invalid_utf8: Some(InvalidUtf8::Error), ..cli };
+        let server = CountServer::new(vec![dir.clone()], &cli);
+        let partial = server.handle("/counts");
+        assert_eq!(partial.status, 200);
+        assert!(partial.body.contains("\"error\""));
+        assert!(partial.body.contains("\"lines\": 2"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_handle_connection() {
+        let dir = test_dir("connection");
+        let file = dir.join("a.txt");
+        fs::write(&file, "hello\n").unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(b"GET /counts HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        });
+
+        let cli = Cli { lines: true, ..Cli::default() };
+        let server = CountServer::new(vec![file], &cli);
+        let (stream, _) = listener.accept().unwrap();
+        server.handle_connection(stream).unwrap();
+
+        let response = client.join().unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("\"lines\": 1"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -23,8 +23,321 @@ mod integration_tests {
     #[test]
     fn test_cli_json_output() -> WcResult<()> {
         let mut cmd = Command::cargo_bin("rs-wc")?;
-        let assert = cmd.args(&["-f", "json", "Cargo.toml"]).assert();
+        let assert = cmd.args(["-f", "json", "Cargo.toml"]).assert();
         assert.success().stdout(predicate::str::is_match(r#""filename": "Cargo.toml""#)?);
         Ok(())
     }
+
+    #[test]
+    fn test_cli_fail_if() -> WcResult<()> {
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        cmd.args(["-l", "--fail-if", "lines>1"]).write_stdin("a\nb\nc\n")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("lines is 3 (limit lines>1)"));
+
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        cmd.args(["-l", "--fail-if", "lines>10"]).write_stdin("a\nb\nc\n")
+            .assert()
+            .success();
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_check_line_endings() -> WcResult<()> {
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        cmd.args(["-l", "--check-line-endings", "lf"]).write_stdin("a\r\nb\n")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("expected LF line endings, found 1 LF, 1 CRLF"));
+
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        cmd.args(["-l", "--check-line-endings", "crlf"]).write_stdin("a\r\nb\r\n")
+            .assert()
+            .success();
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_scan_unicode() -> WcResult<()> {
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        cmd.args(["-l", "--scan-unicode"]).write_stdin("ok\nif admin\u{202E} {\n")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("-:2:9: U+202E RIGHT-TO-LEFT OVERRIDE"));
+
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        cmd.args(["-l", "--scan-unicode"]).write_stdin("plain text\n")
+            .assert()
+            .success();
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_count_matches() -> WcResult<()> {
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        cmd.args(["-l", "-f", "json", "--count-matches", "TODO|FIXME"])
+            .write_stdin("TODO a\nb\nTODO FIXME\n")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(r#""matches": 3"#))
+            .stdout(predicate::str::contains(r#""matching_lines": 2"#));
+
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        cmd.args(["--count-matches", "("])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("invalid pattern '('"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_continues_past_errors() -> WcResult<()> {
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        cmd.args(["-l", "missing.txt", "Cargo.toml", "README.md"])
+            .assert()
+            .failure()
+            .stdout(predicate::str::contains("Cargo.toml"))
+            .stdout(predicate::str::contains("total"))
+            .stderr(predicate::str::contains("rs-wc: File not found: missing.txt"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_fatal_error_message() -> WcResult<()> {
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        cmd.args(["--output", "/nonexistent/dir/out.txt", "Cargo.toml"])
+            .assert()
+            .code(1)
+            .stderr("rs-wc: Failed to create /nonexistent/dir/out.txt: No such file or directory (os error 2)\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_directory_argument() -> WcResult<()> {
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        cmd.args(["-l", "src", "Cargo.toml"])
+            .assert()
+            .failure()
+            .stdout(predicate::str::contains("Cargo.toml"))
+            .stderr(predicate::str::contains("rs-wc: Is a directory: src"));
+
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        cmd.args(["-l", "-r", "src"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("src/lib.rs"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_skipped_binary_notices() -> WcResult<()> {
+        let dir = std::env::temp_dir().join(format!("rs-wc-skipped-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("text.txt"), "hello\n")?;
+        std::fs::write(dir.join("data.bin"), b"\x00\x01\x02\x00")?;
+        let dir_arg = dir.to_str().unwrap();
+
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        let plain = cmd.args(["-r", "--text-only", dir_arg]).assert().success();
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        let quiet = cmd.args(["-r", "--text-only", "-q", dir_arg]).assert().success();
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        let json = cmd.args(["-r", "--text-only", "-f", "json", dir_arg]).assert().success();
+        std::fs::remove_dir_all(&dir)?;
+
+        plain.stderr(predicate::str::contains("data.bin: skipped"));
+        quiet.stderr("");
+        json.stderr("").stdout(predicate::str::contains(r#""skipped": "binary""#));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cli_walk_errors_are_per_file_failures() -> WcResult<()> {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir = std::env::temp_dir().join(format!("rs-wc-walk-errors-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("locked"))?;
+        std::fs::create_dir_all(dir.join("sub"))?;
+        std::fs::write(dir.join("a.txt"), "hello\n")?;
+        std::fs::write(dir.join("sub").join("b.txt"), "world\n")?;
+        std::fs::set_permissions(dir.join("locked"), std::fs::Permissions::from_mode(0o000))?;
+        // root can read the locked directory anyway; a dangling link that
+        // is followed can't be walked by anyone
+        symlink(dir.join("missing"), dir.join("sub").join("dangling"))?;
+        let locked = std::fs::read_dir(dir.join("locked")).is_err();
+        let dir_arg = dir.to_str().unwrap();
+
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        let walked = cmd.args(["-l", "-r", "--follow-symlinks", dir_arg]).assert().failure();
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        let streamed = cmd.args(["-l", "-r", "--follow-symlinks", "--stream", dir_arg]).assert().failure();
+        std::fs::set_permissions(dir.join("locked"), std::fs::Permissions::from_mode(0o755))?;
+        std::fs::remove_dir_all(&dir)?;
+
+        for output in [walked, streamed] {
+            let output = output
+                .stderr(predicate::str::contains("rs-wc: Directory traversal error:"))
+                .stderr(predicate::str::contains("dangling"))
+                .stdout(predicate::str::contains("a.txt"))
+                .stdout(predicate::str::contains("b.txt"))
+                .stdout(predicate::str::contains("2 total"));
+            if locked {
+                output.stderr(predicate::str::contains("locked"));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_compact_needs_json() -> WcResult<()> {
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        cmd.args(["--compact", "-f", "human", "Cargo.toml"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("--compact only applies to --format json"));
+
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        cmd.args(["--stream", "--compact", "-f", "json", "Cargo.toml", "README.md"])
+            .assert()
+            .success()
+            .stdout(predicate::str::is_match(r"^\{[^\n]*\}\n$")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_verbose() -> WcResult<()> {
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        cmd.args(["-v", "Cargo.toml"])
+            .assert()
+            .success()
+            // Built with io-uring, a small file's span also has io_uring=true
+            .stderr(predicate::str::is_match(r"file\{path=Cargo.toml( io_uring=true)?\}: close time.busy=")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_readability() -> WcResult<()> {
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        let assert = cmd.args(["readability", "-f", "json", "README.md"]).assert();
+        assert.success()
+            .stdout(predicate::str::contains(r#""flesch_reading_ease":"#))
+            .stdout(predicate::str::contains(r#""gunning_fog":"#));
+
+        // Words as the scores count them, without the number
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        cmd.args(["readability", "-f", "json", "-"])
+            .write_stdin("The 3 cats sat.\n")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(r#""words": 3"#))
+            .stdout(predicate::str::contains(r#""sentences": 1"#));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_lint() -> WcResult<()> {
+        let file = std::env::temp_dir().join(format!("rs-wc-lint-{}.txt", std::process::id()));
+        std::fs::write(&file, format!("short\n{}\nok\n{}\n", "x".repeat(30), "y".repeat(25)))?;
+        let file_arg = file.to_str().unwrap();
+
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        let long = cmd.args(["lint", "--limit", "20", file_arg]).assert().failure();
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        let short = cmd.args(["lint", "--limit", "30", file_arg]).assert().success();
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        let counted = cmd.args(["-L", "lint", "--limit", "30", file_arg]).assert().success();
+        std::fs::remove_file(&file)?;
+
+        long.stdout(format!("{}: 2 lines longer than 20: 2, 4\n", file_arg));
+        short.stdout("");
+        counted.stdout("");
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_lint_editorconfig() -> WcResult<()> {
+        let dir = std::env::temp_dir().join(format!("rs-wc-lint-editorconfig-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("good"))?;
+        std::fs::create_dir_all(dir.join("bad"))?;
+        std::fs::write(dir.join("good/.editorconfig"), "root = true\n[*]\nmax_line_length = 10\n")?;
+        std::fs::write(dir.join("good/a.txt"), format!("ok\n{}\n", "x".repeat(20)))?;
+        std::fs::write(dir.join("bad/.editorconfig"), b"\xff\xfe")?;
+        std::fs::write(dir.join("bad/b.txt"), format!("{}\n", "y".repeat(40)))?;
+        let good = dir.join("good/a.txt");
+        let bad = dir.join("bad/b.txt");
+        let (good, bad) = (good.to_str().unwrap(), bad.to_str().unwrap());
+
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        let lint = cmd.args(["lint", "--editorconfig", "--limit", "30", good, bad]).assert().failure();
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        let count = cmd.args(["-L", "--editorconfig", good, bad]).assert().success();
+        std::fs::remove_dir_all(&dir)?;
+
+        // The unreadable .editorconfig is reported, and its file still checked
+        lint.stdout(format!("{}: 1 line longer than 10: 2\n{}: 1 line longer than 30: 1\n", good, bad))
+            .stderr(predicate::str::contains(".editorconfig"));
+        count.stdout(predicate::str::contains("b.txt"))
+            .stderr(predicate::str::contains(".editorconfig"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_git_diff() -> WcResult<()> {
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        cmd.args(["git-diff", "-f", "json", "HEAD..HEAD"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(r#""results": []"#));
+
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        cmd.args(["git-diff", "no-such-rev..HEAD"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::is_match(r"\S")?);
+
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        cmd.args(["git-diff", "--", "--output=/dev/null"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::is_match(r"\S")?);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cli_daemon_removes_socket() -> WcResult<()> {
+        let socket = std::env::temp_dir().join(format!("rs-wc-daemon-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+
+        for _ in 0..2 {
+            let mut daemon = std::process::Command::new(assert_cmd::cargo::cargo_bin("rs-wc"))
+                .args(["daemon", "--socket"])
+                .arg(&socket)
+                .stderr(std::process::Stdio::null())
+                .spawn()?;
+            for _ in 0..100 {
+                if socket.exists() {
+                    break;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(20));
+            }
+            assert!(socket.exists(), "the daemon never bound its socket");
+
+            std::process::Command::new("kill").arg(daemon.id().to_string()).status()?;
+            daemon.wait()?;
+            // A second start binds the same path again
+            assert!(!socket.exists());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_diff() -> WcResult<()> {
+        let mut cmd = Command::cargo_bin("rs-wc")?;
+        let assert = cmd.args(["diff", "-l", "-f", "json", "Cargo.toml", "Cargo.toml"]).assert();
+        assert.success().stdout(predicate::str::contains(r#""delta": 0"#));
+        Ok(())
+    }
 }
\ No newline at end of file